@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::sync::Arc;
 
@@ -14,26 +15,36 @@ use frontend::classes::ClassGraph;
 use frontend::loader::Class;
 use frontend::translate::VarId;
 
-use crate::layout::{FieldLayoutMap, VTableMap};
-use crate::mangle;
+use crate::layout::{ClassIdMap, FieldLayoutMap, VTableMap};
+use crate::mangle::{self, MangleScheme};
 
 mod common;
+mod debug;
 mod decls;
 mod parts;
+mod symbols;
 
 use self::common::*;
+use self::debug::DebugInfo;
 use self::decls::DeclDatabase;
-use self::parts::{MethodCodeGen, PreludeCodeGen};
+use self::parts::{gen_module_header, gen_runtime_decls, MethodCodeGen, PreludeCodeGen};
 
 pub struct Target {
     pub triple: String,
     pub data_layout: String,
+    pub mangle_scheme: MangleScheme,
+    pub debug_info: bool,
+    /// Calls `_Jrt_array_store_check` before storing into a reference array.
+    /// The runtime accepts every value until arrays record their component
+    /// class, so the driver leaves this off.
+    pub array_store_checks: bool,
 }
 
 pub struct CodeGen {
     classes: ClassGraph,
     vtables: VTableMap,
     field_layouts: FieldLayoutMap,
+    class_ids: Arc<ClassIdMap>,
     target: Arc<Target>,
 }
 
@@ -45,32 +56,137 @@ impl CodeGen {
             classes,
             vtables,
             field_layouts,
+            class_ids: Arc::new(ClassIdMap::default()),
             target: Arc::new(target),
         })
     }
 
+    /// Assigns class ids to the given classes and their superclasses, which
+    /// are emitted along with the classes for the runtime's type checks.
+    ///
+    /// All classes of a program have to be numbered at once, before any of
+    /// them is generated.
+    pub fn assign_class_ids(&mut self, names: &[StrBuf]) -> Fallible<()> {
+        self.class_ids = Arc::new(ClassIdMap::build(&self.classes, names)?);
+        Ok(())
+    }
+
+    /// Evicts a class and everything derived from it, so that it is loaded
+    /// again the next time it is needed.
+    pub fn invalidate(&self, name: &StrBuf) {
+        for evicted in self.classes.invalidate(name) {
+            self.vtables.invalidate(&evicted);
+            self.field_layouts.invalidate(&evicted);
+        }
+    }
+
     pub fn generate_class(&self, name: &StrBuf) -> Fallible<ClassCodeGen> {
         let class = match self.classes.get(name)? {
             Class::File(class_file) => class_file,
             _ => bail!("can't generate code for array class"),
         };
-        let _class_name = class
+        let class_name = class
             .constant_pool
             .get_utf8(class.get_this_class().name_index)
             .unwrap();
-        let _source_file = class.attributes.get::<SourceFile>()?;
+        let source_file = class.attributes.get::<SourceFile>()?;
+        let debug = if self.target.debug_info {
+            Some(DebugInfo::new(class_name, source_file.as_str()))
+        } else {
+            None
+        };
 
         Ok(ClassCodeGen {
             out: String::new(),
-            decls: DeclDatabase::new(&self.classes, &self.vtables, &self.field_layouts),
+            decls: DeclDatabase::new(
+                &self.classes,
+                &self.vtables,
+                &self.field_layouts,
+                &self.target,
+            ),
             class: class.clone(),
             classes: self.classes.clone(),
             vtables: self.vtables.clone(),
             field_layouts: self.field_layouts.clone(),
+            class_ids: self.class_ids.clone(),
             var_id_gen: TmpVarIdGen::new(),
             target: self.target.clone(),
+            debug,
+            defined: HashSet::new(),
+            class_init: None,
         })
     }
+
+    /// Lists the symbols defined for the given classes, in the format
+    /// described in the `symbols` module.
+    pub fn gen_symbols(&self, names: &[StrBuf]) -> Fallible<String> {
+        let mut out = String::new();
+        symbols::gen_symbols(
+            &mut out,
+            &self.classes,
+            &self.class_ids,
+            self.target.mangle_scheme,
+            names,
+        )?;
+        Ok(out)
+    }
+
+    /// Starts a module that the code of several classes can be merged into,
+    /// instead of emitting one module per class.
+    pub fn generate_module(&self, name: &str) -> Fallible<ModuleCodeGen> {
+        if self.target.debug_info {
+            bail!("debug info is not supported for merged modules");
+        }
+        Ok(ModuleCodeGen {
+            name: name.to_owned(),
+            decls: DeclDatabase::new(
+                &self.classes,
+                &self.vtables,
+                &self.field_layouts,
+                &self.target,
+            ),
+            classes: vec![],
+            target: self.target.clone(),
+        })
+    }
+}
+
+pub struct ModuleCodeGen {
+    name: String,
+    decls: DeclDatabase,
+    classes: Vec<ClassCodeGen>,
+    target: Arc<Target>,
+}
+
+impl ModuleCodeGen {
+    pub fn add_class(&mut self, class: ClassCodeGen) {
+        self.classes.push(class);
+    }
+
+    pub fn finish(mut self) -> Fallible<String> {
+        let mut out = String::new();
+        gen_module_header(&mut out, &self.name, None, &self.target)?;
+        gen_runtime_decls(&mut out)?;
+
+        let mut defined = HashSet::new();
+        let mut class_inits = vec![];
+        for class in self.classes.iter_mut() {
+            class.gen_string_consts(&mut out)?;
+            self.decls.merge(&class.decls);
+            defined.extend(class.defined.iter().cloned());
+            class_inits.extend(class.class_init.iter().cloned());
+        }
+        self.decls.remove_defined(&defined);
+        for entry in self.decls.entries() {
+            writeln!(out, "{}", entry)?;
+        }
+
+        for class in self.classes.iter() {
+            out.push_str(&class.out);
+        }
+        gen_global_ctors(&mut out, &class_inits)?;
+        Ok(out)
+    }
 }
 
 pub struct ClassCodeGen {
@@ -80,8 +196,12 @@ pub struct ClassCodeGen {
     classes: ClassGraph,
     vtables: VTableMap,
     field_layouts: FieldLayoutMap,
+    class_ids: Arc<ClassIdMap>,
     var_id_gen: TmpVarIdGen,
     target: Arc<Target>,
+    debug: Option<DebugInfo>,
+    defined: HashSet<String>,
+    class_init: Option<String>,
 }
 
 impl ClassCodeGen {
@@ -92,6 +212,11 @@ impl ClassCodeGen {
             writeln!(out, "{}", entry)?;
         }
         out.push_str(&self.out);
+        let class_inits: Vec<String> = self.class_init.iter().cloned().collect();
+        gen_global_ctors(&mut out, &class_inits)?;
+        if let Some(ref debug) = self.debug {
+            debug.gen_metadata(&mut out)?;
+        }
         Ok(out)
     }
 
@@ -106,12 +231,13 @@ impl ClassCodeGen {
             self.out,
             "  %code = call i32 @_Jrt_start(i32 %argc, i8** %argv, void (%ref) * @{})",
             mangle::mangle_method_name(
+                self.target.mangle_scheme,
                 class_name,
                 "main",
                 &ReturnTypeDescriptor::Void,
                 &[ParameterDescriptor::Field(FieldType::Array(ArrayType {
                     component_type: Box::new(FieldType::Object(ObjectType {
-                        class_name: "java.lang.String".to_owned()
+                        class_name: "java/lang/String".to_owned()
                     }))
                 }))]
             )
@@ -121,11 +247,15 @@ impl ClassCodeGen {
         Ok(())
     }
 
+    /// Defines the vtable of the class being compiled. Only the owning
+    /// module emits a definition; every other module references it through
+    /// an `external` declaration, so the symbol is never duplicated.
     pub fn gen_vtable_const(&mut self, class_file: &ClassFile) -> Fallible<()> {
         let class_name = class_file.get_name();
         let vtable = self.vtables.get(class_name)?;
-        let vtable_name = mangle::mangle_vtable_name(class_name);
+        let vtable_name = mangle::mangle_vtable_name(self.target.mangle_scheme, class_name);
         let vtable_type = self.decls.add_vtable_type(class_name)?;
+        self.defined.insert(vtable_name.clone());
 
         writeln!(
             self.out,
@@ -153,6 +283,7 @@ impl ClassCodeGen {
                 "  {} * @{},",
                 GenFunctionType(&key.method_descriptor),
                 mangle::mangle_method_name(
+                    self.target.mangle_scheme,
                     &target.class_name,
                     &key.method_name,
                     &key.method_descriptor.ret,
@@ -175,10 +306,8 @@ impl ClassCodeGen {
                 vtbl = interface_vtable_const
             )?;
             write!(self.out, "  i32 {}", offset)?;
-            if idx < vtable.interface_count() - 1 {
+            if idx + 1 < vtable.interface_count() {
                 write!(self.out, ",")?;
-            } else {
-                write!(self.out, "")?;
             }
             writeln!(self.out, " ; #{} interface {}", idx, name)?;
         }
@@ -187,6 +316,24 @@ impl ClassCodeGen {
         Ok(())
     }
 
+    /// Defines the range of class ids of the class being compiled, for
+    /// classes that have been assigned an id.
+    pub fn gen_class_id_const(&mut self, class_file: &ClassFile) -> Fallible<()> {
+        let class_name = class_file.get_name();
+        let range = match self.class_ids.get(class_name) {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+        let class_id_name = mangle::mangle_class_id_name(self.target.mangle_scheme, class_name);
+        writeln!(
+            self.out,
+            "@{} = constant {{ i32, i32 }} {{ i32 {}, i32 {} }} ; <class id, last subclass id>",
+            class_id_name, range.id, range.last_subclass_id
+        )?;
+        self.defined.insert(class_id_name);
+        Ok(())
+    }
+
     fn gen_prelude(&mut self, out: &mut String) -> Fallible<()> {
         let mut prelude_code_gen = PreludeCodeGen {
             out,
@@ -201,12 +348,44 @@ impl ClassCodeGen {
         prelude_code_gen.gen_prelude()
     }
 
+    fn gen_string_consts(&mut self, out: &mut String) -> Fallible<()> {
+        let mut prelude_code_gen = PreludeCodeGen {
+            out,
+            decls: &mut self.decls,
+            class: &self.class,
+            classes: &self.classes,
+            vtables: &self.vtables,
+            field_layouts: &self.field_layouts,
+            var_id_gen: &mut self.var_id_gen,
+            target: &self.target,
+        };
+        prelude_code_gen.gen_string_consts()
+    }
+
+    fn gen_method_name(&self, method: &Method, consts: &ConstantPool) -> String {
+        let class_name = consts
+            .get_utf8(self.class.get_this_class().name_index)
+            .unwrap();
+        let method_name = consts.get_utf8(method.name_index).unwrap();
+        mangle::mangle_method_name(
+            self.target.mangle_scheme,
+            class_name,
+            method_name,
+            &method.descriptor.ret,
+            &method.descriptor.params,
+        )
+    }
+
     pub fn gen_method(
         &mut self,
         method: &Method,
         blocks: &BlockGraph,
         consts: &ConstantPool,
     ) -> Fallible<()> {
+        self.defined.insert(self.gen_method_name(method, consts));
+        if method.is_static() && method.is_synchronized() {
+            self.gen_class_monitor()?;
+        }
         let mut method_code_gen = MethodCodeGen {
             out: &mut self.out,
             decls: &mut self.decls,
@@ -216,32 +395,37 @@ impl ClassCodeGen {
             field_layouts: &self.field_layouts,
             var_id_gen: &mut self.var_id_gen,
             target: &self.target,
+            debug: self.debug.as_mut(),
         };
         method_code_gen.gen_method(method, blocks, consts)
     }
 
+    /// Defines the slot of the monitor that static synchronized methods lock,
+    /// unless an earlier method of the class has defined it already.
+    fn gen_class_monitor(&mut self) -> Fallible<()> {
+        let monitor_name =
+            mangle::mangle_class_monitor_name(self.target.mangle_scheme, self.class.get_name());
+        if self.defined.insert(monitor_name.clone()) {
+            writeln!(self.out, "\n@{} = internal global i8* null", monitor_name)?;
+        }
+        Ok(())
+    }
+
     pub fn gen_native_method(
         &mut self,
         method: &Method,
         args: &[VarId],
         consts: &ConstantPool,
     ) -> Fallible<()> {
-        let class_name = consts
-            .get_utf8(self.class.get_this_class().name_index)
-            .unwrap();
-        let method_name = consts.get_utf8(method.name_index).unwrap();
+        let mangled_name = self.gen_method_name(method, consts);
         write!(
             self.out,
             "\ndeclare {return_type} @{mangled_name}({args})",
             return_type = tlt_return_type(&method.descriptor.ret),
-            mangled_name = mangle::mangle_method_name(
-                class_name,
-                method_name,
-                &method.descriptor.ret,
-                &method.descriptor.params
-            ),
+            mangled_name = mangled_name,
             args = args.iter().gen_comma_sep(|arg| tlt_type(&arg.0))
         )?;
+        self.defined.insert(mangled_name);
         Ok(())
     }
 
@@ -251,46 +435,69 @@ impl ClassCodeGen {
         args: &[VarId],
         consts: &ConstantPool,
     ) -> Fallible<()> {
-        let class_name = consts
-            .get_utf8(self.class.get_this_class().name_index)
-            .unwrap();
-        let method_name = consts.get_utf8(method.name_index).unwrap();
+        let mangled_name = self.gen_method_name(method, consts);
         write!(
             self.out,
             "\ndefine {return_type} @{mangled_name}({args}) {{",
             return_type = tlt_return_type(&method.descriptor.ret),
-            mangled_name = mangle::mangle_method_name(
-                class_name,
-                method_name,
-                &method.descriptor.ret,
-                &method.descriptor.params
-            ),
+            mangled_name = mangled_name,
             args = args.iter().gen_comma_sep(|arg| tlt_type(&arg.0))
         )?;
+        self.defined.insert(mangled_name);
         writeln!(self.out, "  call void @_Jrt_abstract() noreturn")?;
         writeln!(self.out, "  unreachable")?;
         writeln!(self.out, "}}")?;
         Ok(())
     }
 
+    pub fn gen_static_fields(&mut self, class_file: &ClassFile) -> Fallible<()> {
+        for field in class_file.fields.iter().filter(|field| field.is_static()) {
+            let field_name = class_file.constant_pool.get_utf8(field.name_index).unwrap();
+            let mangled_name = mangle::mangle_field_name(
+                self.target.mangle_scheme,
+                class_file.get_name(),
+                field_name,
+            );
+            writeln!(
+                self.out,
+                "@{} = global {} zeroinitializer",
+                mangled_name,
+                tlt_field_type(&field.descriptor)
+            )?;
+            self.defined.insert(mangled_name);
+        }
+        Ok(())
+    }
+
     pub fn gen_class_init(&mut self) -> Fallible<()> {
-        let mangled_name = mangle::mangle_method_name(
+        self.class_init = Some(mangle::mangle_method_name(
+            self.target.mangle_scheme,
             self.class.get_name(),
             "<clinit>",
             &ReturnTypeDescriptor::Void,
             &[],
-        );
-        writeln!(
-            self.out,
-            "@llvm.global_ctors = appending global [1 x {{ i32, void ()*, i8* }}] ["
-        )?;
-        writeln!(self.out, "  {{ i32, void ()*, i8* }}")?;
-        writeln!(
-            self.out,
-            "  {{ i32 65535, void ()* @{}, i8* null }}",
-            mangled_name
-        )?;
-        writeln!(self.out, "]")?;
+        ));
         Ok(())
     }
 }
+
+fn gen_global_ctors(out: &mut String, ctors: &[String]) -> Fallible<()> {
+    if ctors.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "@llvm.global_ctors = appending global [{} x {{ i32, void ()*, i8* }}] [",
+        ctors.len()
+    )?;
+    for (idx, ctor) in ctors.iter().enumerate() {
+        writeln!(out, "  {{ i32, void ()*, i8* }}")?;
+        write!(out, "  {{ i32 65535, void ()* @{}, i8* null }}", ctor)?;
+        if idx + 1 < ctors.len() {
+            write!(out, ",")?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "]")?;
+    Ok(())
+}
@@ -69,6 +69,10 @@ impl<'a> fmt::Display for OpVal<'a> {
     }
 }
 
+/// The size of a type in bytes, as a constant expression.
+///
+/// The size is left for LLVM to compute from the module's data layout, so
+/// pointer-sized fields (such as `%ref`) come out right on 32-bit targets.
 pub struct GenSizeOf<T: fmt::Display>(pub T);
 
 impl<T: fmt::Display> fmt::Display for GenSizeOf<T> {
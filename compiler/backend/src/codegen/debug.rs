@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+
+use classfile::attrs::LineNumberTable;
+
+pub struct DebugInfo {
+    nodes: Vec<String>,
+    locations: HashMap<(u32, usize), usize>,
+    file: usize,
+    unit: usize,
+    subroutine_type: usize,
+}
+
+impl DebugInfo {
+    pub fn new(class_name: &str, source_file: &str) -> Self {
+        let directory = match class_name.rfind('/') {
+            Some(idx) => &class_name[..idx],
+            None => "",
+        };
+        let mut debug_info = DebugInfo {
+            nodes: vec![],
+            locations: HashMap::new(),
+            file: 0,
+            unit: 0,
+            subroutine_type: 0,
+        };
+        debug_info.file = debug_info.add_node(format!(
+            "!DIFile(filename: \"{}\", directory: \"{}\")",
+            source_file, directory
+        ));
+        debug_info.unit = debug_info.add_node(format!(
+            "distinct !DICompileUnit(language: DW_LANG_Java, file: !{}, producer: \"rustretto\", \
+             isOptimized: false, runtimeVersion: 0, emissionKind: FullDebug)",
+            debug_info.file
+        ));
+        debug_info.subroutine_type =
+            debug_info.add_node("!DISubroutineType(types: !{})".to_owned());
+        debug_info
+    }
+
+    fn add_node(&mut self, node: String) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    pub fn add_subprogram(&mut self, name: &str, linkage_name: &str, line: u32) -> usize {
+        let node = format!(
+            "distinct !DISubprogram(name: \"{}\", linkageName: \"{}\", scope: !{file}, \
+             file: !{file}, line: {line}, type: !{}, isLocal: false, isDefinition: true, \
+             scopeLine: {line}, isOptimized: false, unit: !{})",
+            name,
+            linkage_name,
+            self.subroutine_type,
+            self.unit,
+            file = self.file,
+            line = line
+        );
+        self.add_node(node)
+    }
+
+    pub fn add_location(&mut self, line: u32, scope: usize) -> usize {
+        if let Some(id) = self.locations.get(&(line, scope)) {
+            return *id;
+        }
+        let id = self.add_node(format!("!DILocation(line: {}, scope: !{})", line, scope));
+        self.locations.insert((line, scope), id);
+        id
+    }
+
+    pub fn gen_metadata(&self, out: &mut String) -> fmt::Result {
+        let flags = self.nodes.len();
+        writeln!(out)?;
+        writeln!(out, "!llvm.dbg.cu = !{{!{}}}", self.unit)?;
+        writeln!(out, "!llvm.module.flags = !{{!{}, !{}}}", flags, flags + 1)?;
+        for (id, node) in self.nodes.iter().enumerate() {
+            writeln!(out, "!{} = {}", id, node)?;
+        }
+        writeln!(out, "!{} = !{{i32 2, !\"Dwarf Version\", i32 4}}", flags)?;
+        writeln!(
+            out,
+            "!{} = !{{i32 2, !\"Debug Info Version\", i32 3}}",
+            flags + 1
+        )?;
+        Ok(())
+    }
+}
+
+pub struct DebugScope {
+    pub subprogram: usize,
+    pub lines: LineNumbers,
+}
+
+/// Maps bytecode offsets of a method to source lines.
+pub struct LineNumbers(BTreeMap<u32, u32>);
+
+impl LineNumbers {
+    pub fn new(table: Option<LineNumberTable>) -> Self {
+        let lines = table
+            .iter()
+            .flat_map(|table| table.entries.iter())
+            .map(|entry| (u32::from(entry.start_pc), u32::from(entry.line_number)))
+            .collect();
+        LineNumbers(lines)
+    }
+
+    pub fn first(&self) -> u32 {
+        self.0.values().min().cloned().unwrap_or(0)
+    }
+
+    pub fn line_at(&self, pc: u32) -> u32 {
+        match self.0.range(..=pc).next_back() {
+            Some((_, line)) => *line,
+            None => self.first(),
+        }
+    }
+}
+
+/// Ends an instruction, attaching a debug location to it if there is one.
+pub fn end_instr(out: &mut String, location: Option<usize>) -> fmt::Result {
+    if let Some(location) = location {
+        write!(out, ", !dbg !{}", location)?;
+    }
+    writeln!(out)
+}
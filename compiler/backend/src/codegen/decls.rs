@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fmt::{self, Write};
 use std::sync::Arc;
 
@@ -12,6 +13,7 @@ use frontend::loader::{ArrayClass, Class};
 use frontend::types::Type;
 
 use crate::codegen::common::*;
+use crate::codegen::Target;
 use crate::layout::{FieldLayoutMap, VTableMap};
 use crate::mangle;
 
@@ -67,15 +69,22 @@ pub struct DeclDatabase {
     classes: ClassGraph,
     vtables: VTableMap,
     field_layouts: FieldLayoutMap,
+    target: Arc<Target>,
     decls: IndexMap<DeclKey, DeclEntry, FnvBuildHasher>,
 }
 
 impl DeclDatabase {
-    pub fn new(classes: &ClassGraph, vtables: &VTableMap, field_layouts: &FieldLayoutMap) -> Self {
+    pub fn new(
+        classes: &ClassGraph,
+        vtables: &VTableMap,
+        field_layouts: &FieldLayoutMap,
+        target: &Arc<Target>,
+    ) -> Self {
         Self {
             classes: classes.clone(),
             vtables: vtables.clone(),
             field_layouts: field_layouts.clone(),
+            target: target.clone(),
             decls: IndexMap::default(),
         }
     }
@@ -84,6 +93,21 @@ impl DeclDatabase {
         self.decls.values().map(|entry| &*entry.declaration)
     }
 
+    /// Adds all declarations of `other` that are not yet part of this database.
+    pub fn merge(&mut self, other: &DeclDatabase) {
+        for (key, entry) in other.decls.iter() {
+            if !self.decls.contains_key(key) {
+                self.decls.insert(key.clone(), entry.clone());
+            }
+        }
+    }
+
+    /// Removes the declarations of globals that are defined within the module.
+    pub fn remove_defined(&mut self, defined: &HashSet<String>) {
+        self.decls
+            .retain(|_, entry| !(entry.global && defined.contains(&*entry.identifier)));
+    }
+
     pub fn add_object_type(&mut self, class_name: &StrBuf) -> Fallible<DeclIdentifier> {
         self.add(DeclKey::ObjectType {
             class_name: class_name.clone(),
@@ -96,6 +120,7 @@ impl DeclDatabase {
         })
     }
 
+    /// Declares the vtable of a class owned by another module.
     pub fn add_vtable_const(&mut self, class_name: &StrBuf) -> Fallible<DeclIdentifier> {
         let vtable_type = self.add_vtable_type(class_name)?;
         self.add(DeclKey::VTableConst {
@@ -157,6 +182,7 @@ impl DeclDatabase {
             classes: &self.classes,
             vtables: &self.vtables,
             field_layouts: &self.field_layouts,
+            target: &self.target,
         };
         let identifier = match key {
             DeclKey::ObjectType { ref class_name } => gen.gen_object_type(class_name)?,
@@ -194,6 +220,7 @@ struct DeclGen<'a> {
     classes: &'a ClassGraph,
     vtables: &'a VTableMap,
     field_layouts: &'a FieldLayoutMap,
+    target: &'a Target,
 }
 
 impl<'a> DeclGen<'a> {
@@ -203,7 +230,8 @@ impl<'a> DeclGen<'a> {
         field_name: &StrBuf,
         field_type: &FieldType,
     ) -> Fallible<DeclIdentifier> {
-        let mangled_name = mangle::mangle_field_name(class_name, field_name);
+        let mangled_name =
+            mangle::mangle_field_name(self.target.mangle_scheme, class_name, field_name);
         writeln!(
             self.out,
             "@{field_name} = external global {field_type}",
@@ -224,6 +252,7 @@ impl<'a> DeclGen<'a> {
         is_static: bool,
     ) -> Fallible<DeclIdentifier> {
         let mangled_name = mangle::mangle_method_name(
+            self.target.mangle_scheme,
             class_name,
             method_name,
             &method_descriptor.ret,
@@ -263,7 +292,7 @@ impl<'a> DeclGen<'a> {
         class_name: &StrBuf,
         vtable_type: &DeclIdentifier,
     ) -> Fallible<DeclIdentifier> {
-        let vtable_name = mangle::mangle_vtable_name(class_name);
+        let vtable_name = mangle::mangle_vtable_name(self.target.mangle_scheme, class_name);
         writeln!(
             self.out,
             "@{vtbl} = external constant {vtyp}",
@@ -282,7 +311,7 @@ impl<'a> DeclGen<'a> {
             Class::Array(_) => bail!("can't generate vtable for array"),
         };
         let vtable = self.vtables.get(class_name)?;
-        let vtable_name = mangle::mangle_vtable_name(class_name);
+        let vtable_name = mangle::mangle_vtable_name(self.target.mangle_scheme, class_name);
         writeln!(self.out, "%{} = type {{", vtable_name)?;
         if !class_file.is_interface() {
             writeln!(self.out, "  i32, ; <number of methods>")?;
@@ -304,10 +333,8 @@ impl<'a> DeclGen<'a> {
         for (idx, (name, _)) in vtable.iter_interfaces().enumerate() {
             writeln!(self.out, "  i8*,")?;
             write!(self.out, "  i32")?;
-            if idx < vtable.interface_count() - 1 {
+            if idx + 1 < vtable.interface_count() {
                 write!(self.out, ",")?;
-            } else {
-                write!(self.out, "")?;
             }
             writeln!(self.out, " ; #{} interface {}", idx, name)?;
         }
@@ -321,7 +348,9 @@ impl<'a> DeclGen<'a> {
 
     fn gen_object_type(&mut self, class_name: &StrBuf) -> Fallible<DeclIdentifier> {
         match self.classes.get(class_name)? {
-            Class::File(_) => self.gen_object_struct_type(class_name),
+            Class::File(class_file) => {
+                self.gen_object_struct_type(class_name, class_file.is_interface())
+            }
             Class::Array(array_class) => self.gen_object_array_type(class_name, &array_class),
         }
     }
@@ -331,7 +360,7 @@ impl<'a> DeclGen<'a> {
         class_name: &StrBuf,
         array_class: &ArrayClass,
     ) -> Fallible<DeclIdentifier> {
-        let object_type_name = mangle::mangle_class_name(class_name);
+        let object_type_name = mangle::mangle_class_name(self.target.mangle_scheme, class_name);
         writeln!(self.out, "%{} = type {{", object_type_name)?;
         writeln!(self.out, "  i32, ; length")?;
         writeln!(
@@ -346,17 +375,27 @@ impl<'a> DeclGen<'a> {
         })
     }
 
-    fn gen_object_struct_type(&mut self, class_name: &StrBuf) -> Fallible<DeclIdentifier> {
+    fn gen_object_struct_type(
+        &mut self,
+        class_name: &StrBuf,
+        is_interface: bool,
+    ) -> Fallible<DeclIdentifier> {
+        let object_type_name = mangle::mangle_class_name(self.target.mangle_scheme, class_name);
+        if is_interface {
+            // interfaces are never instantiated, so their layout is left opaque
+            writeln!(self.out, "%{} = type opaque", object_type_name)?;
+            return Ok(DeclIdentifier {
+                global: false,
+                identifier: Arc::new(object_type_name),
+            });
+        }
         let field_layout = self.field_layouts.get(class_name)?;
-        let object_type_name = mangle::mangle_class_name(class_name);
         writeln!(self.out, "%{} = type {{", object_type_name)?;
         for (idx, key) in field_layout.iter().enumerate() {
             let ftyp = tlt_field_type(&key.field_type);
             write!(self.out, "  {}", ftyp)?;
-            if idx < field_layout.len() - 1 {
+            if idx + 1 < field_layout.len() {
                 write!(self.out, ",")?;
-            } else {
-                write!(self.out, "")?;
             }
             writeln!(self.out, " ; {}", key.field_name)?;
         }
@@ -367,3 +406,117 @@ impl<'a> DeclGen<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mangle::MangleScheme;
+    use frontend::test_util::{
+        TestClass, TestClassLoader, ACC_ABSTRACT, ACC_INTERFACE, ACC_PUBLIC,
+    };
+
+    fn readable_target() -> Arc<Target> {
+        Arc::new(Target {
+            triple: "x86_64-unknown-linux-gnu".to_owned(),
+            data_layout: String::new(),
+            mangle_scheme: MangleScheme::Readable,
+            debug_info: false,
+            array_store_checks: false,
+        })
+    }
+
+    #[test]
+    fn interface_types_have_no_fields() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                fields: vec![("monitor", "J")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Runnable",
+                super_name: Some("java/lang/Object"),
+                access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+                methods: vec![("run", "()V")],
+                ..TestClass::default()
+            },
+        ]));
+        let target = readable_target();
+        let field_layouts = FieldLayoutMap::new(classes.clone());
+        let mut decls = DeclDatabase::new(
+            &classes,
+            &VTableMap::new(classes.clone()),
+            &field_layouts,
+            &target,
+        );
+        let runnable = StrBuf::new("Runnable");
+
+        decls.add_object_type(&runnable).unwrap();
+        decls.add_vtable_type(&runnable).unwrap();
+
+        assert_eq!(0, field_layouts.get(&runnable).unwrap().len());
+        let entries = decls.entries().collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "%Runnable = type opaque\n",
+                "%Runnable-vtable = type {\n  void (%ref) *, ; #0 method run\n  i32 ; <number of interfaces>\n}\n",
+            ],
+            entries
+        );
+    }
+
+    #[test]
+    fn vtable_types_with_few_entries() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Marker",
+                super_name: Some("java/lang/Object"),
+                access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Runnable",
+                super_name: Some("java/lang/Object"),
+                access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+                methods: vec![("run", "()V")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Task",
+                super_name: Some("java/lang/Object"),
+                interfaces: vec!["Runnable"],
+                methods: vec![("run", "()V")],
+                ..TestClass::default()
+            },
+        ]));
+        let target = readable_target();
+        let field_layouts = FieldLayoutMap::new(classes.clone());
+        let mut decls = DeclDatabase::new(
+            &classes,
+            &VTableMap::new(classes.clone()),
+            &field_layouts,
+            &target,
+        );
+
+        decls
+            .add_vtable_type(&StrBuf::new("java/lang/Object"))
+            .unwrap();
+        decls.add_vtable_type(&StrBuf::new("Marker")).unwrap();
+        decls.add_vtable_type(&StrBuf::new("Task")).unwrap();
+
+        let entries = decls.entries().collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "%java.lang.Object-vtable = type {\n  i32, ; <number of methods>\n  i32 ; <number of interfaces>\n}\n",
+                "%Marker-vtable = type {\n  i32 ; <number of interfaces>\n}\n",
+                "%Task-vtable = type {\n  i32, ; <number of methods>\n  void (%ref) *, ; #0 method run\n  i32, ; <number of interfaces>\n  i8*,\n  i32 ; #0 interface Runnable\n}\n",
+            ],
+            entries
+        );
+    }
+}
@@ -3,4 +3,4 @@ mod method;
 mod prelude;
 
 pub use self::method::MethodCodeGen;
-pub use self::prelude::PreludeCodeGen;
+pub use self::prelude::{gen_module_header, gen_runtime_decls, PreludeCodeGen};
@@ -1,8 +1,9 @@
-use std::fmt::Write;
+use std::fmt::{self, Write};
 use std::sync::Arc;
 
+use classfile::descriptors::ParameterDescriptor;
 use classfile::{ClassFile, ConstantIndex, ConstantPool, FieldRef};
-use failure::Fallible;
+use failure::{ensure, format_err, Fallible};
 use strbuf::StrBuf;
 
 use frontend::classes::ClassGraph;
@@ -13,6 +14,7 @@ use frontend::translate::{
 use frontend::types::Type;
 
 use crate::codegen::common::*;
+use crate::codegen::debug;
 use crate::codegen::decls::DeclDatabase;
 use crate::codegen::Target;
 use crate::layout::{FieldLayoutMap, VTableMap};
@@ -27,6 +29,7 @@ pub struct ExprCodeGen<'a> {
     pub field_layouts: &'a FieldLayoutMap,
     pub var_id_gen: &'a mut TmpVarIdGen,
     pub target: &'a Arc<Target>,
+    pub location: Option<usize>,
 }
 
 impl<'a> ExprCodeGen<'a> {
@@ -34,6 +37,7 @@ impl<'a> ExprCodeGen<'a> {
         match expr {
             Expr::String(index) => self.gen_load_string(*index, consts, dest)?,
             Expr::GetStatic(index) => self.gen_expr_get_static(*index, consts, dest)?,
+            Expr::PutStatic(index, value) => self.gen_expr_put_static(*index, value, consts)?,
             Expr::GetField(obj, index) => self.gen_expr_get_field(obj, *index, consts, dest)?,
             Expr::PutField(obj, index, value) => {
                 self.gen_expr_put_field(obj, *index, value, consts)?
@@ -60,7 +64,7 @@ impl<'a> ExprCodeGen<'a> {
         let vtable_const = self.decls.add_vtable_const(class_name)?;
 
         if let Dest::Assign(assign) = dest {
-            writeln!(
+            write!(
                 self.out,
                 "  {} = call %ref @_Jrt_object_new(i64 {size}, i8* bitcast ({vtyp}* {vtbl} to i8*))",
                 assign,
@@ -68,6 +72,7 @@ impl<'a> ExprCodeGen<'a> {
                 vtyp = vtable_type,
                 vtbl = vtable_const
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
@@ -80,14 +85,19 @@ impl<'a> ExprCodeGen<'a> {
     ) -> Fallible<()> {
         let len = consts.get_utf8(index).unwrap().len();
         if let Dest::Assign(assign) = dest {
-            writeln!(
+            write!(
                 self.out,
-                "  {} = call %ref @_Jrt_ldstr(i8* getelementptr ([{} x i8], [{} x i8]* @.str{}, i64 0, i64 0))",
+                "  {} = call %ref @_Jrt_ldstr(i8* getelementptr ([{} x i8], [{} x i8]* @{}, i64 0, i64 0))",
                 assign,
                 len + 1,
                 len + 1,
-                index.into_u16()
+                mangle::mangle_string_const_name(
+                    self.target.mangle_scheme,
+                    self.class.get_name(),
+                    index.into_u16()
+                )
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
@@ -104,36 +114,44 @@ impl<'a> ExprCodeGen<'a> {
 
         let fptr = match expr.target {
             InvokeTarget::Virtual(ref var) => {
-                let vtable = self.vtables.get(method_class_name)?;
-                let target = vtable.get(method_name, &expr.method.descriptor).unwrap();
+                let target = self.vtables.get_target(
+                    method_class_name,
+                    method_name,
+                    &expr.method.descriptor,
+                )?;
 
                 let tmp_fptr = self.var_id_gen.gen();
-                writeln!(
+                write!(
                     self.out,
                     "  %t{fptr} = call i8* @_Jrt_object_vtable_lookup(%ref {object}, i64 {index})",
                     fptr = tmp_fptr,
                     object = OpVal(var),
                     index = target.method_index_lower
                 )?;
+                self.end_instr()?;
                 let tmp_fptr_cast = self.var_id_gen.gen();
-                writeln!(
+                write!(
                     self.out,
                     "  %t{fptr_cast} = bitcast i8* %t{fptr} to {ftyp}*",
                     fptr_cast = tmp_fptr_cast,
                     fptr = tmp_fptr,
                     ftyp = GenFunctionType(&expr.method.descriptor)
                 )?;
+                self.end_instr()?;
 
                 format!("%t{}", tmp_fptr_cast)
             }
             InvokeTarget::Interface(ref var) => {
-                let vtable = self.vtables.get(method_class_name)?;
-                let target = vtable.get(method_name, &expr.method.descriptor).unwrap();
+                let target = self.vtables.get_target(
+                    method_class_name,
+                    method_name,
+                    &expr.method.descriptor,
+                )?;
                 let iface_vtable_type = self.decls.add_vtable_type(method_class_name)?;
                 let iface_vtable_const = self.decls.add_vtable_const(method_class_name)?;
 
                 let tmp_fptr = self.var_id_gen.gen();
-                writeln!(
+                write!(
                     self.out,
                     "  %t{fptr} = call i8* @_Jrt_object_itable_lookup(%ref {object}, i8* bitcast ({ivtyp}* {ivtbl} to i8*), i64 {index})",
                     fptr = tmp_fptr,
@@ -142,21 +160,29 @@ impl<'a> ExprCodeGen<'a> {
                     ivtbl = iface_vtable_const,
                     index = target.method_index_lower
                 )?;
+                self.end_instr()?;
                 let tmp_fptr_cast = self.var_id_gen.gen();
-                writeln!(
+                write!(
                     self.out,
                     "  %t{fptr_cast} = bitcast i8* %t{fptr} to {ftyp}*",
                     fptr_cast = tmp_fptr_cast,
                     fptr = tmp_fptr,
                     ftyp = GenFunctionType(&expr.method.descriptor)
                 )?;
+                self.end_instr()?;
 
                 format!("%t{}", tmp_fptr_cast)
             }
             InvokeTarget::Special(_) => {
-                if method_class_name != self.class.get_name() {
+                let target_class_name = self.classes.resolve_special(
+                    self.class.get_name(),
+                    method_class_name,
+                    method_name,
+                    &expr.method.descriptor,
+                )?;
+                if target_class_name != *self.class.get_name() {
                     self.decls.add_instance_method(
-                        method_class_name,
+                        &target_class_name,
                         method_name,
                         &expr.method.descriptor,
                     )?;
@@ -164,7 +190,8 @@ impl<'a> ExprCodeGen<'a> {
                 format!(
                     "@{}",
                     mangle::mangle_method_name(
-                        method_class_name,
+                        self.target.mangle_scheme,
+                        &target_class_name,
                         method_name,
                         &expr.method.descriptor.ret,
                         &expr.method.descriptor.params
@@ -172,9 +199,14 @@ impl<'a> ExprCodeGen<'a> {
                 )
             }
             InvokeTarget::Static => {
-                if method_class_name != self.class.get_name() {
+                let target_class_name = self.classes.resolve_method(
+                    method_class_name,
+                    method_name,
+                    &expr.method.descriptor,
+                )?;
+                if target_class_name != *self.class.get_name() {
                     self.decls.add_static_method(
-                        method_class_name,
+                        &target_class_name,
                         method_name,
                         &expr.method.descriptor,
                     )?;
@@ -182,7 +214,8 @@ impl<'a> ExprCodeGen<'a> {
                 format!(
                     "@{}",
                     mangle::mangle_method_name(
-                        method_class_name,
+                        self.target.mangle_scheme,
+                        &target_class_name,
                         method_name,
                         &expr.method.descriptor.ret,
                         &expr.method.descriptor.params
@@ -197,6 +230,25 @@ impl<'a> ExprCodeGen<'a> {
             write!(self.out, "  ")?;
         }
 
+        // long and double arguments take a single operand each, and have to
+        // line up with the wide parameter types the callee is declared with
+        for (idx, (arg, ParameterDescriptor::Field(param_type))) in expr
+            .args
+            .iter()
+            .zip(expr.method.descriptor.params.iter())
+            .enumerate()
+        {
+            ensure!(
+                tlt_type(&arg.get_type()) == tlt_field_type(param_type),
+                "argument {} of {}.{} is {}, but the method expects {:?}",
+                idx,
+                method_class_name,
+                method_name,
+                arg.get_type(),
+                param_type
+            );
+        }
+
         let mut args = vec![];
 
         match expr.target {
@@ -210,31 +262,34 @@ impl<'a> ExprCodeGen<'a> {
             args.push(&arg);
         }
 
-        writeln!(
+        write!(
             self.out,
             "call {return_type} {fptr}({args})",
             fptr = fptr,
             return_type = tlt_return_type(&expr.method.descriptor.ret),
             args = args.iter().gen_comma_sep(|arg| GenOpWithType(arg))
         )?;
+        self.end_instr()?;
         Ok(())
     }
 
     fn gen_expr_monitor(&mut self, op: &Op, transition: &MonitorStateTransition) -> Fallible<()> {
         match transition {
             MonitorStateTransition::Enter => {
-                writeln!(
+                write!(
                     self.out,
                     "  call void @_Jrt_object_monitorenter(%ref {})",
                     OpVal(op)
                 )?;
+                self.end_instr()?;
             }
             MonitorStateTransition::Exit => {
-                writeln!(
+                write!(
                     self.out,
                     "  call void @_Jrt_object_monitorexit(%ref {})",
                     OpVal(op)
                 )?;
+                self.end_instr()?;
             }
         }
         Ok(())
@@ -244,6 +299,8 @@ impl<'a> ExprCodeGen<'a> {
         match binary_expr.operation {
             BinaryOperation::Add => self.gen_expr_binary_simple("add", binary_expr, dest)?,
             BinaryOperation::Sub => self.gen_expr_binary_simple("sub", binary_expr, dest)?,
+            BinaryOperation::Div => self.gen_expr_binary_checked("div", binary_expr, dest)?,
+            BinaryOperation::Rem => self.gen_expr_binary_checked("rem", binary_expr, dest)?,
             BinaryOperation::BitwiseAnd => self.gen_expr_binary_simple("and", binary_expr, dest)?,
             BinaryOperation::BitwiseOr => self.gen_expr_binary_simple("or", binary_expr, dest)?,
             BinaryOperation::BitwiseXor => self.gen_expr_binary_simple("xor", binary_expr, dest)?,
@@ -265,23 +322,45 @@ impl<'a> ExprCodeGen<'a> {
         dest: Dest,
     ) -> Fallible<()> {
         if let Dest::Assign(assign) = dest {
+            // the shift distance is masked to the bit width of the shifted value
+            let mask = match binary_expr.result_type {
+                Type::Long => 63,
+                _ => 31,
+            };
+            let count_type = binary_expr.operand_right.get_type();
             let tmp_masked = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
-                "  %t{} = and {} {}, 31",
+                "  %t{} = and {} {}, {}",
                 tmp_masked,
-                tlt_type(&binary_expr.operand_right.get_type()),
-                OpVal(&binary_expr.operand_right)
+                tlt_type(&count_type),
+                OpVal(&binary_expr.operand_right),
+                mask
             )?;
-            writeln!(
+            self.end_instr()?;
+            let mut tmp_count = tmp_masked;
+            if tlt_type(&count_type) != tlt_type(&binary_expr.result_type) {
+                tmp_count = self.var_id_gen.gen();
+                write!(
+                    self.out,
+                    "  %t{} = zext {} %t{} to {}",
+                    tmp_count,
+                    tlt_type(&count_type),
+                    tmp_masked,
+                    tlt_type(&binary_expr.result_type)
+                )?;
+                self.end_instr()?;
+            }
+            write!(
                 self.out,
                 "  {} = {} {} {}, %t{}",
                 assign,
                 operation,
                 tlt_type(&binary_expr.result_type),
                 OpVal(&binary_expr.operand_left),
-                tmp_masked
+                tmp_count
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
@@ -293,7 +372,7 @@ impl<'a> ExprCodeGen<'a> {
         dest: Dest,
     ) -> Fallible<()> {
         if let Dest::Assign(assign) = dest {
-            writeln!(
+            write!(
                 self.out,
                 "  {} = {} {} {}, {}",
                 assign,
@@ -302,10 +381,42 @@ impl<'a> ExprCodeGen<'a> {
                 OpVal(&binary_expr.operand_left),
                 OpVal(&binary_expr.operand_right)
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
 
+    fn gen_expr_binary_checked(
+        &mut self,
+        operation: &str,
+        binary_expr: &BinaryExpr,
+        dest: Dest,
+    ) -> Fallible<()> {
+        // the runtime throws on division by zero, and defines the overflowing
+        // cases that `sdiv` and `srem` leave undefined, so the call is emitted
+        // even if the result is unused
+        let prefix = match binary_expr.result_type {
+            Type::Long => "l",
+            _ => "i",
+        };
+        if let Dest::Assign(assign) = dest {
+            write!(self.out, "  {} = ", assign)?;
+        } else {
+            write!(self.out, "  ")?;
+        }
+        write!(
+            self.out,
+            "call {typ} @_Jrt_{}{}({typ} {}, {typ} {})",
+            prefix,
+            operation,
+            OpVal(&binary_expr.operand_left),
+            OpVal(&binary_expr.operand_right),
+            typ = tlt_type(&binary_expr.result_type)
+        )?;
+        self.end_instr()?;
+        Ok(())
+    }
+
     fn gen_expr_compare(&mut self, expr: &CompareExpr, dest: Dest) -> Fallible<()> {
         match expr {
             CompareExpr::ICmp(comp, var1, var2) => {
@@ -322,8 +433,11 @@ impl<'a> ExprCodeGen<'a> {
 
     fn gen_expr_convert(&mut self, conv_expr: &ConvertExpr, dest: Dest) -> Fallible<()> {
         match conv_expr.operation {
+            ConvertOperation::IntToBoolean => {
+                self.gen_expr_convert_truncate_and_extend(&conv_expr.operand, "i1", false, dest)
+            }
             ConvertOperation::IntToChar => {
-                self.gen_expr_convert_truncate_and_extend(&conv_expr.operand, "i8", false, dest)
+                self.gen_expr_convert_truncate_and_extend(&conv_expr.operand, "i16", false, dest)
             }
             ConvertOperation::IntToByte => {
                 self.gen_expr_convert_truncate_and_extend(&conv_expr.operand, "i8", true, dest)
@@ -344,7 +458,7 @@ impl<'a> ExprCodeGen<'a> {
         if let Dest::Assign(assign) = dest {
             let op_type = tlt_type(&op.get_type());
             let tmp_trunc = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = trunc {typ} {op} to {via}",
                 tmp_trunc,
@@ -352,8 +466,9 @@ impl<'a> ExprCodeGen<'a> {
                 op = OpVal(op),
                 via = via
             )?;
+            self.end_instr()?;
             if sign {
-                writeln!(
+                write!(
                     self.out,
                     "  {} = sext {via} %t{} to {typ}",
                     assign,
@@ -361,8 +476,9 @@ impl<'a> ExprCodeGen<'a> {
                     typ = op_type,
                     via = via
                 )?;
+                self.end_instr()?;
             } else {
-                writeln!(
+                write!(
                     self.out,
                     "  {} = zext {via} %t{} to {typ}",
                     assign,
@@ -370,6 +486,7 @@ impl<'a> ExprCodeGen<'a> {
                     typ = op_type,
                     via = via
                 )?;
+                self.end_instr()?;
             }
         }
         Ok(())
@@ -378,25 +495,27 @@ impl<'a> ExprCodeGen<'a> {
     fn gen_expr_array_new(&mut self, ctyp: &Type, count: &Op, dest: Dest) -> Fallible<()> {
         if let Dest::Assign(assign) = dest {
             let component_type = tlt_array_component_type(ctyp);
-            writeln!(
+            write!(
                 self.out,
                 "  {} = call %ref @_Jrt_array_new(i32 {count}, i64 {width})",
                 assign,
                 count = OpVal(count),
                 width = GenSizeOf(&component_type)
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
 
     fn gen_expr_array_length(&mut self, aref: &Op, dest: Dest) -> Fallible<()> {
         if let Dest::Assign(assign) = dest {
-            writeln!(
+            write!(
                 self.out,
                 "  {} = call i32 @_Jrt_array_length(%ref {})",
                 assign,
                 OpVal(aref)
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
@@ -425,17 +544,18 @@ impl<'a> ExprCodeGen<'a> {
                 _ => assign.clone(),
             };
 
-            writeln!(
+            write!(
                 self.out,
                 "  {} = load {ctyp}, {ctyp}* %t{}",
                 load_assign,
                 tmp_array_ptr,
                 ctyp = component_type
             )?;
+            self.end_instr()?;
 
             match ctyp {
                 Type::Boolean | Type::Byte | Type::Short => {
-                    writeln!(
+                    write!(
                         self.out,
                         "   {} = sext {ctyp} %t{} to {vtyp}",
                         assign,
@@ -443,9 +563,10 @@ impl<'a> ExprCodeGen<'a> {
                         vtyp = tlt_type(&ctyp),
                         ctyp = component_type
                     )?;
+                    self.end_instr()?;
                 }
                 Type::Char => {
-                    writeln!(
+                    write!(
                         self.out,
                         "   {} = zext {ctyp} %t{} to {vtyp}",
                         assign,
@@ -453,6 +574,7 @@ impl<'a> ExprCodeGen<'a> {
                         vtyp = tlt_type(&ctyp),
                         ctyp = component_type
                     )?;
+                    self.end_instr()?;
                 }
                 _ => {}
             }
@@ -478,7 +600,7 @@ impl<'a> ExprCodeGen<'a> {
         let truncated = match ctyp {
             Type::Boolean | Type::Byte | Type::Short | Type::Char => {
                 let tmp_trunc = self.var_id_gen.gen();
-                writeln!(
+                write!(
                     self.out,
                     "   %t{} = trunc {vtyp} {val} to {ctyp}",
                     tmp_trunc,
@@ -486,18 +608,32 @@ impl<'a> ExprCodeGen<'a> {
                     val = OpVal(value),
                     ctyp = component_type
                 )?;
+                self.end_instr()?;
                 format!("%t{}", tmp_trunc)
             }
             _ => OpVal(value).to_string(),
         };
 
-        writeln!(
+        // null can be stored into any reference array
+        let is_null = matches!(value, Op::Const(Const::Null));
+        if *ctyp == Type::Reference && self.target.array_store_checks && !is_null {
+            write!(
+                self.out,
+                "  call void @_Jrt_array_store_check(%ref {}, %ref {})",
+                OpVal(aref),
+                OpVal(value)
+            )?;
+            self.end_instr()?;
+        }
+
+        write!(
             self.out,
             "  store {ctyp} {val}, {ctyp}* %t{aptr}",
             ctyp = component_type,
             val = truncated,
             aptr = tmp_array_ptr,
         )?;
+        self.end_instr()?;
         Ok(())
     }
 
@@ -507,27 +643,70 @@ impl<'a> ExprCodeGen<'a> {
         consts: &ConstantPool,
         dest: Dest,
     ) -> Fallible<()> {
-        let field_ref = consts.get_field_ref(index).unwrap();
-        let field_name = consts.get_utf8(field_ref.name_index).unwrap();
-        let field_class = consts.get_class(field_ref.class_index).unwrap();
-        let field_class_name = consts.get_utf8(field_class.name_index).unwrap();
-
-        let field_identifier =
-            self.decls
-                .add_static_field(field_class_name, field_name, &field_ref.descriptor)?;
+        let field_ref = get_field_ref(consts, index)?;
+        let field_identifier = self.gen_static_field_ref(&field_ref, consts)?;
 
         if let Dest::Assign(assign) = dest {
-            writeln!(
+            write!(
                 self.out,
                 "  {} = load {ftyp}, {ftyp}* {field}",
                 assign,
                 ftyp = tlt_field_type(&field_ref.descriptor),
                 field = field_identifier
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
 
+    fn gen_expr_put_static(
+        &mut self,
+        index: ConstantIndex,
+        value: &Op,
+        consts: &ConstantPool,
+    ) -> Fallible<()> {
+        let field_ref = get_field_ref(consts, index)?;
+        let field_identifier = self.gen_static_field_ref(&field_ref, consts)?;
+
+        write!(
+            self.out,
+            "  store {ftyp} {}, {ftyp}* {field}",
+            OpVal(value),
+            ftyp = tlt_field_type(&field_ref.descriptor),
+            field = field_identifier
+        )?;
+        self.end_instr()?;
+        Ok(())
+    }
+
+    fn gen_static_field_ref(
+        &mut self,
+        field_ref: &FieldRef,
+        consts: &ConstantPool,
+    ) -> Fallible<String> {
+        let field_name = consts.get_utf8(field_ref.name_index).unwrap();
+        let field_class = consts.get_class(field_ref.class_index).unwrap();
+        // static fields are only defined on the class that declares them,
+        // which may be a superclass of the referenced one
+        let field_class_name = self.classes.resolve_static_field(
+            consts.get_utf8(field_class.name_index).unwrap(),
+            field_name,
+            &field_ref.descriptor,
+        )?;
+
+        // fields declared by this class are defined in the same module
+        if field_class_name == *self.class.get_name() {
+            return Ok(format!(
+                "@{}",
+                mangle::mangle_field_name(self.target.mangle_scheme, &field_class_name, field_name)
+            ));
+        }
+        let field_identifier =
+            self.decls
+                .add_static_field(&field_class_name, field_name, &field_ref.descriptor)?;
+        Ok(field_identifier.to_string())
+    }
+
     fn gen_expr_get_field(
         &mut self,
         object: &Op,
@@ -544,13 +723,14 @@ impl<'a> ExprCodeGen<'a> {
                 Dest::Assign(DestAssign::Tmp(tmp_field_ptr)),
             )?;
 
-            writeln!(
+            write!(
                 self.out,
                 "  {} = load {field_type}, {field_type}* %t{}",
                 assign,
                 tmp_field_ptr,
                 field_type = tlt_field_type(&field_ref.descriptor)
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
@@ -570,13 +750,14 @@ impl<'a> ExprCodeGen<'a> {
             Dest::Assign(DestAssign::Tmp(tmp_field_ptr)),
         )?;
 
-        writeln!(
+        write!(
             self.out,
             "  store {field_type} {}, {field_type}* %t{}",
             OpVal(value),
             tmp_field_ptr,
             field_type = tlt_field_type(&field_ref.descriptor)
         )?;
+        self.end_instr()?;
         Ok(())
     }
 
@@ -591,23 +772,25 @@ impl<'a> ExprCodeGen<'a> {
 
         if let Dest::Assign(assign) = dest {
             let tmp_element_ptr = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = call i8* @_Jrt_array_element_ptr(%ref {})",
                 tmp_element_ptr,
                 OpVal(aref)
             )?;
+            self.end_instr()?;
 
             let tmp_element_ptr_cast = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = bitcast i8* %t{} to {ctyp}*",
                 tmp_element_ptr_cast,
                 tmp_element_ptr,
                 ctyp = component_type
             )?;
+            self.end_instr()?;
 
-            writeln!(
+            write!(
                 self.out,
                 "  {} = getelementptr {ctyp}, {ctyp}* %t{}, i32 {idx}",
                 assign,
@@ -615,6 +798,7 @@ impl<'a> ExprCodeGen<'a> {
                 idx = OpVal(idx),
                 ctyp = component_type
             )?;
+            self.end_instr()?;
         }
         Ok(component_type)
     }
@@ -626,7 +810,7 @@ impl<'a> ExprCodeGen<'a> {
         consts: &ConstantPool,
         dest: Dest,
     ) -> Fallible<FieldRef> {
-        let field_ref = consts.get_field_ref(index).unwrap();
+        let field_ref = get_field_ref(consts, index)?;
         if let Dest::Assign(assign) = dest {
             let field_name = consts.get_utf8(field_ref.name_index).unwrap();
             let field_class = consts.get_class(field_ref.class_index).unwrap();
@@ -636,21 +820,23 @@ impl<'a> ExprCodeGen<'a> {
             let object_type = self.decls.add_object_type(field_class_name)?;
 
             let tmp_field_ptr = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = call i8* @_Jrt_object_field_ptr(%ref {})",
                 tmp_field_ptr,
                 OpVal(object)
             )?;
+            self.end_instr()?;
 
             let tmp_field_ptr_cast = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = bitcast i8* %t{} to {}*",
                 tmp_field_ptr_cast, tmp_field_ptr, object_type
             )?;
+            self.end_instr()?;
 
-            writeln!(
+            write!(
                 self.out,
                 "  {} = getelementptr {otyp}, {otyp}* %t{}, i64 0, i32 {field_index}",
                 assign,
@@ -658,6 +844,7 @@ impl<'a> ExprCodeGen<'a> {
                 otyp = object_type,
                 field_index = field_layout.get(field_name, &field_ref.descriptor).unwrap()
             )?;
+            self.end_instr()?;
         }
         Ok(field_ref)
     }
@@ -679,7 +866,7 @@ impl<'a> ExprCodeGen<'a> {
                 IComparator::Gt => "sgt",
             };
             let tmp_i1 = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = icmp {} i32 {}, {}",
                 tmp_i1,
@@ -687,7 +874,9 @@ impl<'a> ExprCodeGen<'a> {
                 OpVal(var1),
                 OpVal(var2)
             )?;
-            writeln!(self.out, "  {} = zext i1 %t{} to i32", assign, tmp_i1)?;
+            self.end_instr()?;
+            write!(self.out, "  {} = zext i1 %t{} to i32", assign, tmp_i1)?;
+            self.end_instr()?;
         }
         Ok(())
     }
@@ -701,61 +890,70 @@ impl<'a> ExprCodeGen<'a> {
     ) -> Fallible<()> {
         if let Dest::Assign(assign) = dest {
             let tmp_ptr1 = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{ptr} = extractvalue %ref {op}, 0",
                 op = OpVal(var1),
                 ptr = tmp_ptr1
             )?;
+            self.end_instr()?;
             let tmp_ptr2 = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{ptr} = extractvalue %ref {op}, 0",
                 op = OpVal(var2),
                 ptr = tmp_ptr2
             )?;
+            self.end_instr()?;
             let code = match comp {
                 AComparator::Eq => "eq",
                 AComparator::Ne => "ne",
             };
             let tmp_i1 = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = icmp {} i8* %t{}, %t{}",
                 tmp_i1, code, tmp_ptr1, tmp_ptr2
             )?;
-            writeln!(self.out, "  {} = zext i1 %t{} to i32", assign, tmp_i1)?;
+            self.end_instr()?;
+            write!(self.out, "  {} = zext i1 %t{} to i32", assign, tmp_i1)?;
+            self.end_instr()?;
         }
         Ok(())
     }
 
     fn gen_expr_compare_long(&mut self, var1: &Op, var2: &Op, dest: Dest) -> Fallible<()> {
         let tmp_lt = self.var_id_gen.gen();
-        writeln!(
+        write!(
             self.out,
             "  %t{} = icmp slt i64 {}, {}",
             tmp_lt,
             OpVal(var1),
             OpVal(var2)
         )?;
+        self.end_instr()?;
         let tmp_lt_ext = self.var_id_gen.gen();
-        writeln!(self.out, "  %t{} = zext i1 %t{} to i32", tmp_lt_ext, tmp_lt)?;
+        write!(self.out, "  %t{} = zext i1 %t{} to i32", tmp_lt_ext, tmp_lt)?;
+        self.end_instr()?;
         let tmp_gt = self.var_id_gen.gen();
-        writeln!(
+        write!(
             self.out,
             "  %t{} = icmp sgt i64 {}, {}",
             tmp_gt,
             OpVal(var1),
             OpVal(var2)
         )?;
+        self.end_instr()?;
         let tmp_gt_ext = self.var_id_gen.gen();
-        writeln!(self.out, "  %t{} = zext i1 %t{} to i32", tmp_gt_ext, tmp_gt)?;
+        write!(self.out, "  %t{} = zext i1 %t{} to i32", tmp_gt_ext, tmp_gt)?;
+        self.end_instr()?;
         if let Dest::Assign(assign) = dest {
-            writeln!(
+            write!(
                 self.out,
                 "  {} = sub i32 %t{}, %t{}",
                 assign, tmp_gt_ext, tmp_lt_ext
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
@@ -771,7 +969,7 @@ impl<'a> ExprCodeGen<'a> {
             let typ = tlt_type(&var1.get_type());
 
             let tmp_is_gt = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = fcmp ogt {typ} {}, {}",
                 tmp_is_gt,
@@ -779,9 +977,10 @@ impl<'a> ExprCodeGen<'a> {
                 OpVal(var2),
                 typ = typ
             )?;
+            self.end_instr()?;
 
             let tmp_is_eq = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = fcmp oeq {typ} {}, {}",
                 tmp_is_eq,
@@ -789,9 +988,10 @@ impl<'a> ExprCodeGen<'a> {
                 OpVal(var2),
                 typ = typ
             )?;
+            self.end_instr()?;
 
             let tmp_is_lt = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = fcmp olt {typ} {}, {}",
                 tmp_is_lt,
@@ -799,6 +999,7 @@ impl<'a> ExprCodeGen<'a> {
                 OpVal(var2),
                 typ = typ
             )?;
+            self.end_instr()?;
 
             let nan_op = match mode {
                 NaNCmpMode::Greater => Op::Const(Const::Int(1)),
@@ -806,27 +1007,42 @@ impl<'a> ExprCodeGen<'a> {
             };
 
             let tmp_lt_or_nan = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = select i1 %t{}, i32 -1, i32 {}",
                 tmp_lt_or_nan,
                 tmp_is_lt,
                 OpVal(&nan_op),
             )?;
+            self.end_instr()?;
 
             let tmp_eq_or_lt_or_nan = self.var_id_gen.gen();
-            writeln!(
+            write!(
                 self.out,
                 "  %t{} = select i1 %t{}, i32 0, i32 %t{}",
                 tmp_eq_or_lt_or_nan, tmp_is_eq, tmp_lt_or_nan,
             )?;
+            self.end_instr()?;
 
-            writeln!(
+            write!(
                 self.out,
                 "  {} = select i1 %t{}, i32 1, i32 %t{}",
                 assign, tmp_is_gt, tmp_eq_or_lt_or_nan,
             )?;
+            self.end_instr()?;
         }
         Ok(())
     }
+
+    /// Ends the instruction being written, attaching the debug location of
+    /// the statement it belongs to.
+    fn end_instr(&mut self) -> fmt::Result {
+        debug::end_instr(self.out, self.location)
+    }
+}
+
+fn get_field_ref(consts: &ConstantPool, index: ConstantIndex) -> Fallible<FieldRef> {
+    consts
+        .get_field_ref(index)
+        .ok_or_else(|| format_err!("invalid field ref #{}", index.into_u16()))
 }
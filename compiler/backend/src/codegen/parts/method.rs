@@ -1,14 +1,16 @@
+use std::collections::BTreeSet;
 use std::fmt::{self, Write};
 use std::sync::Arc;
 
 use classfile::{ClassFile, ConstantPool, Method};
-use failure::Fallible;
+use failure::{bail, Fallible};
 
 use frontend::blocks::{BlockGraph, PhiOperand, PhiOperandSource};
 use frontend::classes::ClassGraph;
-use frontend::translate::{BasicBlock, BranchStub, Expr, Statement, Switch};
+use frontend::translate::{BasicBlock, BranchStub, Expr, Op, Statement, Switch};
 
 use crate::codegen::common::*;
+use crate::codegen::debug::{self, DebugInfo, DebugScope, LineNumbers};
 use crate::codegen::decls::DeclDatabase;
 use crate::codegen::Target;
 use crate::layout::{FieldLayoutMap, VTableMap};
@@ -16,6 +18,33 @@ use crate::mangle;
 
 use super::expr::ExprCodeGen;
 
+/// The monitor held by a synchronized method.
+enum Monitor {
+    Object(Op),
+    /// The slot of the class monitor, see `mangle::mangle_class_monitor_name`.
+    Class(String),
+}
+
+impl Monitor {
+    fn enter(&self) -> String {
+        match self {
+            Monitor::Object(receiver) => {
+                format!("@_Jrt_object_monitorenter(%ref {})", OpVal(receiver))
+            }
+            Monitor::Class(slot) => format!("@_Jrt_class_monitorenter(i8** @{})", slot),
+        }
+    }
+
+    fn exit(&self) -> String {
+        match self {
+            Monitor::Object(receiver) => {
+                format!("@_Jrt_object_monitorexit(%ref {})", OpVal(receiver))
+            }
+            Monitor::Class(slot) => format!("@_Jrt_class_monitorexit(i8** @{})", slot),
+        }
+    }
+}
+
 pub struct MethodCodeGen<'a> {
     pub out: &'a mut String,
     pub decls: &'a mut DeclDatabase,
@@ -25,6 +54,7 @@ pub struct MethodCodeGen<'a> {
     pub field_layouts: &'a FieldLayoutMap,
     pub var_id_gen: &'a mut TmpVarIdGen,
     pub target: &'a Arc<Target>,
+    pub debug: Option<&'a mut DebugInfo>,
 }
 
 impl<'a> MethodCodeGen<'a> {
@@ -39,23 +69,67 @@ impl<'a> MethodCodeGen<'a> {
             .unwrap();
         let method_name = consts.get_utf8(method.name_index).unwrap();
         let mangled_name = mangle::mangle_method_name(
+            self.target.mangle_scheme,
             class_name,
             method_name,
             &method.descriptor.ret,
             &method.descriptor.params,
         );
+        let scope = match self.debug {
+            Some(ref mut debug) => {
+                let table = match method.code()? {
+                    Some(code) => code.line_number_table()?,
+                    None => None,
+                };
+                let lines = LineNumbers::new(table);
+                let subprogram = debug.add_subprogram(method_name, &mangled_name, lines.first());
+                Some(DebugScope { subprogram, lines })
+            }
+            None => None,
+        };
+        // a synchronized method holds the monitor of its receiver while it
+        // runs, or the monitor of its class if it is static
+        let monitor = if !method.is_synchronized() {
+            None
+        } else if method.is_static() {
+            let monitor_name =
+                mangle::mangle_class_monitor_name(self.target.mangle_scheme, class_name);
+            Some(Monitor::Class(monitor_name))
+        } else {
+            match blocks.entry().locals.get(&0) {
+                Some(receiver) => Some(Monitor::Object(receiver.clone())),
+                None => bail!("synchronized method {} has no receiver", method_name),
+            }
+        };
         let gen_args = blocks.entry().locals.iter();
-        writeln!(
+        write!(
             self.out,
-            "\ndefine {return_type} @{mangled_name}({args}) {{",
+            "\ndefine {return_type} @{mangled_name}({args})",
             return_type = tlt_return_type(&method.descriptor.ret),
             mangled_name = mangled_name,
             args = gen_args.gen_comma_sep(|(_, op)| GenOpWithType(op))
         )?;
+        if let Some(ref scope) = scope {
+            write!(self.out, " !dbg !{}", scope.subprogram)?;
+        }
+        writeln!(self.out, " {{")?;
         writeln!(self.out, "entry:")?;
+        if let Some(ref monitor) = monitor {
+            writeln!(self.out, "  call void {}", monitor.enter())?;
+        }
         writeln!(self.out, "  br label %B0")?;
-        for block in blocks.blocks() {
-            self.gen_block(block, blocks, consts)?;
+        // emit blocks in reverse postorder, so that definitions precede their
+        // uses, followed by any blocks that are not reachable from the start
+        let ordered = blocks.blocks_rpo();
+        let visited = ordered
+            .iter()
+            .map(|block| block.address)
+            .collect::<BTreeSet<_>>();
+        let unreachable = blocks
+            .blocks()
+            .filter(|block| !visited.contains(&block.address));
+        for block in ordered.iter().cloned().chain(unreachable) {
+            self.gen_block(block, blocks, consts, scope.as_ref(), monitor.as_ref())?;
         }
         writeln!(self.out, "}}")?;
         Ok(())
@@ -66,39 +140,68 @@ impl<'a> MethodCodeGen<'a> {
         block: &BasicBlock,
         blocks: &BlockGraph,
         consts: &ConstantPool,
+        scope: Option<&DebugScope>,
+        monitor: Option<&Monitor>,
     ) -> Fallible<()> {
         writeln!(self.out, "B{}:", block.address)?;
         self.gen_phi_nodes(block, blocks)?;
+        let mut pc = block.address.addr();
         for stmt in block.statements.iter() {
-            self.gen_statement(stmt, consts)?;
+            pc = stmt.pc;
+            let location = self.location(pc, scope);
+            self.gen_statement(stmt, consts, location)?;
         }
+        // the branch belongs to the line of the last statement
+        let location = self.location(pc, scope);
         match &block.branch_stub {
             BranchStub::Return(ret_opt) => {
+                if let Some(monitor) = monitor {
+                    write!(self.out, "  call void {}", monitor.exit())?;
+                    debug::end_instr(self.out, location)?;
+                }
                 if let Some(ret) = ret_opt {
-                    writeln!(
+                    write!(
                         self.out,
                         "  ret {} {}",
                         tlt_type(&ret.get_type()),
                         OpVal(ret)
                     )?;
                 } else {
-                    writeln!(self.out, "  ret void")?;
+                    write!(self.out, "  ret void")?;
                 }
+                debug::end_instr(self.out, location)?;
             }
-            BranchStub::Switch(switch) => self.gen_switch(switch)?,
+            BranchStub::Switch(switch) => self.gen_switch(switch, location)?,
             BranchStub::Throw(var) => {
-                writeln!(
+                // the method completes abruptly, which releases the monitor too
+                if let Some(monitor) = monitor {
+                    write!(self.out, "  call void {}", monitor.exit())?;
+                    debug::end_instr(self.out, location)?;
+                }
+                write!(
                     self.out,
                     "  call void @_Jrt_throw(%ref {}) noreturn",
                     OpVal(var)
                 )?;
-                writeln!(self.out, "  unreachable")?;
+                debug::end_instr(self.out, location)?;
+                write!(self.out, "  unreachable")?;
+                debug::end_instr(self.out, location)?;
             }
         }
         Ok(())
     }
 
-    fn gen_switch(&mut self, switch: &Switch) -> Fallible<()> {
+    /// The debug location of the code at `pc`, if debug info is generated.
+    fn location(&mut self, pc: u32, scope: Option<&DebugScope>) -> Option<usize> {
+        match (self.debug.as_mut(), scope) {
+            (Some(debug), Some(scope)) => {
+                Some(debug.add_location(scope.lines.line_at(pc), scope.subprogram))
+            }
+            _ => None,
+        }
+    }
+
+    fn gen_switch(&mut self, switch: &Switch, location: Option<usize>) -> Fallible<()> {
         write!(
             self.out,
             "  switch i32 {}, label %B{} [",
@@ -108,21 +211,33 @@ impl<'a> MethodCodeGen<'a> {
         for (value, addr) in switch.cases.iter() {
             write!(self.out, " i32 {}, label %B{}", value, addr)?;
         }
-        writeln!(self.out, " ]")?;
+        write!(self.out, " ]")?;
+        debug::end_instr(self.out, location)?;
         Ok(())
     }
 
-    fn gen_statement(&mut self, stmt: &Statement, consts: &ConstantPool) -> Fallible<()> {
+    fn gen_statement(
+        &mut self,
+        stmt: &Statement,
+        consts: &ConstantPool,
+        location: Option<usize>,
+    ) -> Fallible<()> {
         let dest;
         if let Some(ref var) = stmt.assign {
             dest = Dest::Assign(DestAssign::Var(var.clone()));
         } else {
             dest = Dest::Ignore;
         }
-        self.gen_expr(&stmt.expression, consts, dest)
+        self.gen_expr(&stmt.expression, consts, dest, location)
     }
 
-    fn gen_expr(&mut self, expr: &Expr, consts: &ConstantPool, dest: Dest) -> Fallible<()> {
+    fn gen_expr(
+        &mut self,
+        expr: &Expr,
+        consts: &ConstantPool,
+        dest: Dest,
+        location: Option<usize>,
+    ) -> Fallible<()> {
         let mut expr_code_gen = ExprCodeGen {
             out: self.out,
             decls: self.decls,
@@ -132,6 +247,7 @@ impl<'a> MethodCodeGen<'a> {
             field_layouts: self.field_layouts,
             var_id_gen: self.var_id_gen,
             target: self.target,
+            location,
         };
         expr_code_gen.gen_expr(expr, consts, dest)
     }
@@ -12,6 +12,7 @@ use crate::codegen::common::*;
 use crate::codegen::decls::DeclDatabase;
 use crate::codegen::Target;
 use crate::layout::{FieldLayoutMap, VTableMap};
+use crate::mangle;
 
 pub struct PreludeCodeGen<'a> {
     pub out: &'a mut String,
@@ -27,53 +28,30 @@ pub struct PreludeCodeGen<'a> {
 impl<'a> PreludeCodeGen<'a> {
     pub fn gen_prelude(&mut self) -> Fallible<()> {
         let filename = self.class.attributes.get::<SourceFile>()?;
-
-        writeln!(self.out, "; ModuleID = '{}'", self.class.get_name())?;
-        writeln!(self.out, "source_filename = \"{}\"", filename.as_str())?;
-        writeln!(
-            self.out,
-            "target datalayout = \"{}\"",
-            self.target.data_layout
-        )?;
-        writeln!(self.out, "target triple = \"{}\"", self.target.triple)?;
-        writeln!(self.out)?;
-
-        writeln!(self.out, "%ref = type {{ i8*, i8* }}")?;
-
-        writeln!(
-            self.out,
-            "declare i32 @_Jrt_start(i32, i8**, void (%ref) *)"
-        )?;
-        writeln!(self.out, "declare %ref @_Jrt_object_new(i64, i8*)")?;
-        writeln!(self.out, "declare i8* @_Jrt_object_field_ptr(%ref)")?;
-        writeln!(
-            self.out,
-            "declare i8* @_Jrt_object_vtable_lookup(%ref, i64)"
-        )?;
-        writeln!(
+        gen_module_header(
             self.out,
-            "declare i8* @_Jrt_object_itable_lookup(%ref, i8*, i64)"
+            self.class.get_name(),
+            Some(filename.as_str()),
+            self.target,
         )?;
-        writeln!(self.out, "declare void @_Jrt_object_monitorenter(%ref)")?;
-        writeln!(self.out, "declare void @_Jrt_object_monitorexit(%ref)")?;
-        writeln!(self.out, "declare %ref @_Jrt_array_new(i32, i64)")?;
-        writeln!(self.out, "declare i32 @_Jrt_array_length(%ref)")?;
-        writeln!(self.out, "declare i8* @_Jrt_array_element_ptr(%ref)")?;
-        writeln!(self.out, "declare void @_Jrt_throw(%ref) noreturn")?;
-        writeln!(self.out, "declare void @_Jrt_abstract() noreturn")?;
-        writeln!(self.out, "declare %ref @_Jrt_ldstr(i8*)")?;
+        gen_runtime_decls(self.out)?;
+        self.gen_string_consts()
+    }
 
-        for index in self.class.constant_pool.indices() {
-            if let Constant::String(string_const) =
-                self.class.constant_pool.get_info(index).unwrap()
-            {
+    pub fn gen_string_consts(&mut self) -> Fallible<()> {
+        for (_, constant) in self.class.constant_pool.iter() {
+            if let Constant::String(string_const) = constant {
                 let utf8_index = string_const.string_index;
                 writeln!(self.out)?;
                 let utf8 = self.class.constant_pool.get_utf8(utf8_index).unwrap();
                 writeln!(
                     self.out,
-                    "@.str{} = internal constant [{} x i8] {}",
-                    utf8_index.into_u16(),
+                    "@{} = internal constant [{} x i8] {}",
+                    mangle::mangle_string_const_name(
+                        self.target.mangle_scheme,
+                        self.class.get_name(),
+                        utf8_index.into_u16()
+                    ),
                     utf8.len() + 1,
                     GenStringConst(&*utf8)
                 )?;
@@ -82,3 +60,48 @@ impl<'a> PreludeCodeGen<'a> {
         Ok(())
     }
 }
+
+pub fn gen_module_header(
+    out: &mut String,
+    module_id: &str,
+    source_filename: Option<&str>,
+    target: &Target,
+) -> Fallible<()> {
+    writeln!(out, "; ModuleID = '{}'", module_id)?;
+    if let Some(filename) = source_filename {
+        writeln!(out, "source_filename = \"{}\"", filename)?;
+    }
+    writeln!(out, "target datalayout = \"{}\"", target.data_layout)?;
+    writeln!(out, "target triple = \"{}\"", target.triple)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+pub fn gen_runtime_decls(out: &mut String) -> Fallible<()> {
+    writeln!(out, "%ref = type {{ i8*, i8* }}")?;
+
+    writeln!(out, "declare i32 @_Jrt_start(i32, i8**, void (%ref) *)")?;
+    writeln!(out, "declare %ref @_Jrt_object_new(i64, i8*)")?;
+    writeln!(out, "declare i8* @_Jrt_object_field_ptr(%ref)")?;
+    writeln!(out, "declare i8* @_Jrt_object_vtable_lookup(%ref, i64)")?;
+    writeln!(
+        out,
+        "declare i8* @_Jrt_object_itable_lookup(%ref, i8*, i64)"
+    )?;
+    writeln!(out, "declare void @_Jrt_object_monitorenter(%ref)")?;
+    writeln!(out, "declare void @_Jrt_object_monitorexit(%ref)")?;
+    writeln!(out, "declare void @_Jrt_class_monitorenter(i8**)")?;
+    writeln!(out, "declare void @_Jrt_class_monitorexit(i8**)")?;
+    writeln!(out, "declare %ref @_Jrt_array_new(i32, i64)")?;
+    writeln!(out, "declare i32 @_Jrt_array_length(%ref)")?;
+    writeln!(out, "declare i8* @_Jrt_array_element_ptr(%ref)")?;
+    writeln!(out, "declare void @_Jrt_array_store_check(%ref, %ref)")?;
+    writeln!(out, "declare i32 @_Jrt_idiv(i32, i32)")?;
+    writeln!(out, "declare i32 @_Jrt_irem(i32, i32)")?;
+    writeln!(out, "declare i64 @_Jrt_ldiv(i64, i64)")?;
+    writeln!(out, "declare i64 @_Jrt_lrem(i64, i64)")?;
+    writeln!(out, "declare void @_Jrt_throw(%ref) noreturn")?;
+    writeln!(out, "declare void @_Jrt_abstract() noreturn")?;
+    writeln!(out, "declare %ref @_Jrt_ldstr(i8*)")?;
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! The symbol manifest lists the symbols that are defined for each compiled
+//! class, so that the runtime and other tools can locate them without
+//! mangling names themselves.
+//!
+//! Each line starts with the class name and the kind of the symbol, and ends
+//! with the symbol. Method lines give the method name and descriptor before
+//! the symbol:
+//!
+//! ```text
+//! java/lang/Object vtable _ZTVN4java4lang6ObjectE
+//! java/lang/Object classid _Jrt_classid_N4java4lang6ObjectE
+//! java/lang/Object method hashCode()I _ZN4java4lang6Object8hashCodeIu9J...EEiv
+//! ```
+
+use std::fmt::Write;
+
+use classfile::descriptors::{MethodDescriptor, ParameterDescriptor, ReturnTypeDescriptor};
+use failure::Fallible;
+use strbuf::StrBuf;
+
+use frontend::classes::ClassGraph;
+use frontend::loader::Class;
+
+use crate::layout::ClassIdMap;
+use crate::mangle::{self, MangleScheme};
+
+pub fn gen_symbols(
+    out: &mut String,
+    classes: &ClassGraph,
+    class_ids: &ClassIdMap,
+    scheme: MangleScheme,
+    names: &[StrBuf],
+) -> Fallible<()> {
+    for name in names {
+        let class_file = match classes.get(name)? {
+            Class::File(class_file) => class_file,
+            _ => continue,
+        };
+        let vtable_name = mangle::mangle_vtable_name(scheme, name);
+        writeln!(out, "{} vtable {}", name, vtable_name)?;
+        if class_ids.get(name).is_some() {
+            let class_id_name = mangle::mangle_class_id_name(scheme, name);
+            writeln!(out, "{} classid {}", name, class_id_name)?;
+        }
+        for method in class_file.methods.iter() {
+            let method_name = class_file
+                .constant_pool
+                .get_utf8(method.name_index)
+                .unwrap();
+            let mangled_name = mangle::mangle_method_name(
+                scheme,
+                name,
+                method_name,
+                &method.descriptor.ret,
+                &method.descriptor.params,
+            );
+            writeln!(
+                out,
+                "{} method {}{} {}",
+                name,
+                method_name,
+                descriptor_string(&method.descriptor),
+                mangled_name
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn descriptor_string(descriptor: &MethodDescriptor) -> String {
+    let mut output = String::from("(");
+    for ParameterDescriptor::Field(field_type) in descriptor.params.iter() {
+        output.push_str(&field_type.to_string());
+    }
+    output.push(')');
+    match descriptor.ret {
+        ReturnTypeDescriptor::Void => output.push('V'),
+        ReturnTypeDescriptor::Field(ref field_type) => output.push_str(&field_type.to_string()),
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use frontend::test_util::{TestClass, TestClassLoader};
+
+    #[test]
+    fn manifest_lists_class_symbols() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                methods: vec![("<init>", "()V")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Greeter",
+                super_name: Some("java/lang/Object"),
+                methods: vec![("greet", "([Ljava/lang/String;I)Z")],
+                ..TestClass::default()
+            },
+        ]));
+        let names = vec![StrBuf::new("java/lang/Object"), StrBuf::new("Greeter")];
+        let class_ids = ClassIdMap::build(&classes, &names).unwrap();
+
+        let mut out = String::new();
+        gen_symbols(
+            &mut out,
+            &classes,
+            &class_ids,
+            MangleScheme::Itanium,
+            &names,
+        )
+        .unwrap();
+
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(6, lines.len());
+        assert_eq!("java/lang/Object vtable _ZTVN4java4lang6ObjectE", lines[0]);
+        assert_eq!(
+            "java/lang/Object classid _Jrt_classid_N4java4lang6ObjectE",
+            lines[1]
+        );
+        assert!(lines[2].starts_with("java/lang/Object method <init>()V _ZN4java4lang6Object4init"));
+        assert_eq!("Greeter vtable _ZTVN7GreeterE", lines[3]);
+        assert_eq!("Greeter classid _Jrt_classid_N7GreeterE", lines[4]);
+        assert!(
+            lines[5].starts_with("Greeter method greet([Ljava/lang/String;I)Z _ZN7Greeter5greet")
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use failure::Fallible;
+use strbuf::StrBuf;
+
+use frontend::classes::ClassGraph;
+use frontend::loader::Class;
+
+/// The ids of a class and its subclasses.
+///
+/// Classes are numbered in depth-first order of the class hierarchy, so the
+/// ids of all subclasses of a class follow its own id without gaps (Cohen's
+/// encoding). Checking whether a class extends another thus takes a single
+/// range comparison.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClassIdRange {
+    pub id: u32,
+    pub last_subclass_id: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct ClassIdMap {
+    ranges: HashMap<StrBuf, ClassIdRange>,
+}
+
+impl ClassIdMap {
+    /// Numbers the given classes along with their superclasses.
+    ///
+    /// The ids only depend on the set of classes, not on the order in which
+    /// they are given. Interfaces and array classes are not numbered.
+    pub fn build(classes: &ClassGraph, names: &[StrBuf]) -> Fallible<Self> {
+        let mut roots = BTreeSet::new();
+        let mut subclasses = BTreeMap::<StrBuf, BTreeSet<StrBuf>>::new();
+        for name in names {
+            match classes.get(name)? {
+                Class::File(ref class_file) if !class_file.is_interface() => {}
+                _ => continue,
+            }
+            let chain = classes.superclasses(name)?;
+            for pair in chain.windows(2) {
+                subclasses
+                    .entry(pair[1].clone())
+                    .or_default()
+                    .insert(pair[0].clone());
+            }
+            roots.extend(chain.last().cloned());
+        }
+
+        let mut ranges = HashMap::new();
+        let mut next_id = 0;
+        for root in roots {
+            number_subtree(&root, &subclasses, &mut next_id, &mut ranges);
+        }
+        Ok(ClassIdMap { ranges })
+    }
+
+    pub fn get(&self, name: &StrBuf) -> Option<ClassIdRange> {
+        self.ranges.get(name).cloned()
+    }
+}
+
+fn number_subtree(
+    name: &StrBuf,
+    subclasses: &BTreeMap<StrBuf, BTreeSet<StrBuf>>,
+    next_id: &mut u32,
+    ranges: &mut HashMap<StrBuf, ClassIdRange>,
+) {
+    let id = *next_id;
+    *next_id += 1;
+    for subclass in subclasses.get(name).into_iter().flatten() {
+        number_subtree(subclass, subclasses, next_id, ranges);
+    }
+    let range = ClassIdRange {
+        id,
+        last_subclass_id: *next_id - 1,
+    };
+    ranges.insert(name.clone(), range);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use frontend::test_util::{
+        TestClass, TestClassLoader, ACC_ABSTRACT, ACC_INTERFACE, ACC_PUBLIC,
+    };
+
+    fn hierarchy() -> ClassGraph {
+        ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Runnable",
+                super_name: Some("java/lang/Object"),
+                access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Animal",
+                super_name: Some("java/lang/Object"),
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Dog",
+                super_name: Some("Animal"),
+                interfaces: vec!["Runnable"],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Cat",
+                super_name: Some("Animal"),
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Stone",
+                super_name: Some("java/lang/Object"),
+                ..TestClass::default()
+            },
+        ]))
+    }
+
+    fn is_subclass_of(class: ClassIdRange, other: ClassIdRange) -> bool {
+        other.id <= class.id && class.id <= other.last_subclass_id
+    }
+
+    fn names(names: &[&str]) -> Vec<StrBuf> {
+        names.iter().map(|name| StrBuf::new(name)).collect()
+    }
+
+    #[test]
+    fn subclass_range_is_nested_within_superclass_range() {
+        let classes = hierarchy();
+        let ids =
+            ClassIdMap::build(&classes, &names(&["Dog", "Cat", "Stone", "Runnable"])).unwrap();
+        let get = |name| ids.get(&StrBuf::new(name)).unwrap();
+
+        let object = get("java/lang/Object");
+        let animal = get("Animal");
+        let dog = get("Dog");
+        let cat = get("Cat");
+        let stone = get("Stone");
+
+        assert!(is_subclass_of(animal, object));
+        assert!(is_subclass_of(dog, animal));
+        assert!(is_subclass_of(dog, object));
+        assert!(animal.id < dog.id && dog.last_subclass_id <= animal.last_subclass_id);
+        assert!(!is_subclass_of(dog, cat));
+        assert!(!is_subclass_of(stone, animal));
+        assert!(!is_subclass_of(animal, dog));
+        assert_eq!(None, ids.get(&StrBuf::new("Runnable")));
+    }
+
+    #[test]
+    fn ids_do_not_depend_on_input_order() {
+        let classes = hierarchy();
+        let forward = ClassIdMap::build(&classes, &names(&["Cat", "Dog", "Stone"])).unwrap();
+        let backward = ClassIdMap::build(&classes, &names(&["Stone", "Dog", "Cat"])).unwrap();
+
+        assert_eq!(forward.ranges, backward.ranges);
+    }
+}
@@ -101,43 +101,88 @@ impl FieldLayoutMap {
         Ok(inner[name].clone())
     }
 
+    pub fn invalidate(&self, name: &StrBuf) {
+        self.inner.lock().unwrap().remove(name);
+    }
+
     fn build_table(
         &self,
         name: &StrBuf,
         table: &mut IndexMap<FieldAccessKey, (), FnvBuildHasher>,
     ) -> Fallible<()> {
-        let classfile = match self.classes.get(name)? {
-            Class::File(classfile) => classfile,
-            Class::Array(_) => bail!("can't build vtable for array"),
-        };
-
-        if let Some(super_class) = classfile.get_super_class() {
-            let super_class_name = classfile
-                .constant_pool
-                .get_utf8(super_class.name_index)
-                .unwrap();
-            self.build_table(super_class_name, table)?;
-        }
-
-        for field in classfile.fields.iter() {
-            // skip static methods
-            if field.is_static() {
-                continue;
+        // interfaces can't be instantiated, so they don't have instance fields
+        if let Class::File(classfile) = self.classes.get(name)? {
+            if classfile.is_interface() {
+                return Ok(());
             }
+        }
 
-            let field_name = classfile
-                .constant_pool
-                .get_utf8(field.name_index)
-                .unwrap()
-                .clone();
-
-            let key = FieldAccessKey {
-                field_name,
-                field_type: field.descriptor.clone(),
+        // superclass fields are laid out before the fields of their subclasses
+        for class_name in self.classes.superclasses(name)?.iter().rev() {
+            let classfile = match self.classes.get(class_name)? {
+                Class::File(classfile) => classfile,
+                Class::Array(_) => bail!("can't build field layout for array"),
             };
-            table.insert(key, ());
+
+            for field in classfile.fields.iter() {
+                // skip static fields
+                if field.is_static() {
+                    continue;
+                }
+
+                let field_name = classfile
+                    .constant_pool
+                    .get_utf8(field.name_index)
+                    .unwrap()
+                    .clone();
+
+                let key = FieldAccessKey {
+                    field_name,
+                    field_type: field.descriptor.clone(),
+                };
+                table.insert(key, ());
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use classfile::descriptors::BaseType;
+
+    use frontend::test_util::{TestClass, TestClassLoader};
+
+    #[test]
+    fn subclass_fields_follow_superclass_fields() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Base",
+                super_name: Some("java/lang/Object"),
+                fields: vec![("a", "I"), ("b", "J")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Derived",
+                super_name: Some("Base"),
+                fields: vec![("c", "I")],
+                ..TestClass::default()
+            },
+        ]));
+        let layouts = FieldLayoutMap::new(classes);
+
+        let layout = layouts.get(&StrBuf::new("Derived")).unwrap();
+
+        assert_eq!(3, layout.len());
+        let int_type = FieldType::Base(BaseType::Int);
+        assert_eq!(Some(0), layout.get("a", &int_type));
+        assert_eq!(Some(2), layout.get("c", &int_type));
+    }
+}
@@ -1,5 +1,7 @@
+mod class_ids;
 mod fields;
 mod vtable;
 
+pub use self::class_ids::ClassIdMap;
 pub use self::fields::FieldLayoutMap;
 pub use self::vtable::VTableMap;
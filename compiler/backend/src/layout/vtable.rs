@@ -145,6 +145,29 @@ impl VTableMap {
         Ok(inner[name].clone())
     }
 
+    /// Evicts the vtable of `name` and the vtables of all classes implementing it.
+    pub fn invalidate(&self, name: &StrBuf) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.retain(|class_name, vtable| {
+            class_name != name && !vtable.inner.interfaces.contains_key(name)
+        });
+    }
+
+    /// Looks up the dispatch target of a method in the vtable of `name`,
+    /// loading the class and its superinterfaces if necessary.
+    pub fn get_target(
+        &self,
+        name: &StrBuf,
+        method_name: &str,
+        method_descriptor: &MethodDescriptor,
+    ) -> Fallible<MethodDispatchTarget> {
+        let vtable = self.get(name)?;
+        match vtable.get(method_name, method_descriptor) {
+            Some(target) => Ok(target.clone()),
+            None => bail!("no method {} in vtable of {}", method_name, name),
+        }
+    }
+
     fn build_table(
         &self,
         name: &StrBuf,
@@ -166,23 +189,19 @@ impl VTableMap {
             }
         }
 
-        for cidx in classfile.interfaces.iter() {
-            let interface_constant = classfile.constant_pool.get_class(*cidx).unwrap();
-            let interface_name = classfile
-                .constant_pool
-                .get_utf8(interface_constant.name_index)
-                .unwrap();
-            if let Some(method_index) = table_inner.interfaces.get(&*interface_name) {
+        for interface_name in classfile.interface_names()? {
+            let interface_name = StrBuf::new(interface_name);
+            if let Some(method_index) = table_inner.interfaces.get(&interface_name) {
                 // skip interfaces that are already implemented by superclasses
                 if *method_index >= method_offset {
                     continue;
                 }
             }
             let interface_method_offset = table_inner.methods.len();
-            self.build_table(interface_name, table_inner, interface_method_offset)?;
+            self.build_table(&interface_name, table_inner, interface_method_offset)?;
             table_inner
                 .interfaces
-                .insert(interface_name.clone(), interface_method_offset);
+                .insert(interface_name, interface_method_offset);
         }
 
         for method in classfile.methods.iter() {
@@ -234,3 +253,135 @@ impl VTableMap {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use classfile::descriptors::ReturnTypeDescriptor;
+    use classfile::FieldType;
+
+    use frontend::test_util::{
+        TestClass, TestClassLoader, ACC_ABSTRACT, ACC_INTERFACE, ACC_PUBLIC,
+    };
+
+    #[test]
+    fn overriding_method_reuses_inherited_slot() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                methods: vec![("hashCode", "()I"), ("toString", "()Ljava/lang/String;")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Test",
+                super_name: Some("java/lang/Object"),
+                methods: vec![("<init>", "()V"), ("toString", "()Ljava/lang/String;")],
+                ..TestClass::default()
+            },
+        ]));
+        let vtables = VTableMap::new(classes);
+
+        let object_vtable = vtables.get(&StrBuf::new("java/lang/Object")).unwrap();
+        let vtable = vtables.get(&StrBuf::new("Test")).unwrap();
+
+        let descriptor = MethodDescriptor {
+            params: vec![],
+            ret: ReturnTypeDescriptor::Field(
+                FieldType::try_from_str("Ljava/lang/String;").unwrap(),
+            ),
+        };
+        let inherited = object_vtable.get("toString", &descriptor).unwrap();
+        let target = vtable.get("toString", &descriptor).unwrap();
+
+        assert_eq!(object_vtable.method_count(), vtable.method_count());
+        assert_eq!(inherited.method_index_lower, target.method_index_lower);
+        assert_eq!(inherited.method_index_upper, target.method_index_upper);
+        assert_eq!("java/lang/Object", &*inherited.class_name);
+        assert_eq!("Test", &*target.class_name);
+    }
+
+    #[test]
+    fn interface_only_referenced_by_name_is_loaded() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                methods: vec![("hashCode", "()I")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "java/util/Collection",
+                super_name: Some("java/lang/Object"),
+                access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+                methods: vec![("size", "()I")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "java/util/List",
+                super_name: Some("java/lang/Object"),
+                access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+                interfaces: vec!["java/util/Collection"],
+                methods: vec![("size", "()I"), ("isEmpty", "()Z")],
+                ..TestClass::default()
+            },
+        ]));
+        let vtables = VTableMap::new(classes);
+        let list = StrBuf::new("java/util/List");
+        let descriptor = MethodDescriptor {
+            params: vec![],
+            ret: ReturnTypeDescriptor::Field(FieldType::try_from_str("I").unwrap()),
+        };
+
+        let target = vtables.get_target(&list, "size", &descriptor).unwrap();
+        assert_eq!("java/util/Collection", &*target.class_name);
+        assert_eq!(0, target.method_index_lower);
+
+        let err = vtables
+            .get_target(&list, "hashCode", &descriptor)
+            .unwrap_err();
+        assert_eq!(
+            "no method hashCode in vtable of java/util/List",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn invalidate_evicts_implementing_classes() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass {
+                name: "java/lang/Object",
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Runnable",
+                super_name: Some("java/lang/Object"),
+                access_flags: ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT,
+                methods: vec![("run", "()V")],
+                ..TestClass::default()
+            },
+            TestClass {
+                name: "Task",
+                super_name: Some("java/lang/Object"),
+                interfaces: vec!["Runnable"],
+                methods: vec![("run", "()V")],
+                ..TestClass::default()
+            },
+        ]));
+        let vtables = VTableMap::new(classes);
+        let object = StrBuf::new("java/lang/Object");
+        let task = StrBuf::new("Task");
+        let object_vtable = vtables.get(&object).unwrap();
+        let task_vtable = vtables.get(&task).unwrap();
+
+        vtables.invalidate(&StrBuf::new("Runnable"));
+
+        assert!(Arc::ptr_eq(
+            &object_vtable.inner,
+            &vtables.get(&object).unwrap().inner
+        ));
+        assert!(!Arc::ptr_eq(
+            &task_vtable.inner,
+            &vtables.get(&task).unwrap().inner
+        ));
+    }
+}
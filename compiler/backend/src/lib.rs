@@ -2,4 +2,5 @@ mod codegen;
 mod layout;
 mod mangle;
 
-pub use self::codegen::{ClassCodeGen, CodeGen, Target};
+pub use self::codegen::{ClassCodeGen, CodeGen, ModuleCodeGen, Target};
+pub use self::mangle::MangleScheme;
@@ -1,3 +1,21 @@
+//! Symbol names of the generated code.
+//!
+//! The runtime relies on the `Itanium` scheme, which names the class `a/b/C`
+//! with the nested name `N1a1b1CE`:
+//!
+//! - its vtable is `_ZTV` followed by the nested name, e.g.
+//!   `_ZTVN4java4lang6ObjectE` for `java/lang/Object`,
+//! - its class id range is `_Jrt_classid_` followed by the nested name (not
+//!   `_ZTI`, which would demangle as a C++ type info),
+//! - its static fields are nested in the class name, e.g. `_ZN1a1b1C5countE`,
+//! - its methods are nested in the class name as well, followed by a hash of
+//!   the signature and the parameter types, with `<init>` and `<clinit>`
+//!   named `init` and `clinit`.
+//!
+//! The functions of the runtime itself are prefixed with `_Jrt_` instead.
+//! The symbols defined for each class are also listed by the symbol manifest
+//! (see `CodeGen::gen_symbols`).
+
 use std::fmt::Write;
 use std::hash::{Hash, Hasher};
 
@@ -5,7 +23,71 @@ use classfile::descriptors::{BaseType, FieldType, ParameterDescriptor, ReturnTyp
 use fnv::FnvHasher;
 use idna::punycode;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MangleScheme {
+    /// Itanium-like symbols, as expected by the runtime.
+    Itanium,
+    /// Symbols that spell out the JVM names, for inspecting generated code.
+    /// They can't be linked with the runtime.
+    Readable,
+}
+
 pub fn mangle_method_name(
+    scheme: MangleScheme,
+    class_name: &str,
+    method_name: &str,
+    rettype: &ReturnTypeDescriptor,
+    params: &[ParameterDescriptor],
+) -> String {
+    match scheme {
+        MangleScheme::Itanium => itanium_method_name(class_name, method_name, rettype, params),
+        MangleScheme::Readable => readable_method_name(class_name, method_name, rettype, params),
+    }
+}
+
+pub fn mangle_field_name(scheme: MangleScheme, class_name: &str, field_name: &str) -> String {
+    match scheme {
+        MangleScheme::Itanium => itanium_field_name(class_name, field_name),
+        MangleScheme::Readable => format!(
+            "{}.{}",
+            readable_class_name(class_name),
+            escape_name(field_name, false)
+        ),
+    }
+}
+
+pub fn mangle_class_name(scheme: MangleScheme, class_name: &str) -> String {
+    match scheme {
+        MangleScheme::Itanium => itanium_class_name(class_name),
+        MangleScheme::Readable => readable_class_name(class_name),
+    }
+}
+
+pub fn mangle_vtable_name(scheme: MangleScheme, class_name: &str) -> String {
+    match scheme {
+        MangleScheme::Itanium => special_name("_ZTV", class_name),
+        MangleScheme::Readable => format!("{}-vtable", readable_class_name(class_name)),
+    }
+}
+
+pub fn mangle_class_id_name(scheme: MangleScheme, class_name: &str) -> String {
+    match scheme {
+        MangleScheme::Itanium => special_name("_Jrt_classid_", class_name),
+        MangleScheme::Readable => format!("{}-classid", readable_class_name(class_name)),
+    }
+}
+
+pub fn mangle_string_const_name(scheme: MangleScheme, class_name: &str, index: u16) -> String {
+    format!(".str.{}.{}", mangle_class_name(scheme, class_name), index)
+}
+
+/// Names the slot holding the monitor of a class, which is internal to the
+/// module of the class.
+pub fn mangle_class_monitor_name(scheme: MangleScheme, class_name: &str) -> String {
+    format!(".monitor.{}", mangle_class_name(scheme, class_name))
+}
+
+fn itanium_method_name(
     class_name: &str,
     method_name: &str,
     rettype: &ReturnTypeDescriptor,
@@ -26,7 +108,7 @@ pub fn mangle_method_name(
     }
 
     let mut hasher = FnvHasher::default();
-    (class_name, method_name, rettype, rettype).hash(&mut hasher);
+    (class_name, method_name, rettype, params).hash(&mut hasher);
     let hash = hasher.finish();
     write!(mangler.output, "Iu9J{:08x}E", hash as u32).unwrap();
 
@@ -48,7 +130,7 @@ pub fn mangle_method_name(
     mangler.output
 }
 
-pub fn mangle_field_name(class_name: &str, field_name: &str) -> String {
+fn itanium_field_name(class_name: &str, field_name: &str) -> String {
     let mut mangler = Mangler::new();
 
     mangler.nested_start();
@@ -64,7 +146,7 @@ pub fn mangle_field_name(class_name: &str, field_name: &str) -> String {
     mangler.output
 }
 
-pub fn mangle_class_name(class_name: &str) -> String {
+fn itanium_class_name(class_name: &str) -> String {
     let mut mangler = Mangler::new();
 
     mangler.nested_start();
@@ -78,10 +160,9 @@ pub fn mangle_class_name(class_name: &str) -> String {
     mangler.output
 }
 
-pub fn mangle_vtable_name(class_name: &str) -> String {
-    let mut mangler = Mangler::new();
-
-    mangler.output.push_str("TV");
+/// Appends the nested name of the class to the prefix.
+fn special_name(prefix: &str, class_name: &str) -> String {
+    let mut mangler = Mangler::with_prefix(prefix);
 
     mangler.nested_start();
     for ns in class_name.split('/') {
@@ -92,14 +173,92 @@ pub fn mangle_vtable_name(class_name: &str) -> String {
     mangler.output
 }
 
+fn readable_method_name(
+    class_name: &str,
+    method_name: &str,
+    rettype: &ReturnTypeDescriptor,
+    params: &[ParameterDescriptor],
+) -> String {
+    let mut output = readable_class_name(class_name);
+    output.push('.');
+    match method_name {
+        "<init>" => output.push_str("$init"),
+        "<clinit>" => output.push_str("$clinit"),
+        _ => output.push_str(&escape_name(method_name, false)),
+    }
+
+    // the descriptor is spelled out in full, so that overloads stay distinct
+    output.push('$');
+    for ParameterDescriptor::Field(field_type) in params {
+        readable_field_type(&mut output, field_type);
+    }
+    output.push('$');
+    match rettype {
+        ReturnTypeDescriptor::Void => output.push('V'),
+        ReturnTypeDescriptor::Field(ref field_type) => readable_field_type(&mut output, field_type),
+    };
+
+    output
+}
+
+fn readable_field_type(output: &mut String, mut field_type: &FieldType) {
+    loop {
+        match field_type {
+            FieldType::Base(_) | FieldType::Object(_) => break,
+            FieldType::Array(array_type) => {
+                output.push('_');
+                field_type = &*array_type.component_type;
+            }
+        }
+    }
+    match field_type {
+        FieldType::Object(object_type) => {
+            output.push('L');
+            output.push_str(&readable_class_name(&object_type.class_name));
+            output.push('-');
+        }
+        _ => output.push_str(&field_type.to_string()),
+    }
+}
+
+fn readable_class_name(class_name: &str) -> String {
+    class_name
+        .split('/')
+        .map(|ns| escape_name(ns, false))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Encodes characters outside of `[A-Za-z0-9_]` as `$` followed by two hex digits,
+/// which makes names valid unquoted LLVM identifiers. Non-ASCII characters are
+/// either kept (to be encoded by punycode) or escaped byte by byte.
+fn escape_name(name: &str, keep_non_ascii: bool) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for chr in name.chars() {
+        if chr.is_ascii_alphanumeric() || chr == '_' || (keep_non_ascii && !chr.is_ascii()) {
+            escaped.push(chr);
+        } else {
+            let mut buf = [0; 4];
+            for byte in chr.encode_utf8(&mut buf).bytes() {
+                write!(escaped, "${:02x}", byte).unwrap();
+            }
+        }
+    }
+    escaped
+}
+
 struct Mangler {
     output: String,
 }
 
 impl Mangler {
     fn new() -> Self {
+        Mangler::with_prefix("_Z")
+    }
+
+    fn with_prefix(prefix: &str) -> Self {
         Mangler {
-            output: "_Z".to_owned(),
+            output: prefix.to_owned(),
         }
     }
 
@@ -112,9 +271,11 @@ impl Mangler {
     }
 
     fn name(&mut self, name: &str) {
-        let mangled = punycode::encode_str(name).unwrap();
+        // escapes never contain `$$`, so it can stand in for the punycode delimiter
+        let escaped = escape_name(name, true);
+        let mangled = punycode::encode_str(&escaped).unwrap();
         let trimmed = mangled.trim_end_matches('-');
-        let replaced = trimmed.replace('-', "$");
+        let replaced = trimmed.replace('-', "$$");
         write!(self.output, "{}{}", replaced.len(), replaced).unwrap();
     }
 
@@ -136,7 +297,7 @@ impl Mangler {
                 }
                 FieldType::Object(object_type) => {
                     self.nested_start();
-                    for ns in object_type.class_name.split('.') {
+                    for ns in object_type.class_name.split('/') {
                         self.name(&ns);
                     }
                     self.nested_end();
@@ -172,10 +333,63 @@ mod tests {
         }};
     }
 
+    fn decode_name(mangled: &str) -> String {
+        let escaped = match mangled.find("$$") {
+            Some(idx) => {
+                punycode::decode_to_string(&format!("{}-{}", &mangled[..idx], &mangled[idx + 2..]))
+                    .unwrap()
+            }
+            None => mangled.to_owned(),
+        };
+        let mut bytes = vec![];
+        let mut rest = escaped.as_str();
+        while let Some(idx) = rest.find('$') {
+            bytes.extend_from_slice(&rest.as_bytes()[..idx]);
+            bytes.push(u8::from_str_radix(&rest[idx + 1..idx + 3], 16).unwrap());
+            rest = &rest[idx + 3..];
+        }
+        bytes.extend_from_slice(rest.as_bytes());
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn mangle_name(name: &str) -> String {
+        let mut mangler = Mangler::new();
+        mangler.name(name);
+        mangler
+            .output
+            .trim_start_matches("_Z")
+            .trim_start_matches(char::is_numeric)
+            .to_owned()
+    }
+
+    fn is_llvm_identifier(name: &str) -> bool {
+        Regex::new(r"^[-a-zA-Z$._][-a-zA-Z$._0-9]*$")
+            .unwrap()
+            .is_match(name)
+    }
+
+    fn mangle_overloads(scheme: MangleScheme) -> (String, String) {
+        let mangle_foo = |base_type| {
+            mangle_method_name(
+                scheme,
+                "Test",
+                "foo",
+                &ReturnTypeDescriptor::Void,
+                &[ParameterDescriptor::Field(FieldType::Base(base_type))],
+            )
+        };
+        (mangle_foo(BaseType::Int), mangle_foo(BaseType::Long))
+    }
+
     #[test]
     fn method_name_without_parameters() {
-        let mangled =
-            mangle_method_name("java/lang/Object", "wait", &ReturnTypeDescriptor::Void, &[]);
+        let mangled = mangle_method_name(
+            MangleScheme::Itanium,
+            "java/lang/Object",
+            "wait",
+            &ReturnTypeDescriptor::Void,
+            &[],
+        );
 
         assert_demangle_match!(
             r"^void java::lang::Object::wait<J[[:xdigit:]]+>\(\)$",
@@ -186,11 +400,12 @@ mod tests {
     #[test]
     fn method_name_with_object_parameter() {
         let mangled = mangle_method_name(
+            MangleScheme::Itanium,
             "java/lang/Object",
             "equals",
             &ReturnTypeDescriptor::Field(FieldType::Base(BaseType::Boolean)),
             &[ParameterDescriptor::Field(FieldType::Object(ObjectType {
-                class_name: "java.lang.Object".to_owned(),
+                class_name: "java/lang/Object".to_owned(),
             }))],
         );
 
@@ -203,12 +418,13 @@ mod tests {
     #[test]
     fn method_name_with_array_parameter() {
         let mangled = mangle_method_name(
+            MangleScheme::Itanium,
             "java/util/Arrays",
             "hashCode",
             &ReturnTypeDescriptor::Field(FieldType::Base(BaseType::Int)),
             &[ParameterDescriptor::Field(FieldType::Array(ArrayType {
                 component_type: Box::new(FieldType::Object(ObjectType {
-                    class_name: "java.lang.Object".to_owned(),
+                    class_name: "java/lang/Object".to_owned(),
                 })),
             }))],
         );
@@ -221,8 +437,128 @@ mod tests {
 
     #[test]
     fn vtable_name() {
-        let mangled = mangle_vtable_name("java/lang/Object");
+        let mangled = mangle_vtable_name(MangleScheme::Itanium, "java/lang/Object");
 
         assert_demangle_match!(r"^\{vtable\(java::lang::Object\)\}$", mangled);
     }
+
+    #[test]
+    fn class_id_name() {
+        let mangled = mangle_class_id_name(MangleScheme::Itanium, "java/lang/Object");
+
+        assert_eq!("_Jrt_classid_N4java4lang6ObjectE", mangled);
+        assert!(is_llvm_identifier(&mangled));
+    }
+
+    #[test]
+    fn method_name_init() {
+        let mangled = mangle_method_name(
+            MangleScheme::Itanium,
+            "java/lang/Object",
+            "<init>",
+            &ReturnTypeDescriptor::Void,
+            &[],
+        );
+
+        assert!(is_llvm_identifier(&mangled));
+        assert_demangle_match!(
+            r"^void java::lang::Object::init<J[[:xdigit:]]+>\(\)$",
+            mangled
+        );
+    }
+
+    #[test]
+    fn class_name_with_dollar() {
+        let mangled = mangle_class_name(MangleScheme::Itanium, "java/util/Map$Entry");
+
+        assert!(is_llvm_identifier(&mangled));
+        assert_demangle_match!(r"^java::util::Map\$24Entry$", mangled);
+    }
+
+    #[test]
+    fn names_are_reversible() {
+        for name in &[
+            "Map$Entry",
+            "a$24",
+            "caf\u{e9}",
+            "caf\u{e9}$",
+            "lambda$main$0",
+            "<clinit>",
+        ] {
+            let mangled = mangle_name(name);
+
+            assert!(is_llvm_identifier(&mangled), "{:?} is not valid", mangled);
+            assert_eq!(*name, decode_name(&mangled));
+        }
+    }
+
+    #[test]
+    fn itanium_overloads_are_distinct() {
+        let (int_overload, long_overload) = mangle_overloads(MangleScheme::Itanium);
+
+        assert_ne!(int_overload, long_overload);
+        assert!(is_llvm_identifier(&int_overload));
+        assert!(is_llvm_identifier(&long_overload));
+    }
+
+    #[test]
+    fn itanium_overloads_have_distinct_hashes() {
+        let (int_overload, long_overload) = mangle_overloads(MangleScheme::Itanium);
+        let hash = |mangled: &str| {
+            let start = mangled.find("Iu9J").unwrap() + 4;
+            mangled[start..start + 8].to_owned()
+        };
+
+        assert_ne!(hash(&int_overload), hash(&long_overload));
+    }
+
+    #[test]
+    fn readable_overloads_are_distinct() {
+        let (int_overload, long_overload) = mangle_overloads(MangleScheme::Readable);
+
+        assert_eq!("Test.foo$I$V", int_overload);
+        assert_eq!("Test.foo$J$V", long_overload);
+    }
+
+    #[test]
+    fn readable_method_name_with_object_parameter() {
+        let mangled = mangle_method_name(
+            MangleScheme::Readable,
+            "java/lang/Object",
+            "<init>",
+            &ReturnTypeDescriptor::Void,
+            &[ParameterDescriptor::Field(FieldType::Array(ArrayType {
+                component_type: Box::new(FieldType::Object(ObjectType {
+                    class_name: "java/lang/String".to_owned(),
+                })),
+            }))],
+        );
+
+        assert_eq!("java.lang.Object.$init$_Ljava.lang.String-$V", mangled);
+        assert!(is_llvm_identifier(&mangled));
+    }
+
+    #[test]
+    fn readable_names_are_escaped() {
+        let mangled = mangle_method_name(
+            MangleScheme::Readable,
+            "Outer$Inner",
+            "<clinit>",
+            &ReturnTypeDescriptor::Void,
+            &[ParameterDescriptor::Field(FieldType::Object(ObjectType {
+                class_name: "caf\u{e9}".to_owned(),
+            }))],
+        );
+
+        assert_eq!("Outer$24Inner.$clinit$Lcaf$c3$a9-$V", mangled);
+        assert!(is_llvm_identifier(&mangled));
+    }
+
+    #[test]
+    fn readable_vtable_name() {
+        let mangled = mangle_vtable_name(MangleScheme::Readable, "java/lang/Object");
+
+        assert_eq!("java.lang.Object-vtable", mangled);
+        assert!(is_llvm_identifier(&mangled));
+    }
 }
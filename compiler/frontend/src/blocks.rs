@@ -1,7 +1,11 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+use std::iter;
 
+use failure::{bail, Fallible};
 use petgraph::graph::NodeIndex;
 use petgraph::stable_graph::StableGraph;
+use petgraph::visit::DfsPostOrder;
 use petgraph::Direction;
 
 use crate::frame::StackAndLocals;
@@ -97,6 +101,22 @@ impl BlockGraph {
             .map(move |index| &self.inner[index])
     }
 
+    /// Returns the blocks reachable from the start block in reverse
+    /// postorder, so that each block comes after all of its dominators.
+    pub fn blocks_rpo(&self) -> Vec<&BasicBlock> {
+        let start = match self.addr_map.get(&BlockId::start()) {
+            Some(index) => *index,
+            None => return vec![],
+        };
+        let mut dfs = DfsPostOrder::new(&self.inner, start);
+        let mut order = vec![];
+        while let Some(index) = dfs.next(&self.inner) {
+            order.push(&self.inner[index]);
+        }
+        order.reverse();
+        order
+    }
+
     pub fn insert(&mut self, block: BasicBlock) {
         let address = block.address;
         let index = self.inner.add_node(block);
@@ -121,6 +141,59 @@ impl BlockGraph {
         }
     }
 
+    /// Removes blocks that do nothing but jump to another block, and redirects
+    /// their predecessors to the final target instead.
+    pub fn collapse_forwarding_blocks(&mut self) {
+        let forwards = self
+            .addr_map
+            .iter()
+            .filter_map(|(addr, index)| {
+                forwarding_target(&self.inner[*index]).map(|target| (*addr, target))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let resolve = |addr: BlockId| {
+            let mut target = addr;
+            for _ in 0..=forwards.len() {
+                match forwards.get(&target) {
+                    Some(next) => target = *next,
+                    None => return target,
+                }
+            }
+            // forwarding blocks that loop back onto themselves are left alone
+            addr
+        };
+
+        let mut referenced = BTreeSet::new();
+        for index in self.addr_map.values() {
+            if let BranchStub::Switch(ref mut switch) = self.inner[*index].branch_stub {
+                let mut taken = switch
+                    .cases
+                    .iter()
+                    .map(|(_, addr)| *addr)
+                    .chain(iter::once(switch.default))
+                    .collect::<BTreeSet<_>>();
+                let addrs = iter::once(&mut switch.default)
+                    .chain(switch.cases.iter_mut().map(|(_, addr)| addr));
+                for addr in addrs {
+                    let target = resolve(*addr);
+                    // never merge two edges of the same switch, so that phi
+                    // nodes keep a single operand per predecessor
+                    if target != *addr && taken.insert(target) {
+                        *addr = target;
+                    }
+                    referenced.insert(*addr);
+                }
+            }
+        }
+
+        for addr in forwards.keys() {
+            if !referenced.contains(addr) {
+                let index = self.addr_map.remove(addr).unwrap();
+                self.inner.remove_node(index);
+            }
+        }
+    }
+
     fn incoming_frames(
         &self,
         block: &BasicBlock,
@@ -141,6 +214,76 @@ impl BlockGraph {
         incoming_frames.chain(entry_frame)
     }
 
+    /// Checks that the stack operands flowing into each block have the same
+    /// computational types as the block's incoming stack, so that its phi
+    /// nodes are well-typed. Locals are not checked, since a slot may be reused with a
+    /// different type on each branch, and is bound to `undef` where it doesn't
+    /// match.
+    pub fn check_phi_types(&self) -> Fallible<()> {
+        for block in self.blocks() {
+            for (i, in_op) in block.incoming.stack.iter().enumerate() {
+                for (src, frame) in self.incoming_frames(block) {
+                    if let Some(op) = frame.stack.get(i) {
+                        if !in_op.get_type().can_unify_naive(&op.get_type()) {
+                            bail!(
+                                "stack slot {} entering block {} is {}, but {} from {:?}",
+                                i,
+                                block.address,
+                                in_op.get_type(),
+                                op.get_type(),
+                                src
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the incoming and outgoing frame of each block, with the type
+    /// of every operand, to help diagnose mismatched phi nodes.
+    pub fn dump_frames(&self) -> String {
+        let mut out = String::new();
+        for index in self.addr_map.values() {
+            let block = &self.inner[*index];
+            writeln!(out, "block {}", block.address).unwrap();
+            write_frame(&mut out, "in: ", &block.incoming).unwrap();
+            write_frame(&mut out, "out:", &block.outgoing).unwrap();
+        }
+        out
+    }
+
+    /// Renders the type of every variable defined in the method, whether it
+    /// enters a block or is assigned by a statement, to help diagnose type
+    /// propagation issues.
+    pub fn dump_types(&self) -> String {
+        let mut types = BTreeMap::new();
+        let frames =
+            iter::once(&self.entry_state).chain(self.blocks().map(|block| &block.incoming));
+        for frame in frames {
+            for op in frame.stack.iter().chain(frame.locals.values()) {
+                if let Op::Var(var) = op {
+                    types.insert(var.1, &var.0);
+                }
+            }
+        }
+        for block in self.blocks() {
+            for var in block
+                .statements
+                .iter()
+                .filter_map(|stmt| stmt.assign.as_ref())
+            {
+                types.insert(var.1, &var.0);
+            }
+        }
+        let mut out = String::new();
+        for (id, typ) in types {
+            writeln!(out, "v{}: {}", id, typ).unwrap();
+        }
+        out
+    }
+
     pub fn phis(&self, block: &BasicBlock) -> impl Iterator<Item = PhiNode> {
         log::trace!(
             "collecting phi nodes for block at address {}",
@@ -175,3 +318,40 @@ impl BlockGraph {
         nodes.into_iter()
     }
 }
+
+fn forwarding_target(block: &BasicBlock) -> Option<BlockId> {
+    if block.address == BlockId::start()
+        || !block.statements.is_empty()
+        || block.exceptions.is_some()
+        || block.incoming != block.outgoing
+    {
+        return None;
+    }
+    match block.branch_stub {
+        BranchStub::Switch(ref switch)
+            if switch.cases.is_empty() && switch.default != block.address =>
+        {
+            Some(switch.default)
+        }
+        _ => None,
+    }
+}
+
+fn write_frame(out: &mut String, label: &str, frame: &StackAndLocals) -> fmt::Result {
+    write!(out, "  {} stack [", label)?;
+    for (i, op) in frame.stack.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write!(out, "{} {}", op, op.get_type())?;
+    }
+    out.push_str("], locals [");
+    for (i, (idx, op)) in frame.locals.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write!(out, "{}: {} {}", idx, op, op.get_type())?;
+    }
+    out.push_str("]\n");
+    Ok(())
+}
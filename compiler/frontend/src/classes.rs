@@ -1,9 +1,12 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 
-use failure::Fallible;
+use classfile::descriptors::{FieldType, MethodDescriptor};
+use failure::{bail, format_err, Error, Fallible};
 use petgraph::graph::NodeIndex;
 use petgraph::stable_graph::StableGraph;
+use petgraph::Direction;
 use strbuf::StrBuf;
 
 use crate::loader::{ArrayClass, Class, ClassLoader};
@@ -39,8 +42,13 @@ impl Inner {
                         .constant_pool
                         .get_utf8(super_class_const.name_index)
                         .unwrap();
-                    let super_class = loader.load(&super_class_name)?;
-                    let super_index = self.add_class(&super_class_name, super_class, loader)?;
+                    let super_index = match self.name_map.get(super_class_name).cloned() {
+                        Some(super_index) => super_index,
+                        None => {
+                            let super_class = loader.load(super_class_name)?;
+                            self.add_class(super_class_name, super_class, loader)?
+                        }
+                    };
                     self.graph.add_edge(index, super_index, Relation::Extends);
                 }
             }
@@ -50,6 +58,126 @@ impl Inner {
     }
 }
 
+struct PreloadState {
+    pending: Vec<StrBuf>,
+    seen: HashSet<StrBuf>,
+    in_flight: usize,
+    loaded: HashMap<StrBuf, Class>,
+    error: Option<Error>,
+}
+
+/// A work queue shared between the threads of `ClassGraph::preload`.
+struct PreloadQueue {
+    state: Mutex<PreloadState>,
+    ready: Condvar,
+}
+
+impl PreloadQueue {
+    fn new(pending: Vec<StrBuf>, resident: HashSet<StrBuf>) -> Self {
+        let mut seen = resident;
+        seen.extend(pending.iter().cloned());
+        let state = PreloadState {
+            pending,
+            seen,
+            in_flight: 0,
+            loaded: HashMap::new(),
+            error: None,
+        };
+        PreloadQueue {
+            state: Mutex::new(state),
+            ready: Condvar::new(),
+        }
+    }
+
+    fn next(&self) -> Option<StrBuf> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.error.is_some() {
+                return None;
+            }
+            if let Some(name) = state.pending.pop() {
+                state.in_flight += 1;
+                return Some(name);
+            }
+            if state.in_flight == 0 {
+                return None;
+            }
+            state = self.ready.wait(state).unwrap();
+        }
+    }
+
+    fn run(&self, loader: &dyn ClassLoader) {
+        while let Some(name) = self.next() {
+            let result = loader.load(&name).and_then(|class| {
+                let dependencies = dependencies(&class)?;
+                Ok((class, dependencies))
+            });
+            let mut state = self.state.lock().unwrap();
+            state.in_flight -= 1;
+            match result {
+                Ok((class, dependencies)) => {
+                    for dependency in dependencies {
+                        // each class is only ever loaded by a single thread
+                        if state.seen.insert(dependency.clone()) {
+                            state.pending.push(dependency);
+                        }
+                    }
+                    state.loaded.insert(name, class);
+                }
+                Err(err) => {
+                    if state.error.is_none() {
+                        state.error = Some(err);
+                    }
+                }
+            }
+            self.ready.notify_all();
+        }
+    }
+
+    fn finish(self) -> Fallible<HashMap<StrBuf, Class>> {
+        let state = self.state.into_inner().unwrap();
+        match state.error {
+            Some(err) => Err(err),
+            None => Ok(state.loaded),
+        }
+    }
+}
+
+fn dependencies(class: &Class) -> Fallible<Vec<StrBuf>> {
+    match class {
+        Class::File(class_file) => {
+            let consts = &class_file.constant_pool;
+            let indices = class_file.super_class.iter().chain(&class_file.interfaces);
+            let mut names = vec![];
+            for index in indices {
+                let name = consts
+                    .get_class(*index)
+                    .and_then(|class_const| consts.get_utf8(class_const.name_index))
+                    .ok_or_else(|| format_err!("supertype #{} is not a class", index.into_u16()))?;
+                names.push(name.clone());
+            }
+            Ok(names)
+        }
+        Class::Array(_) => Ok(vec![]),
+    }
+}
+
+/// Serves classes loaded by `ClassGraph::preload`, falling back to the
+/// graph's own loader for anything else.
+struct PreloadedClassLoader<'a> {
+    classes: &'a HashMap<StrBuf, Class>,
+    parent: &'a dyn ClassLoader,
+}
+
+impl<'a> ClassLoader for PreloadedClassLoader<'a> {
+    fn load(&self, name: &str) -> Fallible<Class> {
+        match self.classes.get(name) {
+            Some(class) => Ok(class.clone()),
+            None => self.parent.load(name),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ClassGraph {
     inner: Arc<Mutex<Inner>>,
@@ -81,4 +209,351 @@ impl ClassGraph {
             Ok(inner.graph[idx].clone())
         }
     }
+
+    /// Loads the given classes along with their superclasses and interfaces
+    /// on a pool of threads, so that later lookups find them resident.
+    ///
+    /// The `BootstrapClassLoader` serializes reading and inflating entries
+    /// behind a single lock, so only the decoding of class files runs in
+    /// parallel, and adding threads beyond that doesn't help.
+    pub fn preload(&self, names: &[StrBuf]) -> Fallible<()> {
+        let resident = {
+            let inner = self.inner.lock().unwrap();
+            inner.name_map.keys().cloned().collect::<HashSet<_>>()
+        };
+        let pending = names
+            .iter()
+            .filter(|name| !resident.contains(*name))
+            .cloned()
+            .collect();
+        let queue = Arc::new(PreloadQueue::new(pending, resident));
+
+        let workers = (0..num_cpus::get())
+            .map(|_| {
+                let queue = queue.clone();
+                let loader = self.loader.clone();
+                thread::spawn(move || queue.run(&*loader))
+            })
+            .collect::<Vec<_>>();
+        for worker in workers {
+            worker.join().expect("preload thread panicked");
+        }
+
+        let queue = match Arc::try_unwrap(queue) {
+            Ok(queue) => queue,
+            Err(_) => unreachable!("preload threads have finished"),
+        };
+        let loaded = queue.finish()?;
+        let preloaded = PreloadedClassLoader {
+            classes: &loaded,
+            parent: &*self.loader,
+        };
+        let mut inner = self.inner.lock().unwrap();
+        for (name, class) in loaded.iter() {
+            if !inner.name_map.contains_key(name) {
+                inner.add_class(name, class.clone(), &preloaded)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Evicts a class along with all classes extending it, so that the next
+    /// lookup loads them again. Returns the names of the evicted classes.
+    pub fn invalidate(&self, name: &StrBuf) -> Vec<StrBuf> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut pending = match inner.name_map.get(name) {
+            Some(idx) => vec![*idx],
+            None => return vec![],
+        };
+        let mut evicted = HashSet::new();
+        while let Some(idx) = pending.pop() {
+            if evicted.insert(idx) {
+                pending.extend(inner.graph.neighbors_directed(idx, Direction::Incoming));
+            }
+        }
+        let names = inner
+            .name_map
+            .iter()
+            .filter(|(_, idx)| evicted.contains(idx))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        for name in names.iter() {
+            inner.name_map.remove(name);
+        }
+        for idx in evicted {
+            inner.graph.remove_node(idx);
+        }
+        names
+    }
+
+    pub fn superclasses(&self, name: &StrBuf) -> Fallible<Vec<StrBuf>> {
+        let mut chain: Vec<StrBuf> = vec![];
+        let mut next = Some(name.clone());
+        while let Some(class_name) = next {
+            if chain.contains(&class_name) {
+                bail!("cyclic superclass chain through {}", class_name);
+            }
+            next = match self.get(&class_name)? {
+                Class::File(class_file) => class_file.get_super_class().map(|super_class| {
+                    class_file
+                        .constant_pool
+                        .get_utf8(super_class.name_index)
+                        .unwrap()
+                        .clone()
+                }),
+                Class::Array(_) => Some(StrBuf::new("java/lang/Object")),
+            };
+            chain.push(class_name);
+        }
+        Ok(chain)
+    }
+
+    /// Finds the class declaring `method_name`, starting the search at
+    /// `class_name` and walking up its superclasses.
+    pub fn resolve_method(
+        &self,
+        class_name: &StrBuf,
+        method_name: &str,
+        descriptor: &MethodDescriptor,
+    ) -> Fallible<StrBuf> {
+        for candidate in self.superclasses(class_name)? {
+            if let Class::File(class_file) = self.get(&candidate)? {
+                let declares_method = class_file.methods.iter().any(|method| {
+                    let name = class_file.constant_pool.get_utf8(method.name_index);
+                    name.map(|name| &**name == method_name).unwrap_or(false)
+                        && method.descriptor == *descriptor
+                });
+                if declares_method {
+                    return Ok(candidate);
+                }
+            }
+        }
+        bail!("no such method {}.{}", class_name, method_name)
+    }
+
+    /// Finds the class declaring the static field `field_name`, starting the
+    /// search at `class_name` and walking up its superclasses.
+    pub fn resolve_static_field(
+        &self,
+        class_name: &StrBuf,
+        field_name: &str,
+        descriptor: &FieldType,
+    ) -> Fallible<StrBuf> {
+        for candidate in self.superclasses(class_name)? {
+            if let Class::File(class_file) = self.get(&candidate)? {
+                let declares_field = class_file.fields.iter().any(|field| {
+                    let name = class_file.constant_pool.get_utf8(field.name_index);
+                    field.is_static()
+                        && name.map(|name| &**name == field_name).unwrap_or(false)
+                        && field.descriptor == *descriptor
+                });
+                if declares_field {
+                    return Ok(candidate);
+                }
+            }
+        }
+        bail!("no such static field {}.{}", class_name, field_name)
+    }
+
+    /// Finds the class declaring the method that an `invokespecial` of
+    /// `class_name.method_name` from within `current` calls.
+    pub fn resolve_special(
+        &self,
+        current: &StrBuf,
+        class_name: &StrBuf,
+        method_name: &str,
+        descriptor: &MethodDescriptor,
+    ) -> Fallible<StrBuf> {
+        let current_class = match self.get(current)? {
+            Class::File(class_file) => class_file,
+            Class::Array(_) => bail!("unexpected array class {}", current),
+        };
+        if method_name != "<init>" && current_class.uses_super_semantics() {
+            let superclasses = self.superclasses(current)?;
+            if superclasses[1..].contains(class_name) {
+                return self.resolve_method(&superclasses[1], method_name, descriptor);
+            }
+        }
+        self.resolve_method(class_name, method_name, descriptor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use classfile::descriptors::ReturnTypeDescriptor;
+
+    use crate::test_util::{TestClass, TestClassLoader, ACC_PUBLIC, ACC_SUPER};
+
+    #[test]
+    fn superclasses_three_levels() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass::new("java/lang/Object", None),
+            TestClass::new("Base", Some("java/lang/Object")),
+            TestClass::new("Derived", Some("Base")),
+        ]));
+
+        let chain = classes.superclasses(&StrBuf::new("Derived")).unwrap();
+
+        assert_eq!(
+            vec!["Derived", "Base", "java/lang/Object"],
+            chain.iter().map(|name| &**name).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn superclasses_cyclic() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass::new("A", Some("B")),
+            TestClass::new("B", Some("A")),
+        ]));
+
+        assert!(classes.superclasses(&StrBuf::new("A")).is_err());
+    }
+
+    #[test]
+    fn preload_loads_superclasses() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass::new("java/lang/Object", None),
+            TestClass::new("Base", Some("java/lang/Object")),
+            TestClass::new("Derived", Some("Base")),
+            TestClass::new("Other", Some("Base")),
+        ]));
+
+        classes
+            .preload(&[StrBuf::new("Derived"), StrBuf::new("Other")])
+            .unwrap();
+
+        let inner = classes.inner.lock().unwrap();
+        let mut names = inner
+            .name_map
+            .keys()
+            .map(|name| &**name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(vec!["Base", "Derived", "Other", "java/lang/Object"], names);
+        assert_eq!(4, inner.graph.node_count());
+        assert_eq!(3, inner.graph.edge_count());
+    }
+
+    #[test]
+    fn invalidate_reloads_class_and_subclasses() {
+        let classes = ClassGraph::new(TestClassLoader(vec![
+            TestClass::new("java/lang/Object", None),
+            TestClass::new("Base", Some("java/lang/Object")),
+            TestClass::new("Derived", Some("Base")),
+        ]));
+        let get = |name| match classes.get(&StrBuf::new(name)).unwrap() {
+            Class::File(class_file) => class_file,
+            class => panic!("unexpected class {:?}", class),
+        };
+        get("Derived");
+        let base = get("Base");
+        let object = get("java/lang/Object");
+        assert!(Arc::ptr_eq(&base, &get("Base")));
+
+        let mut evicted = classes.invalidate(&StrBuf::new("Base"));
+        evicted.sort();
+        assert_eq!(
+            vec!["Base", "Derived"],
+            evicted.iter().map(|name| &**name).collect::<Vec<_>>()
+        );
+
+        assert!(!Arc::ptr_eq(&base, &get("Base")));
+        assert!(Arc::ptr_eq(&object, &get("java/lang/Object")));
+        let chain = classes.superclasses(&StrBuf::new("Derived")).unwrap();
+        assert_eq!(3, chain.len());
+    }
+
+    #[test]
+    fn preload_missing_class() {
+        let classes = ClassGraph::new(TestClassLoader(vec![TestClass::new(
+            "Derived",
+            Some("Base"),
+        )]));
+
+        let err = classes.preload(&[StrBuf::new("Derived")]).unwrap_err();
+        assert_eq!("class Base not found", err.to_string());
+        assert!(classes.inner.lock().unwrap().name_map.is_empty());
+    }
+
+    fn special_classes(derived_flags: u16) -> ClassGraph {
+        ClassGraph::new(TestClassLoader(vec![
+            TestClass::new("java/lang/Object", None),
+            TestClass {
+                methods: vec![("<init>", "()V"), ("run", "()V")],
+                ..TestClass::new("Base", Some("java/lang/Object"))
+            },
+            TestClass {
+                methods: vec![("<init>", "()V"), ("run", "()V")],
+                ..TestClass::new("Middle", Some("Base"))
+            },
+            TestClass {
+                access_flags: derived_flags,
+                ..TestClass::new("Derived", Some("Middle"))
+            },
+        ]))
+    }
+
+    fn resolve_special(classes: &ClassGraph, class_name: &str, method_name: &str) -> StrBuf {
+        let descriptor = MethodDescriptor {
+            params: vec![],
+            ret: ReturnTypeDescriptor::Void,
+        };
+        classes
+            .resolve_special(
+                &StrBuf::new("Derived"),
+                &StrBuf::new(class_name),
+                method_name,
+                &descriptor,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn resolve_special_with_super_flag() {
+        let classes = special_classes(ACC_PUBLIC | ACC_SUPER);
+
+        assert_eq!("Middle", &*resolve_special(&classes, "Base", "run"));
+        assert_eq!("Base", &*resolve_special(&classes, "Base", "<init>"));
+    }
+
+    #[test]
+    fn resolve_special_without_super_flag() {
+        let classes = special_classes(ACC_PUBLIC);
+
+        assert_eq!("Base", &*resolve_special(&classes, "Base", "run"));
+        assert_eq!("Base", &*resolve_special(&classes, "Base", "<init>"));
+    }
+
+    #[test]
+    fn resolve_inherited_static_method() {
+        let classes = special_classes(ACC_PUBLIC | ACC_SUPER);
+        let descriptor = MethodDescriptor {
+            params: vec![],
+            ret: ReturnTypeDescriptor::Void,
+        };
+
+        let target = classes
+            .resolve_method(&StrBuf::new("Derived"), "run", &descriptor)
+            .unwrap();
+
+        assert_eq!("Middle", &*target);
+    }
+
+    #[test]
+    fn resolve_missing_static_method() {
+        let classes = special_classes(ACC_PUBLIC | ACC_SUPER);
+        let descriptor = MethodDescriptor {
+            params: vec![],
+            ret: ReturnTypeDescriptor::Void,
+        };
+
+        let err = classes
+            .resolve_method(&StrBuf::new("Derived"), "missing", &descriptor)
+            .unwrap_err();
+
+        assert_eq!("no such method Derived.missing", err.to_string());
+    }
 }
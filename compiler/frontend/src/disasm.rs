@@ -1,9 +1,12 @@
 use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use std::ops::Range;
 
 use classfile::instructions::{Disassembler, Instr};
 use failure::{bail, Fallible};
 
+use crate::translate::BlockId;
+
 pub struct InstructionWithRange {
     pub range: Range<u32>,
     pub instr: Instr,
@@ -15,6 +18,12 @@ pub struct InstructionBlock {
 }
 
 impl InstructionBlock {
+    pub fn iter_pc(&self) -> impl Iterator<Item = (u32, &Instr)> {
+        self.instrs
+            .iter()
+            .map(|instr| (instr.range.start, &instr.instr))
+    }
+
     fn split(&mut self, addr: u32) -> InstructionBlock {
         let index = self
             .instrs
@@ -34,15 +43,24 @@ impl InstructionBlock {
 
     fn build(disasm: &mut Disassembler, start_addrs: &mut Vec<u32>) -> Fallible<Self> {
         let start_addr = disasm.position();
+        let code_len = disasm.code_len();
+        let branch_target = |addr: u32, offset: i32| {
+            BlockId::try_from_addr_with_offset(addr, offset, code_len).map(BlockId::addr)
+        };
         let mut instrs = vec![];
         while let Some((curr_addr, instr)) = disasm.decode_next()? {
             log::trace!("decoded instruction {:?} at address {}", instr, curr_addr);
             let next_addr = disasm.position();
             let should_break = match instr {
-                Instr::Return | Instr::IReturn | Instr::AReturn | Instr::AThrow => true,
+                Instr::Return
+                | Instr::IReturn
+                | Instr::LReturn
+                | Instr::FReturn
+                | Instr::DReturn
+                | Instr::AReturn
+                | Instr::AThrow => true,
                 Instr::Goto(offset) => {
-                    let addr = (i64::from(curr_addr) + i64::from(offset)) as u32;
-                    start_addrs.push(addr);
+                    start_addrs.push(branch_target(curr_addr, i32::from(offset))?);
                     true
                 }
                 Instr::IfLe(offset)
@@ -61,23 +79,21 @@ impl InstructionBlock {
                 | Instr::IfACmpNe(offset)
                 | Instr::IfNull(offset)
                 | Instr::IfNonNull(offset) => {
-                    let if_addr = (i64::from(curr_addr) + i64::from(offset)) as u32;
+                    let if_addr = branch_target(curr_addr, i32::from(offset))?;
                     start_addrs.extend_from_slice(&[next_addr, if_addr]);
                     true
                 }
                 Instr::TableSwitch(ref table_switch) => {
-                    start_addrs
-                        .push((i64::from(curr_addr) + i64::from(table_switch.default)) as u32);
+                    start_addrs.push(branch_target(curr_addr, table_switch.default)?);
                     for offset in table_switch.offsets.iter() {
-                        start_addrs.push((i64::from(curr_addr) + i64::from(*offset)) as u32);
+                        start_addrs.push(branch_target(curr_addr, *offset)?);
                     }
                     true
                 }
                 Instr::LookupSwitch(ref lookup_switch) => {
-                    start_addrs
-                        .push((i64::from(curr_addr) + i64::from(lookup_switch.default)) as u32);
+                    start_addrs.push(branch_target(curr_addr, lookup_switch.default)?);
                     for (_, offset) in lookup_switch.pairs.iter() {
-                        start_addrs.push((i64::from(curr_addr) + i64::from(*offset)) as u32);
+                        start_addrs.push(branch_target(curr_addr, *offset)?);
                     }
                     true
                 }
@@ -108,6 +124,7 @@ impl InstructionBlock {
 
 pub struct InstructionBlockMap {
     blocks: Vec<InstructionBlock>,
+    leaders: BTreeSet<u32>,
 }
 
 impl InstructionBlockMap {
@@ -122,11 +139,19 @@ impl InstructionBlockMap {
         }
     }
 
+    /// The addresses at which blocks start: the method entry, branch targets
+    /// and the instructions following conditional branches.
+    pub fn leaders(&self) -> &BTreeSet<u32> {
+        &self.leaders
+    }
+
     pub fn build(mut disasm: Disassembler) -> Fallible<Self> {
         let mut blocks = vec![];
+        let mut leaders = BTreeSet::new();
 
         let mut start_addrs = vec![0u32];
         while let Some(start_addr) = start_addrs.pop() {
+            leaders.insert(start_addr);
             let search_result = blocks.binary_search_by(|block: &InstructionBlock| {
                 if block.range.end <= start_addr {
                     Ordering::Less
@@ -159,6 +184,39 @@ impl InstructionBlockMap {
             };
         }
 
-        Ok(InstructionBlockMap { blocks })
+        Ok(InstructionBlockMap { blocks, leaders })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iter_pc_yields_range_starts() {
+        let block = InstructionBlock {
+            range: 4..10,
+            instrs: vec![
+                InstructionWithRange {
+                    range: 4..6,
+                    instr: Instr::BiPush(42),
+                },
+                InstructionWithRange {
+                    range: 6..9,
+                    instr: Instr::SiPush(1000),
+                },
+                InstructionWithRange {
+                    range: 9..10,
+                    instr: Instr::IReturn,
+                },
+            ],
+        };
+
+        let pcs = block.iter_pc().map(|(pc, _)| pc).collect::<Vec<_>>();
+        assert_eq!(vec![4, 6, 9], pcs);
+        for ((pc, instr), instr_with_range) in block.iter_pc().zip(block.instrs.iter()) {
+            assert_eq!(instr_with_range.range.start, pc);
+            assert!(std::ptr::eq(&instr_with_range.instr, instr));
+        }
     }
 }
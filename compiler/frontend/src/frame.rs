@@ -1,9 +1,12 @@
 use std::collections::BTreeMap;
+use std::fmt;
+
+use failure::{bail, Fallible};
 
 use crate::translate::{Op, VarId, VarIdGen};
 use crate::types::Type;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct StackAndLocals {
     pub stack: Vec<Op>,
     pub locals: BTreeMap<usize, Op>,
@@ -17,7 +20,7 @@ impl StackAndLocals {
         for arg in args.iter() {
             locals.insert(next_local_idx, Op::Var(arg.clone()));
             // long and double occupy two local slots
-            if arg.0 == Type::Long || arg.0 == Type::Double {
+            if is_category_2(&arg.0) {
                 next_local_idx += 2;
             } else {
                 next_local_idx += 1;
@@ -53,18 +56,69 @@ impl StackAndLocals {
         self.stack.push(var);
     }
 
-    pub fn load(&mut self, idx: usize) {
-        match self.locals.get(&idx) {
-            Some(var) => self.stack.push(var.clone()),
-            None => panic!("local slot {} is empty ({:?})", idx, self.locals),
+    pub fn load(&mut self, idx: usize) -> Fallible<()> {
+        if let Some(var) = self.locals.get(&idx) {
+            self.stack.push(var.clone());
+            return Ok(());
+        }
+        if let Some(wide_var) = self.wide_local_before(idx) {
+            bail!(
+                "local slot {} is reserved by the {} in slot {}",
+                idx,
+                wide_var.get_type(),
+                idx - 1
+            );
         }
+        bail!("local slot {} is empty ({:?})", idx, self.locals)
     }
 
     pub fn store(&mut self, idx: usize) {
-        self.locals.insert(idx, self.stack.pop().unwrap());
+        let var = self.stack.pop().unwrap();
+        // a long or double reserves the slot after it, and storing into
+        // that reserved slot invalidates the long or double before it
+        if is_category_2(&var.get_type()) {
+            self.locals.remove(&(idx + 1));
+        }
+        if self.wide_local_before(idx).is_some() {
+            self.locals.remove(&(idx - 1));
+        }
+        self.locals.insert(idx, var);
+    }
+
+    fn wide_local_before(&self, idx: usize) -> Option<&Op> {
+        if idx == 0 {
+            return None;
+        }
+        self.locals
+            .get(&(idx - 1))
+            .filter(|var| is_category_2(&var.get_type()))
     }
 }
 
+impl fmt::Display for StackAndLocals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("(stack [")?;
+        for (i, op) in self.stack.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", op)?;
+        }
+        f.write_str("], locals [")?;
+        for (i, (idx, op)) in self.locals.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", idx, op)?;
+        }
+        f.write_str("])")
+    }
+}
+
+pub(crate) fn is_category_2(typ: &Type) -> bool {
+    *typ == Type::Long || *typ == Type::Double
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +141,35 @@ mod tests {
         assert_eq!(frame.locals[&3].get_type(), Type::Double);
         assert_eq!(frame.locals[&5].get_type(), Type::Float);
     }
+
+    #[test]
+    fn store_long_reserves_next_slot() {
+        let mut gen = VarIdGen::default();
+        let args = vec![gen.gen(Type::Int), gen.gen(Type::Int)];
+        let mut frame = StackAndLocals::new(2, 3, &args);
+
+        frame.push(Op::Var(gen.gen(Type::Long)));
+        frame.store(1);
+
+        assert_eq!(frame.locals[&1].get_type(), Type::Long);
+        assert!(!frame.locals.contains_key(&2));
+        assert_eq!(
+            "local slot 2 is reserved by the long in slot 1",
+            frame.load(2).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn store_into_reserved_slot_invalidates_long() {
+        let mut gen = VarIdGen::default();
+        let args = vec![gen.gen(Type::Int), gen.gen(Type::Long)];
+        let mut frame = StackAndLocals::new(2, 3, &args);
+
+        frame.push(Op::Var(gen.gen(Type::Int)));
+        frame.store(2);
+
+        assert!(!frame.locals.contains_key(&1));
+        assert_eq!(frame.locals[&2].get_type(), Type::Int);
+        assert!(frame.load(1).is_err());
+    }
 }
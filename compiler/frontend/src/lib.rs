@@ -3,5 +3,7 @@ pub mod classes;
 pub mod disasm;
 pub mod frame;
 pub mod loader;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod translate;
 pub mod types;
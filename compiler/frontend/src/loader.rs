@@ -1,11 +1,11 @@
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 use classfile::descriptors::{BaseType, FieldType};
 use classfile::ClassFile;
-use failure::{format_err, Fallible};
+use failure::{bail, format_err, Fallible};
 use jar::{ClassEntry, JarReader};
 use strbuf::StrBuf;
 
@@ -64,16 +64,36 @@ pub struct BootstrapClassLoader {
 
 impl BootstrapClassLoader {
     pub fn open<P: AsRef<Path>>(home: P) -> Fallible<Self> {
-        let paths = &[
-            home.as_ref().join("jre/lib/rt.jar"),
-            home.as_ref().join("jre/lib/jce.jar"),
-        ];
+        let home = home.as_ref();
+        let jmods_path = home.join("jmods");
 
         let mut readers = vec![];
-        for path in paths {
-            let file = File::open(path)?;
-            let reader = JarReader::try_new(file)?;
-            readers.push(reader);
+        if jmods_path.is_dir() {
+            // since java 9, the class library is split into jmod files, which
+            // are zip archives that keep their classes below `classes/`
+            let mut paths = vec![];
+            for entry in fs::read_dir(jmods_path)? {
+                let path = entry?.path();
+                if path.extension().map(|ext| ext == "jmod").unwrap_or(false) {
+                    paths.push(path);
+                }
+            }
+            paths.sort();
+            for path in paths {
+                let reader = JarReader::open(path)?.with_class_prefix("classes/");
+                readers.push(reader);
+            }
+        } else {
+            let paths = &[home.join("jre/lib/rt.jar"), home.join("jre/lib/jce.jar")];
+            for path in paths {
+                let file = File::open(path)?;
+                let reader = JarReader::try_new(file)?;
+                readers.push(reader);
+            }
+        }
+
+        if readers.is_empty() {
+            bail!("no class library found in {}", home.display());
         }
 
         Ok(Self {
@@ -99,8 +119,7 @@ impl BootstrapClassLoader {
                 Ok(ArrayClass::Complex(Box::new(Class::Array(inner))))
             }
             FieldType::Object(object_type) => {
-                let class_name = object_type.class_name.replace(".", "/");
-                let class_entry = self.load_entry_from_disk(&class_name)?;
+                let class_entry = self.load_entry_from_disk(&object_type.class_name)?;
                 let class_file = class_entry.decode()?;
                 let class = Class::File(Arc::new(class_file));
                 Ok(ArrayClass::Complex(Box::new(class)))
@@ -123,3 +142,49 @@ impl ClassLoader for BootstrapClassLoader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::io::Write;
+
+    use classfile::test_util::ClassBuilder;
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+
+    #[test]
+    fn bootstrap_loads_object_from_jmods() {
+        let home = tempfile::tempdir().unwrap();
+        let jmods_path = home.path().join("jmods");
+        fs::create_dir(&jmods_path).unwrap();
+        let file = File::create(jmods_path.join("java.base.jmod")).unwrap();
+        let mut writer = ZipWriter::new(file);
+        writer
+            .start_file("classes/java/lang/Object.class", FileOptions::default())
+            .unwrap();
+        let object = ClassBuilder::with_super("java/lang/Object", None).to_bytes();
+        writer.write_all(&object).unwrap();
+        writer.finish().unwrap();
+
+        let loader = BootstrapClassLoader::open(home.path()).unwrap();
+
+        match loader.load("java/lang/Object").unwrap() {
+            Class::File(class_file) => assert_eq!("java/lang/Object", &**class_file.get_name()),
+            class => panic!("unexpected class {:?}", class),
+        }
+        assert!(loader.load("java/lang/String").is_err());
+    }
+
+    #[test]
+    #[ignore] // needs a JDK at JAVA_HOME, run with `cargo test -- --ignored`
+    fn bootstrap_loads_object_from_java_home() {
+        let home = env::var_os("JAVA_HOME").expect("JAVA_HOME is not set");
+        let loader = BootstrapClassLoader::open(home).unwrap();
+
+        match loader.load("java/lang/Object").unwrap() {
+            Class::File(class_file) => assert_eq!("java/lang/Object", &**class_file.get_name()),
+            class => panic!("unexpected class {:?}", class),
+        }
+    }
+}
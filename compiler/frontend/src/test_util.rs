@@ -0,0 +1,77 @@
+//! A class loader for tests, which serves classes that are described by
+//! their names and members instead of by class files.
+
+use std::sync::Arc;
+
+use classfile::test_util::ClassBuilder;
+use classfile::ClassFile;
+use failure::{bail, Fallible};
+
+use crate::loader::{Class, ClassLoader};
+
+pub const ACC_PUBLIC: u16 = 0x0001;
+pub const ACC_SUPER: u16 = 0x0020;
+pub const ACC_INTERFACE: u16 = 0x0200;
+pub const ACC_ABSTRACT: u16 = 0x0400;
+
+/// A class whose fields and methods are all public and have no attributes.
+pub struct TestClass {
+    pub name: &'static str,
+    pub super_name: Option<&'static str>,
+    pub access_flags: u16,
+    pub interfaces: Vec<&'static str>,
+    pub fields: Vec<(&'static str, &'static str)>,
+    pub methods: Vec<(&'static str, &'static str)>,
+}
+
+impl TestClass {
+    pub fn new(name: &'static str, super_name: Option<&'static str>) -> Self {
+        TestClass {
+            name,
+            super_name,
+            ..TestClass::default()
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut class =
+            ClassBuilder::with_super(self.name, self.super_name).access_flags(self.access_flags);
+        for name in self.interfaces.iter() {
+            class.interface(name);
+        }
+        for (name, descriptor) in self.fields.iter() {
+            class.field(ACC_PUBLIC, name, descriptor);
+        }
+        for (name, descriptor) in self.methods.iter() {
+            class.method(ACC_PUBLIC, name, descriptor, &[]);
+        }
+        class.to_bytes()
+    }
+}
+
+impl Default for TestClass {
+    fn default() -> Self {
+        TestClass {
+            name: "",
+            super_name: None,
+            access_flags: ACC_PUBLIC | ACC_SUPER,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![],
+        }
+    }
+}
+
+pub struct TestClassLoader(pub Vec<TestClass>);
+
+impl ClassLoader for TestClassLoader {
+    fn load(&self, name: &str) -> Fallible<Class> {
+        match self.0.iter().find(|class| class.name == name) {
+            Some(class) => {
+                let class_file = ClassFile::parse(&class.to_bytes()[..])?;
+                Ok(Class::File(Arc::new(class_file)))
+            }
+            None => bail!("class {} not found", name),
+        }
+    }
+}
@@ -1,15 +1,15 @@
 use std::fmt;
 
-use classfile::constant_pool::Constant;
-use classfile::descriptors::ReturnTypeDescriptor;
+use classfile::constant_pool::{ClassRef, Constant};
+use classfile::descriptors::{BaseType, FieldType, ReturnTypeDescriptor};
 use classfile::instructions::{Disassembler, Instr, LookupSwitch, TableSwitch};
-use classfile::{ConstantIndex, ConstantPool, MethodRef};
-use failure::{bail, Fallible};
+use classfile::{ConstantIndex, ConstantPool, FieldRef, MethodRef};
+use failure::{bail, format_err, Fallible};
 use strbuf::StrBuf;
 
 use crate::blocks::BlockGraph;
 use crate::disasm::{InstructionBlock, InstructionBlockMap, InstructionWithRange};
-use crate::frame::StackAndLocals;
+use crate::frame::{is_category_2, StackAndLocals};
 use crate::types::Type;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -24,9 +24,28 @@ impl BlockId {
         BlockId(addr)
     }
 
+    pub fn addr(self) -> u32 {
+        self.0
+    }
+
     pub fn from_addr_with_offset(addr: u32, offset: i32) -> Self {
         BlockId((i64::from(addr) + i64::from(offset)) as u32)
     }
+
+    /// Like `from_addr_with_offset`, but fails unless the resulting address
+    /// lies within code of length `code_len`.
+    pub fn try_from_addr_with_offset(addr: u32, offset: i32, code_len: u32) -> Fallible<Self> {
+        let target = i64::from(addr) + i64::from(offset);
+        if target < 0 || target >= i64::from(code_len) {
+            bail!(
+                "branch target {} at pc {} is outside of the code (length {})",
+                target,
+                addr,
+                code_len
+            );
+        }
+        Ok(BlockId(target as u32))
+    }
 }
 
 impl fmt::Display for BlockId {
@@ -38,6 +57,12 @@ impl fmt::Display for BlockId {
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct VarId(pub Type, pub u64);
 
+impl fmt::Display for VarId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.1)
+    }
+}
+
 #[derive(Default)]
 pub struct VarIdGen {
     next_id: u64,
@@ -74,7 +99,7 @@ pub struct InvokeExpr {
     pub args: Vec<Op>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Const {
     Int(i32),
     Long(i64),
@@ -95,7 +120,7 @@ impl Const {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Op {
     Var(VarId),
     Const(Const),
@@ -110,10 +135,40 @@ impl Op {
     }
 }
 
-#[derive(Debug)]
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Var(v) => write!(f, "{}", v),
+            Op::Const(Const::Int(x)) => write!(f, "{}", x),
+            Op::Const(Const::Long(x)) => write!(f, "{}L", x),
+            Op::Const(Const::Float(x)) => write!(f, "{:?}f", x),
+            Op::Const(Const::Double(x)) => write!(f, "{:?}d", x),
+            Op::Const(Const::Null) => f.write_str("null"),
+        }
+    }
+}
+
+// Writes the operands separated by commas.
+struct CommaSep<'a, T>(&'a [T]);
+
+impl<'a, T: fmt::Display> fmt::Display for CommaSep<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub enum BinaryOperation {
     Add,
     Sub,
+    Div,
+    Rem,
     BitwiseAnd,
     BitwiseOr,
     BitwiseXor,
@@ -132,6 +187,7 @@ pub struct BinaryExpr {
 
 #[derive(Debug)]
 pub enum ConvertOperation {
+    IntToBoolean,
     IntToChar,
     IntToByte,
     IntToShort,
@@ -168,6 +224,7 @@ pub enum MonitorStateTransition {
 pub enum Expr {
     String(ConstantIndex),
     GetStatic(ConstantIndex),
+    PutStatic(ConstantIndex, Op),
     GetField(Op, ConstantIndex),
     PutField(Op, ConstantIndex, Op),
     Invoke(InvokeExpr),
@@ -182,6 +239,64 @@ pub enum Expr {
     Monitor(Op, MonitorStateTransition),
 }
 
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::String(idx) => write!(f, "string #{}", idx.into_u16()),
+            Expr::GetStatic(idx) => write!(f, "getstatic #{}", idx.into_u16()),
+            Expr::PutStatic(idx, value) => write!(f, "putstatic #{} {}", idx.into_u16(), value),
+            Expr::GetField(obj, idx) => write!(f, "getfield {} #{}", obj, idx.into_u16()),
+            Expr::PutField(obj, idx, value) => {
+                write!(f, "putfield {} #{} {}", obj, idx.into_u16(), value)
+            }
+            Expr::Invoke(invoke) => {
+                let (kind, obj) = match invoke.target {
+                    InvokeTarget::Static => ("static", None),
+                    InvokeTarget::Special(ref obj) => ("special", Some(obj)),
+                    InvokeTarget::Virtual(ref obj) => ("virtual", Some(obj)),
+                    InvokeTarget::Interface(ref obj) => ("interface", Some(obj)),
+                };
+                write!(f, "invoke{} ", kind)?;
+                if let Some(obj) = obj {
+                    write!(f, "{} ", obj)?;
+                }
+                write!(
+                    f,
+                    "#{}.#{}({})",
+                    invoke.method.class_index.into_u16(),
+                    invoke.method.name_index.into_u16(),
+                    CommaSep(&invoke.args)
+                )
+            }
+            Expr::New(class_name) => write!(f, "new {}", class_name),
+            Expr::Compare(CompareExpr::ICmp(cmp, a, b)) => write!(f, "icmp {:?} {}, {}", cmp, a, b),
+            Expr::Compare(CompareExpr::ACmp(cmp, a, b)) => write!(f, "acmp {:?} {}, {}", cmp, a, b),
+            Expr::Compare(CompareExpr::LCmp(a, b)) => write!(f, "lcmp {}, {}", a, b),
+            Expr::Compare(CompareExpr::FCmp(a, b, mode)) => {
+                write!(f, "fcmp {:?} {}, {}", mode, a, b)
+            }
+            Expr::Compare(CompareExpr::DCmp(a, b, mode)) => {
+                write!(f, "dcmp {:?} {}, {}", mode, a, b)
+            }
+            Expr::Binary(binary) => write!(
+                f,
+                "{:?} {:?} {}, {}",
+                binary.operation, binary.result_type, binary.operand_left, binary.operand_right
+            ),
+            Expr::ArrayNew(ctyp, count) => write!(f, "newarray {:?} {}", ctyp, count),
+            Expr::ArrayLength(array) => write!(f, "arraylength {}", array),
+            Expr::ArrayLoad(ctyp, array, idx) => {
+                write!(f, "arrayload {:?} {}[{}]", ctyp, array, idx)
+            }
+            Expr::ArrayStore(ctyp, array, idx, value) => {
+                write!(f, "arraystore {:?} {}[{}] {}", ctyp, array, idx, value)
+            }
+            Expr::Convert(convert) => write!(f, "{:?} {}", convert.operation, convert.operand),
+            Expr::Monitor(obj, transition) => write!(f, "monitor {:?} {}", transition, obj),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ExceptionHandlers; // TODO
 
@@ -233,12 +348,43 @@ pub enum BranchStub {
     Throw(Op),
 }
 
+impl fmt::Display for BranchStub {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BranchStub::Return(None) => f.write_str("return"),
+            BranchStub::Return(Some(value)) => write!(f, "return {}", value),
+            BranchStub::Throw(exception) => write!(f, "throw {}", exception),
+            BranchStub::Switch(switch) if switch.cases.is_empty() => {
+                write!(f, "goto {}", switch.default)
+            }
+            BranchStub::Switch(switch) => {
+                write!(f, "switch {} [", switch.value)?;
+                for (value, addr) in switch.cases.iter() {
+                    write!(f, "{} => {}, ", value, addr)?;
+                }
+                write!(f, "_ => {}]", switch.default)
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Statement {
+    pub pc: u32,
     pub assign: Option<VarId>,
     pub expression: Expr,
 }
 
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.pc)?;
+        if let Some(ref var) = self.assign {
+            write!(f, "{} = ", var)?;
+        }
+        write!(f, "{}", self.expression)
+    }
+}
+
 #[derive(Debug)]
 pub struct BasicBlock {
     pub address: BlockId,
@@ -249,18 +395,29 @@ pub struct BasicBlock {
     pub outgoing: StackAndLocals,
 }
 
+impl fmt::Display for BasicBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "block {} {}", self.address, self.incoming)?;
+        for statement in self.statements.iter() {
+            writeln!(f, "  {}", statement)?;
+        }
+        writeln!(f, "  {}", self.branch_stub)
+    }
+}
+
 struct TranslateNext(BranchStub, Option<ExceptionHandlers>);
 
 struct TranslateInstr<'a> {
     range: &'a std::ops::Range<u32>,
     state: &'a mut StackAndLocals,
     consts: &'a ConstantPool,
+    return_type: &'a ReturnTypeDescriptor,
     var_id_gen: &'a mut VarIdGen,
     stmts: &'a mut Vec<Statement>,
 }
 
 impl<'a> TranslateInstr<'a> {
-    fn load(&mut self, idx: usize) {
+    fn load(&mut self, idx: usize) -> Fallible<()> {
         self.state.load(idx)
     }
 
@@ -276,6 +433,12 @@ impl<'a> TranslateInstr<'a> {
 
     fn duplicate2(&mut self) {
         let var1 = self.state.pop();
+        // a single long or double value fills both words on its own
+        if is_category_2(&var1.get_type()) {
+            self.state.push(var1.clone());
+            self.state.push(var1);
+            return;
+        }
         let var2 = self.state.pop();
         self.state.push(var2.clone());
         self.state.push(var1.clone());
@@ -287,59 +450,86 @@ impl<'a> TranslateInstr<'a> {
         self.state.pop_n(n);
     }
 
+    fn pop2(&mut self) {
+        let var = self.state.pop();
+        if !is_category_2(&var.get_type()) {
+            self.state.pop();
+        }
+    }
+
+    fn swap(&mut self) {
+        let var1 = self.state.pop();
+        let var2 = self.state.pop();
+        self.state.push(var1);
+        self.state.push(var2);
+    }
+
     fn push_const(&mut self, c: Const) {
         self.state.push(Op::Const(c));
     }
 
-    fn get_static(&mut self, idx: u16) {
-        let field = self
-            .consts
+    fn field_ref(&self, idx: u16) -> Fallible<FieldRef> {
+        self.consts
             .get_field_ref(ConstantIndex::from_u16(idx))
-            .unwrap();
+            .ok_or_else(|| format_err!("invalid field ref #{}", idx))
+    }
+
+    fn get_static(&mut self, idx: u16) -> Fallible<()> {
+        let field = self.field_ref(idx)?;
         let var = self
             .var_id_gen
             .gen(Type::from_field_type(&field.descriptor));
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::GetStatic(ConstantIndex::from_u16(idx)),
         };
         self.stmts.push(statement);
+        Ok(())
     }
 
-    fn get_field(&mut self, idx: u16) {
+    fn put_static(&mut self, idx: u16) {
+        let value = self.state.pop();
+        let statement = Statement {
+            pc: self.range.start,
+            assign: None,
+            expression: Expr::PutStatic(ConstantIndex::from_u16(idx), value),
+        };
+        self.stmts.push(statement);
+    }
+
+    fn get_field(&mut self, idx: u16) -> Fallible<()> {
         let object = self.state.pop();
-        let field = self
-            .consts
-            .get_field_ref(ConstantIndex::from_u16(idx))
-            .unwrap();
+        let field = self.field_ref(idx)?;
         let var = self
             .var_id_gen
             .gen(Type::from_field_type(&field.descriptor));
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::GetField(object, ConstantIndex::from_u16(idx)),
         };
         self.stmts.push(statement);
+        Ok(())
     }
 
-    fn put_field(&mut self, idx: u16) {
+    fn put_field(&mut self, idx: u16) -> Fallible<()> {
         let value = self.state.pop();
         let object = self.state.pop();
-        let field = self
-            .consts
-            .get_field_ref(ConstantIndex::from_u16(idx))
-            .unwrap();
+        let field = self.field_ref(idx)?;
         let var = self
             .var_id_gen
             .gen(Type::from_field_type(&field.descriptor));
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::PutField(object, ConstantIndex::from_u16(idx), value),
         };
         self.stmts.push(statement);
+        Ok(())
     }
 
     fn load_const(&mut self, idx: u16) {
@@ -348,6 +538,7 @@ impl<'a> TranslateInstr<'a> {
                 let var = self.var_id_gen.gen(Type::Reference);
                 self.state.push(Op::Var(var.clone()));
                 let statement = Statement {
+                    pc: self.range.start,
                     assign: Some(var),
                     expression: Expr::String(string_const.string_index),
                 };
@@ -376,6 +567,7 @@ impl<'a> TranslateInstr<'a> {
         let var = self.var_id_gen.gen(Type::Int);
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::Compare(CompareExpr::LCmp(value1, value2)),
         };
@@ -388,6 +580,7 @@ impl<'a> TranslateInstr<'a> {
         let var = self.var_id_gen.gen(Type::Int);
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::Compare(CompareExpr::FCmp(value1, value2, mode)),
         };
@@ -400,6 +593,7 @@ impl<'a> TranslateInstr<'a> {
         let var = self.var_id_gen.gen(Type::Int);
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::Compare(CompareExpr::DCmp(value1, value2, mode)),
         };
@@ -418,18 +612,17 @@ impl<'a> TranslateInstr<'a> {
             operand_right: value2,
         };
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(result),
             expression: Expr::Binary(binary_expr),
         };
         self.stmts.push(statement);
     }
 
-    fn iinc(&mut self, idx: u8, int: i32) {
+    fn iinc(&mut self, idx: usize, int: i32) {
         let var2 = self.var_id_gen.gen(Type::Int);
-        let var1 = self.state.locals[&(idx as usize)].clone();
-        self.state
-            .locals
-            .insert(idx as usize, Op::Var(var2.clone()));
+        let var1 = self.state.locals[&idx].clone();
+        self.state.locals.insert(idx, Op::Var(var2.clone()));
         let binary_expr = BinaryExpr {
             operation: BinaryOperation::Add,
             result_type: Type::Int,
@@ -437,6 +630,7 @@ impl<'a> TranslateInstr<'a> {
             operand_right: Op::Const(Const::Int(int)),
         };
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var2),
             expression: Expr::Binary(binary_expr),
         };
@@ -483,6 +677,7 @@ impl<'a> TranslateInstr<'a> {
             self.state.push(Op::Var(var.clone()));
         }
         let statement = Statement {
+            pc: self.range.start,
             assign: return_var,
             expression: Expr::Invoke(expr),
         };
@@ -494,17 +689,64 @@ impl<'a> TranslateInstr<'a> {
         let var = self.var_id_gen.gen(Type::Reference);
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::ArrayNew(component_type, count),
         };
         self.stmts.push(statement);
     }
 
+    fn check_cast(&mut self, idx: u16) -> Fallible<()> {
+        // array classes are named by their descriptor, e.g. `[I` or `[Ljava/lang/String;`
+        let target = self
+            .consts
+            .get_class_or_array(ConstantIndex::from_u16(idx))?;
+        // objects don't record their class at runtime, so only casts that are
+        // known to succeed are supported, and pass the reference through
+        let objectref = self.state.pop();
+        if !self.is_cast_safe(&objectref, &target) {
+            bail!("checkcast to class #{} can't be checked at runtime", idx);
+        }
+        self.state.push(objectref);
+        Ok(())
+    }
+
+    /// Whether a cast of `objectref` to `target` is known to succeed, because
+    /// the reference is null, the target is `java/lang/Object`, or the object
+    /// was created in this block as an instance of exactly the target class.
+    fn is_cast_safe(&self, objectref: &Op, target: &ClassRef) -> bool {
+        let var = match (objectref, target) {
+            (Op::Const(Const::Null), _) => return true,
+            (_, ClassRef::Class(name)) if &**name == "java/lang/Object" => return true,
+            (Op::Var(var), _) => var,
+            _ => return false,
+        };
+        let definition = self
+            .stmts
+            .iter()
+            .rev()
+            .find(|stmt| stmt.assign.as_ref() == Some(var));
+        match (definition.map(|stmt| &stmt.expression), target) {
+            (Some(Expr::New(class_name)), ClassRef::Class(name)) => class_name == name,
+            (Some(Expr::ArrayNew(component_type, _)), ClassRef::Array(array_type)) => {
+                match *array_type.component_type {
+                    FieldType::Base(_) => {
+                        *component_type == Type::from_field_type(&array_type.component_type)
+                    }
+                    // reference arrays don't know their component class
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
     fn array_length(&mut self) {
         let arrayref = self.state.pop();
         let var = self.var_id_gen.gen(Type::Int);
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::ArrayLength(arrayref),
         };
@@ -517,6 +759,7 @@ impl<'a> TranslateInstr<'a> {
         let var = self.var_id_gen.gen(component_type.clone());
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::ArrayLoad(component_type, arrayref, index),
         };
@@ -528,6 +771,7 @@ impl<'a> TranslateInstr<'a> {
         let index = self.state.pop();
         let arrayref = self.state.pop();
         let statement = Statement {
+            pc: self.range.start,
             assign: None,
             expression: Expr::ArrayStore(component_type, arrayref, index, value),
         };
@@ -547,9 +791,28 @@ impl<'a> TranslateInstr<'a> {
         )))
     }
 
-    fn ret(self, with_value: bool) -> Fallible<Option<TranslateNext>> {
+    fn ret(mut self, with_value: bool) -> Fallible<Option<TranslateNext>> {
         let var_opt = if with_value {
-            Some(self.state.pop())
+            let value = self.state.pop();
+            // narrow int values to the declared boolean, byte, char or short return type
+            let operation = match self.return_type {
+                ReturnTypeDescriptor::Field(FieldType::Base(base_type)) => match base_type {
+                    BaseType::Boolean => Some(ConvertOperation::IntToBoolean),
+                    BaseType::Byte => Some(ConvertOperation::IntToByte),
+                    BaseType::Char => Some(ConvertOperation::IntToChar),
+                    BaseType::Short => Some(ConvertOperation::IntToShort),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match operation {
+                Some(operation) if value.get_type() == Type::Int => {
+                    self.state.push(value);
+                    self.convert(operation);
+                    Some(self.state.pop())
+                }
+                _ => Some(value),
+            }
         } else {
             None
         };
@@ -566,6 +829,7 @@ impl<'a> TranslateInstr<'a> {
         let else_addr = BlockId::from_addr(self.range.end);
         let tmpvar = self.var_id_gen.gen(Type::Boolean);
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(tmpvar.clone()),
             expression: Expr::Compare(CompareExpr::ICmp(comp, value1, value2)),
         };
@@ -582,6 +846,7 @@ impl<'a> TranslateInstr<'a> {
         let else_addr = BlockId::from_addr(self.range.end);
         let tmpvar = self.var_id_gen.gen(Type::Boolean);
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(tmpvar.clone()),
             expression: Expr::Compare(CompareExpr::ICmp(comp, var, Op::Const(Const::Int(0)))),
         };
@@ -599,6 +864,7 @@ impl<'a> TranslateInstr<'a> {
         let else_addr = BlockId::from_addr(self.range.end);
         let tmpvar = self.var_id_gen.gen(Type::Boolean);
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(tmpvar.clone()),
             expression: Expr::Compare(CompareExpr::ACmp(comp, value1, value2)),
         };
@@ -615,6 +881,7 @@ impl<'a> TranslateInstr<'a> {
         let else_addr = BlockId::from_addr(self.range.end);
         let tmpvar = self.var_id_gen.gen(Type::Boolean);
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(tmpvar.clone()),
             expression: Expr::Compare(CompareExpr::ACmp(comp, value, Op::Const(Const::Null))),
         };
@@ -631,6 +898,7 @@ impl<'a> TranslateInstr<'a> {
         let var = self.var_id_gen.gen(Type::Reference);
         self.state.push(Op::Var(var.clone()));
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(var),
             expression: Expr::New(class_name.clone()),
         };
@@ -640,6 +908,7 @@ impl<'a> TranslateInstr<'a> {
     fn convert(&mut self, operation: ConvertOperation) {
         let value = self.state.pop();
         let target_type = match operation {
+            ConvertOperation::IntToBoolean => Type::Int,
             ConvertOperation::IntToChar => Type::Int,
             ConvertOperation::IntToByte => Type::Int,
             ConvertOperation::IntToShort => Type::Int,
@@ -651,6 +920,7 @@ impl<'a> TranslateInstr<'a> {
             operand: value,
         };
         let statement = Statement {
+            pc: self.range.start,
             assign: Some(result),
             expression: Expr::Convert(convert_expr),
         };
@@ -660,6 +930,7 @@ impl<'a> TranslateInstr<'a> {
     fn monitor(&mut self, transition: MonitorStateTransition) {
         let objectref = self.state.pop();
         let statement = Statement {
+            pc: self.range.start,
             assign: None,
             expression: Expr::Monitor(objectref, transition),
         };
@@ -708,6 +979,7 @@ fn translate_instructions(
     instrs: &mut Iterator<Item = &InstructionWithRange>,
     state: &mut StackAndLocals,
     consts: &ConstantPool,
+    return_type: &ReturnTypeDescriptor,
     var_id_gen: &mut VarIdGen,
     stmts: &mut Vec<Statement>,
 ) -> Fallible<Option<TranslateNext>> {
@@ -717,45 +989,66 @@ fn translate_instructions(
             range,
             state,
             consts,
+            return_type,
             var_id_gen,
             stmts,
         };
         match instr {
             // stack manipulation operations
-            Instr::ALoad0 => t.load(0),
-            Instr::ALoad1 => t.load(1),
-            Instr::ALoad2 => t.load(2),
-            Instr::ALoad3 => t.load(3),
-            Instr::ALoad(idx) => t.load(*idx as usize),
+            Instr::ALoad0 => t.load(0)?,
+            Instr::ALoad1 => t.load(1)?,
+            Instr::ALoad2 => t.load(2)?,
+            Instr::ALoad3 => t.load(3)?,
+            Instr::ALoad(idx) => t.load(*idx as usize)?,
             Instr::AStore0 => t.store(0),
             Instr::AStore1 => t.store(1),
             Instr::AStore2 => t.store(2),
             Instr::AStore3 => t.store(3),
             Instr::AStore(idx) => t.store(*idx as usize),
-            Instr::ILoad(idx) => t.load(*idx as usize),
+            Instr::ILoad(idx) => t.load(*idx as usize)?,
             Instr::IStore(idx) => t.store(*idx as usize),
-            Instr::LLoad(idx) => t.load(*idx as usize),
+            Instr::LLoad(idx) => t.load(*idx as usize)?,
             Instr::LStore(idx) => t.store(*idx as usize),
-            Instr::FLoad(idx) => t.load(*idx as usize),
+            Instr::FLoad(idx) => t.load(*idx as usize)?,
             Instr::FStore(idx) => t.store(*idx as usize),
-            Instr::DLoad(idx) => t.load(*idx as usize),
+            Instr::DLoad(idx) => t.load(*idx as usize)?,
             Instr::DStore(idx) => t.store(*idx as usize),
+            Instr::WideALoad(idx) => t.load(*idx as usize)?,
+            Instr::WideAStore(idx) => t.store(*idx as usize),
+            Instr::WideILoad(idx) => t.load(*idx as usize)?,
+            Instr::WideIStore(idx) => t.store(*idx as usize),
+            Instr::WideLLoad(idx) => t.load(*idx as usize)?,
+            Instr::WideLStore(idx) => t.store(*idx as usize),
+            Instr::WideFLoad(idx) => t.load(*idx as usize)?,
+            Instr::WideFStore(idx) => t.store(*idx as usize),
+            Instr::WideDLoad(idx) => t.load(*idx as usize)?,
+            Instr::WideDStore(idx) => t.store(*idx as usize),
             Instr::Dup => t.duplicate(),
             Instr::Dup2 => t.duplicate2(),
             Instr::Pop => t.pop(1),
-            Instr::Pop2 => t.pop(2),
+            Instr::Pop2 => t.pop2(),
+            Instr::Swap => t.swap(),
             // arithmetic operations
             Instr::LCmp => t.lcmp(),
             Instr::LAdd => t.binary(Type::Long, BinaryOperation::Add),
+            Instr::LSub => t.binary(Type::Long, BinaryOperation::Sub),
+            Instr::LDiv => t.binary(Type::Long, BinaryOperation::Div),
+            Instr::LRem => t.binary(Type::Long, BinaryOperation::Rem),
             Instr::IAdd => t.binary(Type::Int, BinaryOperation::Add),
             Instr::ISub => t.binary(Type::Int, BinaryOperation::Sub),
+            Instr::IDiv => t.binary(Type::Int, BinaryOperation::Div),
+            Instr::IRem => t.binary(Type::Int, BinaryOperation::Rem),
             Instr::IAnd => t.binary(Type::Int, BinaryOperation::BitwiseAnd),
             Instr::IOr => t.binary(Type::Int, BinaryOperation::BitwiseOr),
             Instr::IXor => t.binary(Type::Int, BinaryOperation::BitwiseXor),
             Instr::IShL => t.binary(Type::Int, BinaryOperation::ShiftLeft),
             Instr::IShR => t.binary(Type::Int, BinaryOperation::ShiftRightArithmetic),
             Instr::IUShR => t.binary(Type::Int, BinaryOperation::ShiftRightLogical),
-            Instr::IInc(idx, int) => t.iinc(*idx, i32::from(*int)),
+            Instr::LShL => t.binary(Type::Long, BinaryOperation::ShiftLeft),
+            Instr::LShR => t.binary(Type::Long, BinaryOperation::ShiftRightArithmetic),
+            Instr::LUShR => t.binary(Type::Long, BinaryOperation::ShiftRightLogical),
+            Instr::IInc(idx, int) => t.iinc(*idx as usize, i32::from(*int)),
+            Instr::WideIInc(idx, int) => t.iinc(*idx as usize, i32::from(*int)),
             Instr::FCmpG => t.fcmp(NaNCmpMode::Greater),
             Instr::FCmpL => t.fcmp(NaNCmpMode::Less),
             Instr::DCmpG => t.dcmp(NaNCmpMode::Greater),
@@ -769,13 +1062,15 @@ fn translate_instructions(
             Instr::MonitorEnter => t.monitor(MonitorStateTransition::Enter),
             Instr::MonitorExit => t.monitor(MonitorStateTransition::Exit),
             // field operations
-            Instr::GetStatic(idx) => t.get_static(*idx),
-            Instr::GetField(idx) => t.get_field(*idx),
-            Instr::PutField(idx) => t.put_field(*idx),
+            Instr::GetStatic(idx) => t.get_static(*idx)?,
+            Instr::PutStatic(idx) => t.put_static(*idx),
+            Instr::GetField(idx) => t.get_field(*idx)?,
+            Instr::PutField(idx) => t.put_field(*idx)?,
             // array operations
             Instr::ANewArray(_) => t.array_new(Type::Reference),
             Instr::NewArray(atype) => t.array_new(Type::from_array_type(atype)),
             Instr::ArrayLength => t.array_length(),
+            Instr::CheckCast(idx) => t.check_cast(*idx)?,
             Instr::AaLoad => t.array_load(Type::Reference),
             Instr::BaLoad => t.array_load(Type::Byte),
             Instr::CaLoad => t.array_load(Type::Char),
@@ -796,11 +1091,13 @@ fn translate_instructions(
             Instr::LdC(idx) => t.load_const(u16::from(*idx)),
             Instr::LdCW(idx) => t.load_const(*idx),
             Instr::LdC2W(idx) => t.load_const(*idx),
+            Instr::IConstM1 => t.push_const(Const::Int(-1)),
             Instr::IConst0 => t.push_const(Const::Int(0)),
             Instr::IConst1 => t.push_const(Const::Int(1)),
             Instr::IConst2 => t.push_const(Const::Int(2)),
             Instr::IConst3 => t.push_const(Const::Int(3)),
             Instr::IConst4 => t.push_const(Const::Int(4)),
+            Instr::IConst5 => t.push_const(Const::Int(5)),
             Instr::LConst0 => t.push_const(Const::Long(0)),
             Instr::LConst1 => t.push_const(Const::Long(1)),
             Instr::FConst0 => t.push_const(Const::Float(0.0)),
@@ -820,6 +1117,9 @@ fn translate_instructions(
             Instr::Goto(offset) => return t.goto(*offset),
             Instr::Return => return t.ret(false),
             Instr::IReturn => return t.ret(true),
+            Instr::LReturn => return t.ret(true),
+            Instr::FReturn => return t.ret(true),
+            Instr::DReturn => return t.ret(true),
             Instr::AReturn => return t.ret(true),
             Instr::AThrow => return t.athrow(),
             Instr::IfLt(offset) => return t.if_zcmp(*offset, IComparator::Lt),
@@ -841,6 +1141,10 @@ fn translate_instructions(
             Instr::TableSwitch(table) => return t.table_switch(table),
             Instr::LookupSwitch(lookup) => return t.lookup_switch(lookup),
             // misc operations
+            Instr::Nop => {}
+            Instr::Jsr(_) | Instr::JsrW(_) | Instr::Ret(_) | Instr::WideRet(_) => {
+                bail!("jsr/ret subroutines are not supported; recompile with a modern javac")
+            }
             _ => bail!("unsupported instruction {:?}", instr),
         }
     }
@@ -851,6 +1155,7 @@ fn translate_block(
     instr_block: &InstructionBlock,
     incoming: StackAndLocals,
     consts: &ConstantPool,
+    return_type: &ReturnTypeDescriptor,
     var_id_gen: &mut VarIdGen,
 ) -> Fallible<BasicBlock> {
     let address = BlockId(instr_block.range.start);
@@ -861,6 +1166,7 @@ fn translate_block(
         &mut instrs,
         &mut state,
         &consts,
+        return_type,
         var_id_gen,
         &mut statements,
     )? {
@@ -890,6 +1196,7 @@ pub fn translate_method(
     dasm: Disassembler,
     incoming: StackAndLocals,
     consts: &ConstantPool,
+    return_type: &ReturnTypeDescriptor,
     var_id_gen: &mut VarIdGen,
 ) -> Fallible<BlockGraph> {
     let instr_block_map = InstructionBlockMap::build(dasm)?;
@@ -898,7 +1205,7 @@ pub fn translate_method(
     while let Some((addr, state)) = remaining.pop() {
         if !blocks.contains(addr) {
             let instr_block = instr_block_map.block_starting_at(addr.0);
-            let block = translate_block(instr_block, state, &consts, var_id_gen)?;
+            let block = translate_block(instr_block, state, consts, return_type, var_id_gen)?;
             match block.branch_stub {
                 BranchStub::Switch(ref switch) => {
                     remaining.push((
@@ -915,6 +1222,647 @@ pub fn translate_method(
             blocks.insert(block);
         }
     }
+    blocks.collapse_forwarding_blocks();
     blocks.calculate_edges();
+    blocks.check_phi_types()?;
     Ok(blocks)
 }
+
+#[cfg(test)]
+mod tests {
+    use classfile::descriptors::ParameterDescriptor;
+    use classfile::test_util::ClassBuilder;
+    use classfile::ClassFile;
+
+    use super::*;
+
+    // Builds a class with a single static method `run()I` with the given bytecode.
+    fn class_with_code(code: &[u8]) -> ClassFile {
+        with_run_method(ClassBuilder::new("Test"), code)
+    }
+
+    // Like `class_with_code`, but for a class that may have had constants
+    // added to its pool (starting at index 5).
+    fn with_run_method(class: ClassBuilder, code: &[u8]) -> ClassFile {
+        with_static_method(class, "()I", code)
+    }
+
+    // Like `with_run_method`, but `run` has the given descriptor, so that it
+    // can take arguments.
+    fn with_static_method(mut class: ClassBuilder, descriptor: &str, code: &[u8]) -> ClassFile {
+        let code = class.code(4, 1, code);
+        class.method(0x0009, "run", descriptor, &[code]);
+        class.parse()
+    }
+
+    fn try_translate(code: &[u8]) -> Fallible<BlockGraph> {
+        try_translate_class(&class_with_code(code))
+    }
+
+    // Like `try_translate`, but for a class that may have had constants added
+    // to its pool.
+    fn try_translate_with(class: ClassBuilder, code: &[u8]) -> Fallible<BlockGraph> {
+        try_translate_class(&with_run_method(class, code))
+    }
+
+    // Translates the `run` method of the class, with its arguments in the
+    // first locals.
+    fn try_translate_class(class_file: &ClassFile) -> Fallible<BlockGraph> {
+        let method = &class_file.methods[0];
+        let code = method.code().unwrap().unwrap();
+        let mut var_id_gen = VarIdGen::default();
+        let args = method
+            .descriptor
+            .params
+            .iter()
+            .map(|ParameterDescriptor::Field(field_type)| {
+                var_id_gen.gen(Type::from_field_type(field_type))
+            })
+            .collect::<Vec<_>>();
+        let state = StackAndLocals::new(code.max_stack, code.max_locals, &args);
+        translate_method(
+            code.disassemble(),
+            state,
+            &class_file.constant_pool,
+            &method.descriptor.ret,
+            &mut var_id_gen,
+        )
+    }
+
+    fn translate(code: &[u8]) -> BlockGraph {
+        try_translate(code).unwrap()
+    }
+
+    fn translate_err(code: &[u8]) -> String {
+        match try_translate(code) {
+            Ok(_) => panic!("expected translation to fail"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn get_static_int_field() {
+        let mut class = ClassBuilder::new("Test");
+        // #5: counter, #6: I, #7: counter:I, #8: Test.counter:I
+        class.consts.field_ref(2, "counter", "I");
+        let blocks = try_translate_with(
+            class,
+            &[
+                0xb2, 0x00, 0x08, // 0: getstatic #8
+                0xac, // 3: ireturn
+            ],
+        )
+        .unwrap();
+        let block = blocks.lookup(BlockId::start());
+
+        let var = block.statements[0].assign.clone().unwrap();
+        assert_eq!(Type::Int, var.0);
+        match block.branch_stub {
+            BranchStub::Return(Some(Op::Var(ref ret))) => assert_eq!(var, *ret),
+            ref stub => panic!("unexpected branch stub {:?}", stub),
+        }
+    }
+
+    #[test]
+    fn nested_invokes_keep_argument_order() {
+        let mut class = ClassBuilder::new("Test");
+        // #5: b, #6: ()I, #7: b()I, #8: Test.b()I
+        class.consts.method_ref(2, "b", "()I");
+        // #9: c, #10: ()I, #11: c()I, #12: Test.c()I
+        class.consts.method_ref(2, "c", "()I");
+        // #13: a, #14: (II)I, #15: a(II)I, #16: Test.a(II)I
+        class.consts.method_ref(2, "a", "(II)I");
+        let blocks = try_translate_with(
+            class,
+            &[
+                0xb8, 0x00, 0x08, // 0: invokestatic #8
+                0xb8, 0x00, 0x0c, // 3: invokestatic #12
+                0xb8, 0x00, 0x10, // 6: invokestatic #16
+                0xac, // 9: ireturn
+            ],
+        )
+        .unwrap();
+        let block = blocks.lookup(BlockId::start());
+
+        let invokes = block
+            .statements
+            .iter()
+            .map(|statement| match statement.expression {
+                Expr::Invoke(ref expr) => (
+                    statement.pc,
+                    expr.method.name_index.into_u16(),
+                    expr.args.clone(),
+                ),
+                ref expr => panic!("unexpected expression {:?}", expr),
+            })
+            .collect::<Vec<_>>();
+        let b = Op::Var(block.statements[0].assign.clone().unwrap());
+        let c = Op::Var(block.statements[1].assign.clone().unwrap());
+        assert_eq!(
+            vec![
+                // b, c, a
+                (0, 5, vec![]),
+                (3, 9, vec![]),
+                (6, 13, vec![b, c]),
+            ],
+            invokes
+        );
+    }
+
+    fn try_translate_cast(load: &[u8]) -> Fallible<BlockGraph> {
+        let mut class = ClassBuilder::new("Test");
+        // #5: [I, #6: class #5
+        class.consts.class("[I");
+        let mut code = load.to_vec();
+        code.extend_from_slice(&[
+            0xc0, 0x00, 0x06, // checkcast #6
+            0xbe, // arraylength
+            0xac, // ireturn
+        ]);
+        // static int run(Object obj) { return ((int[]) <load>).length; }
+        let class_file = with_static_method(class, "(Ljava/lang/Object;)I", &code);
+        try_translate_class(&class_file)
+    }
+
+    #[test]
+    fn check_cast_to_array_class() {
+        // aconst_null
+        let blocks = try_translate_cast(&[0x01]).unwrap();
+        let block = blocks.lookup(BlockId::start());
+
+        assert_eq!(1, block.statements.len());
+        match block.statements[0].expression {
+            Expr::ArrayLength(Op::Const(Const::Null)) => {}
+            ref expr => panic!("unexpected expression {:?}", expr),
+        }
+        // iconst_1, newarray int
+        assert!(try_translate_cast(&[0x04, 0xbc, 10]).is_ok());
+    }
+
+    #[test]
+    fn check_cast_that_may_fail_is_rejected() {
+        // aload_0, i.e. `(int[]) obj`
+        match try_translate_cast(&[0x2a]) {
+            Ok(_) => panic!("expected translation to fail"),
+            Err(err) => assert_eq!(
+                "checkcast to class #6 can't be checked at runtime",
+                err.to_string()
+            ),
+        }
+        // iconst_1, newarray long
+        assert!(try_translate_cast(&[0x04, 0xbc, 11]).is_err());
+    }
+
+    #[test]
+    fn ldc2_w_double_return() {
+        let mut class = ClassBuilder::new("Test");
+        // #5: double 0.1, which also takes up #6
+        let mut double = vec![6];
+        double.extend_from_slice(&0.1f64.to_bits().to_be_bytes());
+        class.consts.wide_constant(&double);
+        let blocks = try_translate_with(
+            class,
+            &[
+                0x14, 0x00, 0x05, // 0: ldc2_w #5
+                0xaf, // 3: dreturn
+            ],
+        )
+        .unwrap();
+        let block = blocks.lookup(BlockId::start());
+
+        match block.branch_stub {
+            BranchStub::Return(Some(Op::Const(Const::Double(value)))) => {
+                assert_eq!(0.1f64.to_bits(), value.to_bits())
+            }
+            ref stub => panic!("unexpected branch stub {:?}", stub),
+        }
+    }
+
+    #[test]
+    fn pop2_long() {
+        let blocks = translate(&[
+            0x06, // 0: iconst_3
+            0x0a, // 1: lconst_1
+            0x58, // 2: pop2
+            0xac, // 3: ireturn
+        ]);
+        let block = blocks.lookup(BlockId::start());
+
+        match block.branch_stub {
+            BranchStub::Return(Some(Op::Const(Const::Int(3)))) => {}
+            ref stub => panic!("unexpected branch stub {:?}", stub),
+        }
+    }
+
+    #[test]
+    fn pop2_two_ints() {
+        let blocks = translate(&[
+            0x04, 0x05, 0x06, // 0: iconst_1, iconst_2, iconst_3
+            0x58, // 3: pop2
+            0xac, // 4: ireturn
+        ]);
+        let block = blocks.lookup(BlockId::start());
+
+        match block.branch_stub {
+            BranchStub::Return(Some(Op::Const(Const::Int(1)))) => {}
+            ref stub => panic!("unexpected branch stub {:?}", stub),
+        }
+    }
+
+    #[test]
+    fn dup2_long() {
+        let blocks = translate(&[
+            0x0a, // 0: lconst_1
+            0x5c, // 1: dup2
+            0x61, // 2: ladd
+            0x58, // 3: pop2
+            0x06, // 4: iconst_3
+            0xac, // 5: ireturn
+        ]);
+        let rendered = blocks.lookup(BlockId::start()).to_string();
+
+        assert!(
+            rendered.contains("  2: v0 = Add Long 1L, 1L\n"),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn swap() {
+        let blocks = translate(&[
+            0x04, 0x05, // 0: iconst_1, iconst_2
+            0x5f, // 2: swap
+            0x64, // 3: isub
+            0xac, // 4: ireturn
+        ]);
+        let rendered = blocks.lookup(BlockId::start()).to_string();
+
+        assert!(
+            rendered.contains("  3: v0 = Sub Int 2, 1\n"),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn display_basic_block() {
+        let blocks = translate(&[
+            0x04, 0x05, 0x60, // 0: iconst_1, iconst_2, iadd
+            0x10, 0x03, 0x64, // 3: bipush 3, isub
+            0xac, // 6: ireturn
+        ]);
+        let rendered = blocks.lookup(BlockId::start()).to_string();
+
+        assert!(rendered.starts_with("block 0 "), "{}", rendered);
+        assert!(
+            rendered.contains("  2: v0 = Add Int 1, 2\n"),
+            "{}",
+            rendered
+        );
+        assert!(
+            rendered.contains("  5: v1 = Sub Int v0, 3\n"),
+            "{}",
+            rendered
+        );
+        assert!(rendered.contains("  return v1\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn nop_is_ignored() {
+        // nop, iconst_1, nop, ireturn
+        let blocks = translate(&[0x00, 0x04, 0x00, 0xac]);
+        let block = blocks.lookup(BlockId::start());
+
+        assert!(block.statements.is_empty());
+        match block.branch_stub {
+            BranchStub::Return(Some(Op::Const(Const::Int(1)))) => {}
+            ref stub => panic!("unexpected branch stub {:?}", stub),
+        }
+    }
+
+    #[test]
+    fn empty_goto_chains_are_collapsed() {
+        let blocks = translate(&[
+            0x04, // 0: iconst_1
+            0x99, 0x00, 0x09, // 1: ifeq 10
+            0xa7, 0x00, 0x03, // 4: goto 7
+            0xa7, 0x00, 0x05, // 7: goto 12
+            0x03, 0xac, // 10: iconst_0, ireturn
+            0x04, 0xac, // 12: iconst_1, ireturn
+        ]);
+
+        assert_eq!(3, blocks.blocks().count());
+        assert!(!blocks.contains(BlockId::from_addr(4)));
+        assert!(!blocks.contains(BlockId::from_addr(7)));
+        match blocks.lookup(BlockId::start()).branch_stub {
+            BranchStub::Switch(ref switch) => {
+                assert_eq!(BlockId::from_addr(12), switch.default);
+                assert_eq!(BlockId::from_addr(10), switch.cases[0].1);
+            }
+            ref stub => panic!("unexpected branch stub {:?}", stub),
+        }
+    }
+
+    #[test]
+    fn goto_self_loop_is_kept() {
+        let blocks = translate(&[
+            0x04, // 0: iconst_1
+            0x99, 0x00, 0x06, // 1: ifeq 7
+            0xa7, 0x00, 0x00, // 4: goto 4
+            0x03, 0xac, // 7: iconst_0, ireturn
+        ]);
+
+        assert_eq!(3, blocks.blocks().count());
+        assert!(blocks.contains(BlockId::from_addr(4)));
+    }
+
+    #[test]
+    fn branch_before_start_is_rejected() {
+        let err = translate_err(&[
+            0x04, // 0: iconst_1
+            0x99, 0xff, 0xfe, // 1: ifeq -1
+            0x03, 0xac, // 4: iconst_0, ireturn
+        ]);
+
+        assert_eq!(
+            "branch target -1 at pc 1 is outside of the code (length 6)",
+            err
+        );
+    }
+
+    #[test]
+    fn mismatched_stack_types_at_join_are_rejected() {
+        let err = translate_err(&[
+            0x03, // 0: iconst_0
+            0x99, 0x00, 0x07, // 1: ifeq 8
+            0x04, // 4: iconst_1
+            0xa7, 0x00, 0x04, // 5: goto 9
+            0x01, // 8: aconst_null
+            0x57, 0x03, 0xac, // 9: pop, iconst_0, ireturn
+        ]);
+
+        assert!(
+            err.starts_with("stack slot 0 entering block 9 is "),
+            "unexpected error {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn dump_frames_at_join() {
+        let blocks = translate(&[
+            0x03, // 0: iconst_0
+            0x99, 0x00, 0x07, // 1: ifeq 8
+            0x04, // 4: iconst_1
+            0xa7, 0x00, 0x04, // 5: goto 9
+            0x05, // 8: iconst_2
+            0xac, // 9: ireturn
+        ]);
+
+        // both predecessors leave an int behind, which enters the join as a phi
+        let dump = blocks.dump_frames();
+        assert_eq!(
+            vec![
+                "block 0",
+                "  in:  stack [], locals []",
+                "  out: stack [], locals []",
+                "block 4",
+                "  in:  stack [], locals []",
+                "  out: stack [1 int], locals []",
+                "block 8",
+                "  in:  stack [], locals []",
+                "  out: stack [2 int], locals []",
+                "block 9",
+                "  in:  stack [v1 int], locals []",
+                "  out: stack [], locals []",
+            ],
+            dump.lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn dump_types_of_int_and_string() {
+        let mut class = ClassBuilder::new("Test");
+        // #5: hello, #6: string #5
+        let string_index = class.consts.utf8("hello");
+        let mut string = vec![8];
+        string.extend_from_slice(&string_index.to_be_bytes());
+        class.consts.constant(&string);
+        let blocks = try_translate_with(
+            class,
+            &[
+                0x12, 0x06, // 0: ldc #6
+                0x57, // 2: pop
+                0x04, 0x05, 0x60, // 3: iconst_1, iconst_2, iadd
+                0xac, // 6: ireturn
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["v0: ref", "v1: int"],
+            blocks.dump_types().lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn blocks_rpo_places_join_after_branches() {
+        let blocks = translate(&[
+            0x03, // 0: iconst_0
+            0x99, 0x00, 0x0a, // 1: ifeq 11
+            0x04, // 4: iconst_1
+            0x99, 0x00, 0x07, // 5: ifeq 12
+            0xa7, 0x00, 0x04, // 8: goto 12
+            0x00, // 11: nop
+            0x03, 0xac, // 12: iconst_0, ireturn
+        ]);
+
+        let order = blocks
+            .blocks_rpo()
+            .iter()
+            .map(|block| block.address.addr())
+            .collect::<Vec<_>>();
+        let position = |addr| order.iter().position(|a| *a == addr).unwrap();
+
+        assert_eq!(blocks.blocks().count(), order.len());
+        assert_eq!(0, order[0]);
+        assert!(position(4) < position(8));
+        assert!(position(4) < position(12));
+        assert!(position(8) < position(12));
+        assert!(position(11) < position(12));
+    }
+
+    #[test]
+    fn char_and_int_join_into_int_phi() {
+        let blocks = translate(&[
+            0x04, // 0: iconst_1
+            0xbc, 0x05, // 1: newarray char
+            0x03, 0x34, // 3: iconst_0, caload
+            0x03, // 5: iconst_0
+            0x99, 0x00, 0x08, // 6: ifeq 14
+            0x57, 0x04, // 9: pop, iconst_1
+            0xa7, 0x00, 0x03, // 11: goto 14
+            0xac, // 14: ireturn
+        ]);
+        let join = blocks.lookup(BlockId(14));
+
+        let phi = blocks.phis(join).next().unwrap();
+        assert_eq!(2, phi.operands.len());
+        assert!(phi.operands.iter().all(|operand| operand.opt.is_some()));
+    }
+
+    #[test]
+    fn jsr_is_rejected() {
+        let err = translate_err(&[
+            0xa8, 0x00, 0x05, // 0: jsr 5
+            0x04, 0xac, // 3: iconst_1, ireturn
+            0x4b, 0xa9, 0x00, // 5: astore_0, ret 0
+        ]);
+
+        assert_eq!(
+            "jsr/ret subroutines are not supported; recompile with a modern javac",
+            err
+        );
+    }
+
+    #[test]
+    fn get_static_of_non_field_ref_is_rejected() {
+        let err = translate_err(&[
+            0xb2, 0x00, 0x01, // 0: getstatic #1
+            0xac, // 3: ireturn
+        ]);
+
+        assert_eq!("invalid field ref #1", err);
+    }
+
+    #[test]
+    fn branch_past_end_is_rejected() {
+        let err = translate_err(&[
+            0xa7, 0x00, 0x05, // 0: goto 5
+            0x03, 0xac, // 3: iconst_0, ireturn
+        ]);
+
+        assert_eq!(
+            "branch target 5 at pc 0 is outside of the code (length 5)",
+            err
+        );
+    }
+
+    #[test]
+    fn wide_local_index() {
+        let blocks = translate(&[
+            0x08, // 0: iconst_5
+            0xc4, 0x36, 0x01, 0x2c, // 1: wide istore 300
+            0xc4, 0x84, 0x01, 0x2c, 0x03, 0xe8, // 5: wide iinc 300 1000
+            0xc4, 0x15, 0x01, 0x2c, // 11: wide iload 300
+            0xac, // 15: ireturn
+        ]);
+        let block = blocks.lookup(BlockId::start());
+
+        assert_eq!(1, block.statements.len());
+        let sum = match block.statements[0] {
+            Statement {
+                pc: 5,
+                assign: Some(ref sum),
+                expression: Expr::Binary(ref expr),
+            } => {
+                assert_eq!(BinaryOperation::Add, expr.operation);
+                assert_eq!(Op::Const(Const::Int(5)), expr.operand_left);
+                assert_eq!(Op::Const(Const::Int(1000)), expr.operand_right);
+                sum
+            }
+            ref statement => panic!("unexpected statement {:?}", statement),
+        };
+        match block.branch_stub {
+            BranchStub::Return(Some(Op::Var(ref var))) => assert_eq!(sum, var),
+            ref stub => panic!("unexpected branch stub {:?}", stub),
+        }
+    }
+
+    #[test]
+    fn leaders_include_targets_and_fall_through() {
+        let code = vec![
+            0x03, // 0: iconst_0
+            0x3b, // 1: istore_0
+            0x1a, // 2: iload_0
+            0x10, 0x0a, // 3: bipush 10
+            0xa2, 0x00, 0x09, // 5: if_icmpge 14
+            0x84, 0x00, 0x01, // 8: iinc 0 1
+            0xa7, 0xff, 0xf7, // 11: goto 2
+            0x1a, // 14: iload_0
+            0xac, // 15: ireturn
+        ];
+        let class_file = class_with_code(&code);
+        let code = class_file.methods[0].code().unwrap().unwrap();
+        let map = InstructionBlockMap::build(code.disassemble()).unwrap();
+
+        let leaders = map.leaders().iter().cloned().collect::<Vec<_>>();
+        assert_eq!(vec![0, 2, 8, 14], leaders);
+    }
+
+    fn assert_operand_order(
+        descriptor: &str,
+        code: &[u8],
+        operation: BinaryOperation,
+        left: Const,
+        right: Const,
+    ) {
+        let class_file = with_static_method(ClassBuilder::new("Test"), descriptor, code);
+        let blocks = try_translate_class(&class_file).unwrap();
+        let block = blocks.lookup(BlockId::start());
+        match block.statements[0].expression {
+            Expr::Binary(ref expr) => {
+                assert_eq!(operation, expr.operation);
+                assert_eq!(Op::Const(left), expr.operand_left);
+                assert_eq!(Op::Const(right), expr.operand_right);
+            }
+            ref expr => panic!("unexpected expression {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn int_binary_operand_order() {
+        let cases = vec![
+            (0x64, BinaryOperation::Sub),
+            (0x6c, BinaryOperation::Div),
+            (0x70, BinaryOperation::Rem),
+            (0x78, BinaryOperation::ShiftLeft),
+            (0x7a, BinaryOperation::ShiftRightArithmetic),
+            (0x7c, BinaryOperation::ShiftRightLogical),
+        ];
+        for (opcode, operation) in cases {
+            // bipush 7, iconst_3, <opcode>, ireturn
+            let code = [0x10, 0x07, 0x06, opcode, 0xac];
+            assert_operand_order("()I", &code, operation, Const::Int(7), Const::Int(3));
+        }
+    }
+
+    #[test]
+    fn long_binary_operand_order() {
+        let cases = vec![
+            (0x65, BinaryOperation::Sub),
+            (0x6d, BinaryOperation::Div),
+            (0x71, BinaryOperation::Rem),
+        ];
+        for (opcode, operation) in cases {
+            // lconst_1, lconst_0, <opcode>, lreturn
+            let code = [0x0a, 0x09, opcode, 0xad];
+            assert_operand_order("()J", &code, operation, Const::Long(1), Const::Long(0));
+        }
+    }
+
+    #[test]
+    fn long_shift_operand_order() {
+        let cases = vec![
+            (0x79, BinaryOperation::ShiftLeft),
+            (0x7b, BinaryOperation::ShiftRightArithmetic),
+            (0x7d, BinaryOperation::ShiftRightLogical),
+        ];
+        for (opcode, operation) in cases {
+            // lconst_1, iconst_3, <opcode>, lreturn
+            let code = [0x0a, 0x06, opcode, 0xad];
+            assert_operand_order("()J", &code, operation, Const::Long(1), Const::Int(3));
+        }
+    }
+}
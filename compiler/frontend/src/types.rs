@@ -1,5 +1,9 @@
+use std::fmt;
+
+use classfile::attrs::stack_map_table::VerificationTypeInfo;
 use classfile::instructions::ArrayType;
 use classfile::FieldType;
+use failure::{bail, Fallible};
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Type {
@@ -47,7 +51,107 @@ impl Type {
         }
     }
 
+    pub fn from_verification_type(info: &VerificationTypeInfo) -> Fallible<Self> {
+        match info {
+            VerificationTypeInfo::Top => bail!("top verification type has no value type"),
+            VerificationTypeInfo::Integer => Ok(Type::Int),
+            VerificationTypeInfo::Float => Ok(Type::Float),
+            VerificationTypeInfo::Long => Ok(Type::Long),
+            VerificationTypeInfo::Double => Ok(Type::Double),
+            VerificationTypeInfo::Null
+            | VerificationTypeInfo::UninitializedThis
+            | VerificationTypeInfo::Object(_)
+            | VerificationTypeInfo::Uninitialized(_) => Ok(Type::Reference),
+        }
+    }
+
+    /// The type the JVM computes with on the operand stack, where booleans,
+    /// chars, bytes and shorts are all ints.
+    pub fn computational_type(&self) -> Type {
+        match self {
+            Type::Boolean | Type::Char | Type::Byte | Type::Short => Type::Int,
+            other => other.clone(),
+        }
+    }
+
+    /// Checks whether values of both types can be joined by a phi node,
+    /// which is the case when their computational types agree.
     pub fn can_unify_naive(&self, other: &Self) -> bool {
-        self == other
+        self.computational_type() == other.computational_type()
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Type::Boolean => "boolean",
+            Type::Char => "char",
+            Type::Byte => "byte",
+            Type::Short => "short",
+            Type::Int => "int",
+            Type::Long => "long",
+            Type::Float => "float",
+            Type::Double => "double",
+            Type::Reference => "ref",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strbuf::StrBuf;
+
+    use super::*;
+
+    #[test]
+    fn from_verification_type() {
+        let cases = vec![
+            (VerificationTypeInfo::Integer, Type::Int),
+            (VerificationTypeInfo::Long, Type::Long),
+            (
+                VerificationTypeInfo::Object(StrBuf::new("java/lang/String")),
+                Type::Reference,
+            ),
+            (VerificationTypeInfo::Uninitialized(4), Type::Reference),
+            (VerificationTypeInfo::UninitializedThis, Type::Reference),
+        ];
+        for (info, expected) in cases {
+            assert_eq!(expected, Type::from_verification_type(&info).unwrap());
+        }
+    }
+
+    #[test]
+    fn from_top_verification_type() {
+        assert!(Type::from_verification_type(&VerificationTypeInfo::Top).is_err());
+    }
+
+    #[test]
+    fn int_like_types_unify() {
+        for typ in &[Type::Boolean, Type::Char, Type::Byte, Type::Short] {
+            assert!(typ.can_unify_naive(&Type::Int));
+            assert!(Type::Int.can_unify_naive(typ));
+        }
+        assert!(Type::Char.can_unify_naive(&Type::Byte));
+        assert!(!Type::Int.can_unify_naive(&Type::Long));
+        assert!(!Type::Int.can_unify_naive(&Type::Float));
+        assert!(!Type::Int.can_unify_naive(&Type::Reference));
+    }
+
+    #[test]
+    fn display() {
+        let cases = vec![
+            (Type::Boolean, "boolean"),
+            (Type::Char, "char"),
+            (Type::Byte, "byte"),
+            (Type::Short, "short"),
+            (Type::Int, "int"),
+            (Type::Long, "long"),
+            (Type::Float, "float"),
+            (Type::Double, "double"),
+            (Type::Reference, "ref"),
+        ];
+        for (typ, expected) in cases {
+            assert_eq!(expected, typ.to_string());
+        }
     }
 }
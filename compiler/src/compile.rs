@@ -1,5 +1,4 @@
-use classfile::attrs::Code;
-use classfile::descriptors::ParameterDescriptor;
+use classfile::descriptors::{MethodDescriptor, ParameterDescriptor};
 use failure::{bail, Fallible};
 use strbuf::StrBuf;
 
@@ -9,19 +8,70 @@ use frontend::loader::Class;
 use frontend::translate::{self, VarIdGen};
 use frontend::types::Type;
 
-use backend::CodeGen;
+use backend::{ClassCodeGen, CodeGen, ModuleCodeGen};
+
+/// Selects the methods to translate, given as `<name>[:<descriptor>]`.
+#[derive(Clone, Debug)]
+pub struct MethodFilter {
+    name: String,
+    descriptor: Option<MethodDescriptor>,
+}
+
+impl MethodFilter {
+    pub fn parse(input: &str) -> Fallible<Self> {
+        let mut parts = input.splitn(2, ':');
+        let name = parts.next().unwrap_or_default().to_owned();
+        let descriptor = match parts.next() {
+            Some(descriptor) => Some(MethodDescriptor::try_from_str(descriptor)?),
+            None => None,
+        };
+        Ok(MethodFilter { name, descriptor })
+    }
+
+    fn matches(&self, name: &str, descriptor: &MethodDescriptor) -> bool {
+        self.name == name
+            && self
+                .descriptor
+                .iter()
+                .all(|expected| expected == descriptor)
+    }
+}
 
 pub struct Compiler {
     classes: ClassGraph,
     codegen: CodeGen,
+    only_method: Option<MethodFilter>,
+    dump_types: bool,
 }
 
 impl Compiler {
-    pub fn new(classes: ClassGraph, codegen: CodeGen) -> Self {
-        Self { classes, codegen }
+    pub fn new(
+        classes: ClassGraph,
+        codegen: CodeGen,
+        only_method: Option<MethodFilter>,
+        dump_types: bool,
+    ) -> Self {
+        Self {
+            classes,
+            codegen,
+            only_method,
+            dump_types,
+        }
     }
 
     pub fn compile(&mut self, class_name: &StrBuf, main: bool) -> Fallible<String> {
+        self.generate(class_name, main)?.finish()
+    }
+
+    pub fn gen_symbols(&self, class_names: &[StrBuf]) -> Fallible<String> {
+        self.codegen.gen_symbols(class_names)
+    }
+
+    pub fn generate_module(&self, name: &str) -> Fallible<ModuleCodeGen> {
+        self.codegen.generate_module(name)
+    }
+
+    pub fn generate(&mut self, class_name: &StrBuf, main: bool) -> Fallible<ClassCodeGen> {
         let class_file = match self.classes.get(&class_name)? {
             Class::File(class_file) => class_file,
             class => bail!("unexpected class type {:?}", class),
@@ -30,6 +80,8 @@ impl Compiler {
         let mut classgen = self.codegen.generate_class(class_name)?;
 
         classgen.gen_vtable_const(&class_file)?;
+        classgen.gen_class_id_const(&class_file)?;
+        classgen.gen_static_fields(&class_file)?;
 
         for method in class_file.methods.iter() {
             let name = class_file
@@ -58,14 +110,30 @@ impl Compiler {
                 continue;
             }
 
-            let code = method.attributes.get::<Code>().unwrap();
+            if let Some(ref filter) = self.only_method {
+                if !filter.matches(name, &method.descriptor) {
+                    // keep the method callable, but without translating its code
+                    classgen.gen_abstract_method(method, &args, &class_file.constant_pool)?;
+                    continue;
+                }
+            }
+
+            let code = match method.code()? {
+                Some(code) => code,
+                None => bail!("method {} of class {} has no code", name, class_name),
+            };
             let state = StackAndLocals::new(code.max_stack, code.max_locals, &args);
             let blocks = translate::translate_method(
                 code.disassemble(),
                 state,
                 &class_file.constant_pool,
+                &method.descriptor.ret,
                 &mut var_id_gen,
             )?;
+            if self.dump_types {
+                println!("{}.{}", class_name, name);
+                print!("{}", blocks.dump_types());
+            }
             classgen.gen_method(&method, &blocks, &class_file.constant_pool)?;
 
             if &**name == "<clinit>" {
@@ -77,6 +145,6 @@ impl Compiler {
             classgen.gen_main()?;
         }
 
-        Ok(classgen.finish()?)
+        Ok(classgen)
     }
 }
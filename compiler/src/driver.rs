@@ -4,34 +4,63 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use classfile::ClassFile;
-use failure::{bail, Fallible};
+use classfile::{ClassFile, MethodAccessFlags, MethodDescriptor};
+use failure::{bail, ensure, Error, Fallible};
 use llvm;
+use strbuf::StrBuf;
 use target_lexicon::{OperatingSystem, Triple};
 
 use frontend::classes::ClassGraph;
-use frontend::loader::{BootstrapClassLoader, InputClassLoader};
+use frontend::loader::{BootstrapClassLoader, Class, InputClassLoader};
 
-use backend::{CodeGen, Target};
+use backend::{CodeGen, MangleScheme, Target};
 
-use crate::compile::Compiler;
+use crate::compile::{Compiler, MethodFilter};
+
+/// Settings that control how the driver compiles classes.
+#[derive(Clone, Debug)]
+pub struct DriverOptions {
+    /// Optimizes the generated code aggressively.
+    pub optimize: bool,
+    /// Emits debug info, so that the generated code can be stepped through
+    /// in a debugger.
+    pub debug_info: bool,
+    /// Compiles all classes into a single module instead of one module per
+    /// class.
+    pub single_module: bool,
+    /// Prints the type of every variable of each translated method.
+    pub dump_types: bool,
+    /// Only translates the methods that match the filter.
+    pub only_method: Option<MethodFilter>,
+    /// Continues with the remaining classes when a class fails to compile,
+    /// so that `report` can list all failures at once.
+    pub keep_going: bool,
+    /// The scheme for the symbol names. Only `MangleScheme::Itanium` can be
+    /// linked with the runtime.
+    pub mangle_scheme: MangleScheme,
+}
 
 pub struct Driver {
     loader: BootstrapClassLoader,
     target_triple: Triple,
-    optimize: bool,
+    options: DriverOptions,
     modules: HashMap<String, String>,
+    symbols: String,
+    failures: Vec<(StrBuf, Error)>,
     machine: llvm::codegen::TargetMachine,
 }
 
 impl Driver {
-    pub fn try_new(home: PathBuf, target_triple: Triple, optimize: bool) -> Fallible<Self> {
+    pub fn try_new(home: PathBuf, target_triple: Triple, options: DriverOptions) -> Fallible<Self> {
         let loader = BootstrapClassLoader::open(home)?;
         let modules = HashMap::new();
 
         let mut machine_builder = llvm::codegen::TargetMachine::builder();
+        if target_triple != Triple::host() {
+            machine_builder.set_triple(&target_triple.to_string())?;
+        }
         machine_builder.set_reloc_mode(llvm::codegen::RelocMode::PIC);
-        if optimize {
+        if options.optimize {
             machine_builder.set_opt_level(llvm::codegen::OptLevel::Aggressive);
         }
         let machine = machine_builder.build()?;
@@ -39,13 +68,15 @@ impl Driver {
         Ok(Driver {
             loader,
             target_triple,
-            optimize,
+            options,
             modules,
+            symbols: String::new(),
+            failures: vec![],
             machine,
         })
     }
 
-    pub fn compile(&mut self, main: &str, inputs: &[PathBuf]) -> Fallible<()> {
+    pub fn compile(&mut self, main: Option<&str>, inputs: &[PathBuf]) -> Fallible<()> {
         let mut loader = InputClassLoader::new(self.loader.clone());
 
         let mut class_names = vec!["java/lang/Object".to_owned().into()];
@@ -59,22 +90,88 @@ impl Driver {
         }
 
         let classes = ClassGraph::new(loader);
+        classes.preload(&class_names)?;
+        if let Some(main) = main {
+            check_main_class(&classes, &class_names, main)?;
+        }
         let target = Target {
             triple: self.machine.triple().to_string(),
             data_layout: self.machine.data_layout().to_string_rep().to_string(),
+            mangle_scheme: self.options.mangle_scheme,
+            debug_info: self.options.debug_info,
+            // arrays don't record their component class yet, so the runtime
+            // can't check stores into them
+            array_store_checks: false,
         };
-        let codegen = CodeGen::try_new(classes.clone(), target)?;
-        let mut compiler = Compiler::new(classes.clone(), codegen);
+        let mut codegen = CodeGen::try_new(classes.clone(), target)?;
+        codegen.assign_class_ids(&class_names)?;
+        let mut compiler = Compiler::new(
+            classes.clone(),
+            codegen,
+            self.options.only_method.clone(),
+            self.options.dump_types,
+        );
 
-        for class_name in class_names {
-            let module = compiler.compile(&class_name, &*class_name == main)?;
-            self.modules.insert(class_name.to_string(), module);
+        if self.options.single_module {
+            let mut module = compiler.generate_module("main")?;
+            for class_name in class_names.iter() {
+                let is_main = main == Some(&**class_name);
+                let result = compiler.generate(class_name, is_main);
+                if let Some(class) = self.check_class(class_name, result)? {
+                    module.add_class(class);
+                }
+            }
+            self.modules.insert("main".to_owned(), module.finish()?);
+        } else {
+            for class_name in class_names.iter() {
+                let is_main = main == Some(&**class_name);
+                let result = compiler.compile(class_name, is_main);
+                if let Some(module) = self.check_class(class_name, result)? {
+                    self.modules.insert(class_name.to_string(), module);
+                }
+            }
         }
 
+        let compiled = class_names
+            .into_iter()
+            .filter(|name| self.failures.iter().all(|(failed, _)| failed != name))
+            .collect::<Vec<_>>();
+        self.symbols = compiler.gen_symbols(&compiled)?;
+
         Ok(())
     }
 
+    /// Passes on the error of compiling a class, unless the driver keeps
+    /// going, in which case it is recorded for `report` instead.
+    fn check_class<T>(&mut self, class_name: &StrBuf, result: Fallible<T>) -> Fallible<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if self.options.keep_going => {
+                self.failures.push((class_name.clone(), err));
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Fails with a summary of the classes that could not be compiled when
+    /// keeping going.
+    pub fn report(&self) -> Fallible<()> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        let mut summary = format!("{} class(es) failed to compile", self.failures.len());
+        for (class_name, err) in self.failures.iter() {
+            summary.push_str(&format!("\n  {}: {}", class_name, err));
+        }
+        bail!("{}", summary)
+    }
+
+    /// Writes the IR of each compiled class to its own file in `path`, named
+    /// after the class with `/` replaced by `.` (e.g. `java.lang.Object.ll`).
+    /// When compiling into a single module, it is written to `main.ll`.
     pub fn dump(&self, path: &Path) -> Fallible<()> {
+        fs::create_dir_all(path)?;
         for (name, module) in self.modules.iter() {
             let filename = format!("{}.ll", name.replace("/", "."));
             let mut file = fs::File::create(path.join(filename))?;
@@ -83,22 +180,32 @@ impl Driver {
         Ok(())
     }
 
-    pub fn link(&self, runtime_path: &Path, output_path: &Path) -> Fallible<()> {
-        let mut main = llvm::Module::new("main");
+    /// Writes the symbol manifest of the compiled classes, which lists the
+    /// vtable, class id and method symbols of each class.
+    pub fn emit_symbols(&self, path: &Path) -> Fallible<()> {
+        fs::write(path, &self.symbols)?;
+        Ok(())
+    }
 
-        for (_name, module) in self.modules.iter() {
-            main.link(llvm::Module::parse_ir(module.as_bytes())?)?;
-        }
+    pub fn emit_llvm(&self, path: &Path) -> Fallible<()> {
+        let main = self.link_modules()?;
+        fs::write(path, main.to_ir().to_string())?;
+        Ok(())
+    }
 
-        let mut pass_manager_builder = llvm::transform::PassManagerBuilder::new();
-        if self.optimize {
-            pass_manager_builder.set_opt_level(llvm::transform::OptLevel::O3);
-        } else {
-            pass_manager_builder.set_opt_level(llvm::transform::OptLevel::O0);
-        }
-        let pass_manager = pass_manager_builder.build();
+    /// Writes an object file with the code of all compiled classes, to be
+    /// linked into a program together with the runtime.
+    pub fn emit_object(&self, path: &Path) -> Fallible<()> {
+        let main = self.link_modules()?;
+        let main_obj = self
+            .machine
+            .emit_to_buffer(&main, llvm::codegen::FileType::Object)?;
+        fs::write(path, &*main_obj)?;
+        Ok(())
+    }
 
-        pass_manager.run(&mut main);
+    pub fn link(&self, runtime_path: &Path, output_path: &Path) -> Fallible<()> {
+        let main = self.link_modules()?;
         let main_obj = self
             .machine
             .emit_to_buffer(&main, llvm::codegen::FileType::Object)?;
@@ -140,4 +247,62 @@ impl Driver {
 
         Ok(())
     }
+
+    fn link_modules(&self) -> Fallible<llvm::Module> {
+        let mut main = llvm::Module::new("main");
+
+        for (_name, module) in self.modules.iter() {
+            let mut module = llvm::Module::parse_ir(module.as_bytes())?;
+            if self.options.optimize {
+                self.pass_manager_builder()
+                    .build_for_module()
+                    .run(&mut module);
+            }
+            main.link(module)?;
+        }
+
+        self.pass_manager_builder().build().run(&mut main);
+        main.verify()?;
+
+        Ok(main)
+    }
+
+    fn pass_manager_builder(&self) -> llvm::transform::PassManagerBuilder {
+        let mut pass_manager_builder = llvm::transform::PassManagerBuilder::new();
+        if self.options.optimize {
+            pass_manager_builder.set_opt_level(llvm::transform::OptLevel::O3);
+        } else {
+            pass_manager_builder.set_opt_level(llvm::transform::OptLevel::O0);
+        }
+        pass_manager_builder
+    }
+}
+
+/// Checks that the main class is compiled along with the other inputs, and
+/// declares the `public static void main(String[])` method that the
+/// generated entry point calls.
+fn check_main_class(classes: &ClassGraph, class_names: &[StrBuf], main: &str) -> Fallible<()> {
+    ensure!(
+        class_names.iter().any(|name| &**name == main),
+        "main class {} is not one of the input classes",
+        main
+    );
+    let class_file = match classes.get(&StrBuf::new(main))? {
+        Class::File(class_file) => class_file,
+        class => bail!("unexpected class type {:?}", class),
+    };
+    let descriptor = MethodDescriptor::try_from_str("([Ljava/lang/String;)V")?;
+    let has_main = class_file.methods.iter().any(|method| {
+        let name = class_file.constant_pool.get_utf8(method.name_index);
+        name.map(|name| &**name) == Some("main")
+            && method.descriptor == descriptor
+            && method.is_static()
+            && method.access_flags.contains(MethodAccessFlags::PUBLIC)
+    });
+    ensure!(
+        has_main,
+        "main class {} does not declare public static void main(String[])",
+        main
+    );
+    Ok(())
 }
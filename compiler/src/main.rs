@@ -1,15 +1,18 @@
 use std::alloc::System;
 use std::env;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use failure::{format_err, Fallible};
+use backend::MangleScheme;
+use failure::{bail, format_err, Fallible};
 use structopt::StructOpt;
 use target_lexicon::Triple;
 
 mod compile;
 mod driver;
 
-use crate::driver::Driver;
+use crate::compile::MethodFilter;
+use crate::driver::{Driver, DriverOptions};
 
 #[global_allocator]
 static GLOBAL: System = System;
@@ -20,18 +23,64 @@ static GLOBAL: System = System;
     about = "Compile JVM classfiles into a native executable."
 )]
 struct Compile {
-    #[structopt(parse(from_os_str), short = "o")]
-    output: PathBuf,
-    #[structopt(parse(from_os_str), short = "r")]
-    runtime: PathBuf,
+    #[structopt(parse(from_os_str), short = "o", required_unless = "emit_llvm")]
+    output: Option<PathBuf>,
+    #[structopt(
+        parse(from_os_str),
+        short = "r",
+        raw(required_unless_one = r#"&["emit_llvm", "no_main"]"#)
+    )]
+    runtime: Option<PathBuf>,
     #[structopt(parse(from_os_str))]
     inputs: Vec<PathBuf>,
-    #[structopt(long = "main")]
-    main: String,
+    #[structopt(long = "main", required_unless = "no_main")]
+    main: Option<String>,
+    #[structopt(long = "no-main", conflicts_with = "main")]
+    no_main: bool,
+    #[structopt(long = "target")]
+    target: Option<String>,
     #[structopt(short = "O")]
     optimize: bool,
+    #[structopt(short = "g")]
+    debug_info: bool,
+    #[structopt(long = "single-module")]
+    single_module: bool,
+    #[structopt(long = "only-method")]
+    only_method: Option<String>,
+    #[structopt(long = "types")]
+    dump_types: bool,
+    #[structopt(long = "keep-going")]
+    keep_going: bool,
     #[structopt(parse(from_os_str), long = "save-temp")]
     save_temp: Option<PathBuf>,
+    #[structopt(parse(from_os_str), long = "emit-symbols")]
+    emit_symbols: Option<PathBuf>,
+    #[structopt(parse(from_os_str), long = "emit-llvm")]
+    emit_llvm: Option<PathBuf>,
+    #[structopt(long = "readable-names", requires = "emit_llvm")]
+    readable_names: bool,
+}
+
+impl Compile {
+    fn driver_options(&self) -> Fallible<DriverOptions> {
+        let only_method = match self.only_method {
+            Some(ref only_method) => Some(MethodFilter::parse(only_method)?),
+            None => None,
+        };
+        Ok(DriverOptions {
+            optimize: self.optimize,
+            debug_info: self.debug_info,
+            single_module: self.single_module,
+            dump_types: self.dump_types,
+            only_method,
+            keep_going: self.keep_going,
+            mangle_scheme: if self.readable_names {
+                MangleScheme::Readable
+            } else {
+                MangleScheme::Itanium
+            },
+        })
+    }
 }
 
 fn compile(c: &Compile) -> Fallible<()> {
@@ -39,17 +88,36 @@ fn compile(c: &Compile) -> Fallible<()> {
         env::var("JAVA_HOME").map_err(|_| format_err!("could not read JAVA_HOME variable"))?,
     );
 
-    let triple = Triple::host();
+    let triple = match c.target {
+        Some(ref target) => {
+            Triple::from_str(target).map_err(|_| format_err!("invalid target triple {}", target))?
+        }
+        None => Triple::host(),
+    };
 
-    let mut driver = Driver::try_new(home, triple, c.optimize)?;
+    let mut driver = Driver::try_new(home, triple, c.driver_options()?)?;
 
-    driver.compile(&c.main, &c.inputs)?;
+    driver.compile(c.main.as_deref(), &c.inputs)?;
 
     if let Some(ref temppath) = c.save_temp {
         driver.dump(temppath)?;
     }
 
-    driver.link(&c.runtime, &c.output)?;
+    driver.report()?;
+
+    if let Some(ref symbols_path) = c.emit_symbols {
+        driver.emit_symbols(symbols_path)?;
+    }
+
+    if let Some(ref llvm_path) = c.emit_llvm {
+        return driver.emit_llvm(llvm_path);
+    }
+
+    match (&c.runtime, &c.output) {
+        (_, Some(output)) if c.no_main => driver.emit_object(output)?,
+        (Some(runtime), Some(output)) => driver.link(runtime, output)?,
+        _ => bail!("runtime and output paths are required for linking"),
+    }
 
     Ok(())
 }
@@ -0,0 +1,27 @@
+use assert_cli::Assert;
+
+mod utils;
+
+use utils::JavaSource;
+
+#[test]
+fn custom_alloc_overrides_runtime_default() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { static Object object = new Object(); static int[] array = new int[4]; }\n",
+    );
+
+    // the static initializer runs before `main`, allocating through `_Jrt_alloc`
+    let executable = source.link_with_c(&[(
+        "alloc.c",
+        "#include <stdio.h>\n\
+         #include <stdlib.h>\n\
+         void *_Jrt_alloc(size_t size) { printf(\"_Jrt_alloc\\n\"); return malloc(size); }\n\
+         int main(void) { return 0; }\n",
+    )]);
+
+    Assert::command(&[executable])
+        .stdout()
+        .is("_Jrt_alloc\n_Jrt_alloc\n")
+        .unwrap();
+}
@@ -7,6 +7,7 @@ cases! {
     clinit,
     for_loop,
     while_loop,
+    decrement_loop,
     if_else,
     inheritance,
     table_switch,
@@ -16,5 +17,28 @@ cases! {
     fields,
     array,
     interfaces,
-    sync_block
+    marker_interface,
+    sync_block,
+    sync_method,
+    shift_long,
+    return_narrow,
+    return_wide,
+    native_method,
+    default_package,
+    compare_float,
+    compare_double,
+    compare_nan,
+    load_wide_const,
+    load_float_const,
+    push_const,
+    operand_order,
+    invoke_order,
+    super_call,
+    inherited_static_call,
+    wide_args,
+    check_cast,
+    static_field,
+    inherited_static_field,
+    null_values,
+    array_negative_size
 }
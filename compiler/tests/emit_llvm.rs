@@ -0,0 +1,143 @@
+use std::fs;
+
+use assert_cli::Assert;
+
+mod utils;
+
+use utils::JavaSource;
+
+fn compile_to_ir(flags: &[&str]) -> String {
+    compile_source_to_ir(
+        "public class Test { public static void main(String[] args) {} }",
+        flags,
+    )
+}
+
+fn compile_source_to_ir(source: &str, flags: &[&str]) -> String {
+    let source = JavaSource::compile("Test", source);
+    let llvm_path = source.path().join("Test.ll");
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--main", "Test"])
+        .with_args(flags)
+        .with_args(&["--emit-llvm"])
+        .with_args(&[&llvm_path])
+        .with_args(&source.classes())
+        .unwrap();
+
+    let ir = fs::read(&llvm_path).unwrap();
+    llvm::Module::parse_ir(&ir).unwrap();
+    String::from_utf8(ir).unwrap()
+}
+
+#[test]
+fn emit_llvm() {
+    let ir = compile_to_ir(&[]);
+    assert!(ir.contains("define i32 @main("));
+    assert!(!ir.contains("!llvm.dbg.cu"));
+}
+
+#[test]
+fn emit_llvm_with_debug_info() {
+    let ir = compile_to_ir(&["-g"]);
+    assert!(ir.contains("!llvm.dbg.cu"));
+    assert!(ir.contains("!DISubprogram(name: \"main\""));
+    assert!(ir.contains(", !dbg !"));
+}
+
+#[test]
+fn emit_llvm_without_array_store_checks() {
+    let ir = compile_source_to_ir(
+        "public class Test { public static void main(String[] args) { \
+         Object[] objects = new Object[1]; objects[0] = args; } }",
+        &[],
+    );
+    assert!(!ir.contains("call void @_Jrt_array_store_check("));
+}
+
+#[test]
+fn emit_llvm_with_readable_names() {
+    let ir = compile_to_ir(&["--readable-names"]);
+    assert!(ir.contains("define void @Test.main$_Ljava.lang.String-$V("));
+    assert!(!ir.contains("@_ZN4Test"));
+}
+
+#[test]
+fn emit_llvm_for_non_host_target() {
+    let triple = if cfg!(target_arch = "aarch64") {
+        "x86_64-unknown-linux-gnu"
+    } else {
+        "aarch64-unknown-linux-gnu"
+    };
+    let ir = compile_to_ir(&["--target", triple]);
+    assert!(ir.contains(&format!("target triple = \"{}\"", triple)));
+}
+
+#[test]
+fn emit_llvm_sizes_follow_target_pointer_width() {
+    let ir = compile_source_to_ir(
+        "public class Test { static Object[] OBJECTS = new Object[3]; \
+         public static void main(String[] args) {} }",
+        &["-O", "--target", "i686-unknown-linux-gnu"],
+    );
+    assert!(ir.contains("target datalayout = \"e-m:e-p:32:32"));
+    // a reference is a pair of pointers, i.e. 8 bytes with 32-bit pointers
+    assert!(ir.contains("@_Jrt_array_new(i32 3, i64 8)"));
+}
+
+#[test]
+fn emit_llvm_optimized() {
+    let source = "public class Test { static int limit = 100; static int total; \
+                  public static void main(String[] args) { \
+                  for (int i = 0; i < limit; i++) { total = total + limit; } } }";
+    let count_loads = |ir: &str| ir.matches("load i32, i32* @_ZN4Test5limitE").count();
+
+    let ir = compile_source_to_ir(source, &[]);
+    let optimized_ir = compile_source_to_ir(source, &["-O"]);
+
+    assert!(count_loads(&optimized_ir) < count_loads(&ir));
+}
+
+#[test]
+fn emit_llvm_class_init_stores_static_field() {
+    let ir = compile_source_to_ir(
+        "public class Test { static int X = 5; public static void main(String[] args) {} }",
+        &[],
+    );
+    assert!(ir.contains("@_ZN4Test1XE = global i32 zeroinitializer"));
+    assert!(ir.contains("store i32 5, i32* @_ZN4Test1XE"));
+    assert!(!ir.contains("@_ZN4Test1XE = external global"));
+}
+
+#[test]
+fn emit_llvm_only_method() {
+    let ir = compile_source_to_ir(
+        "public class Test { static int helper() { return 42; } \
+         public static void main(String[] args) { helper(); } }",
+        &["--only-method", "main"],
+    );
+    let body = |name: &str| {
+        let symbol = format!("@_ZN4Test{}{}", name.len(), name);
+        let start = ir
+            .match_indices("define ")
+            .map(|(idx, _)| idx)
+            .find(|idx| ir[*idx..].lines().next().unwrap().contains(&symbol))
+            .unwrap();
+        let end = start + ir[start..].find("\n}").unwrap();
+        ir[start..end].to_owned()
+    };
+    assert!(body("main").contains("call i32 @_ZN4Test6helper"));
+    assert!(body("helper").contains("call void @_Jrt_abstract()"));
+}
+
+#[test]
+fn emit_llvm_class_ids() {
+    let ir = compile_source_to_ir(
+        "public class Test { public static void main(String[] args) {} }",
+        &[],
+    );
+    assert!(
+        ir.contains("@_Jrt_classid_N4java4lang6ObjectE = constant { i32, i32 } { i32 0, i32 1 }")
+    );
+    assert!(ir.contains("@_Jrt_classid_N4TestE = constant { i32, i32 } { i32 1, i32 1 }"));
+}
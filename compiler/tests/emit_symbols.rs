@@ -0,0 +1,34 @@
+use std::fs;
+
+use assert_cli::Assert;
+
+mod utils;
+
+use utils::JavaSource;
+
+#[test]
+fn emit_symbols_lists_vtables_and_methods() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { public static void main(String[] args) {} }\n",
+    );
+    let tmppath = source.path();
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--main", "Test"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[tmppath.join("Test.ll")])
+        .with_args(&["--emit-symbols"])
+        .with_args(&[tmppath.join("Test.symbols")])
+        .with_args(&source.classes())
+        .unwrap();
+
+    let symbols = fs::read_to_string(tmppath.join("Test.symbols")).unwrap();
+    let lines = symbols.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"java/lang/Object vtable _ZTVN4java4lang6ObjectE"));
+    assert!(lines.contains(&"Test vtable _ZTVN4TestE"));
+    assert!(lines.contains(&"Test classid _Jrt_classid_N4TestE"));
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("Test method main([Ljava/lang/String;)V _ZN4Test4main")));
+}
@@ -0,0 +1,76 @@
+//! Compares the IR generated for the classes in `tests/golden` with the
+//! snapshots checked in next to them. Run with `UPDATE_GOLDEN=1` to rewrite
+//! the snapshots after an intended change to the generated code.
+//!
+//! The fixtures bring their own minimal `java.lang.Object`, so the snapshots
+//! don't depend on the class library of the installed JDK.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+
+use assert_cli::Assert;
+use regex::{Captures, Regex};
+use tempfile::TempDir;
+
+const CLASSES: &[&str] = &["FieldAccess", "VirtualDispatch", "Square", "Switch"];
+
+/// Removes the target specific lines, and renumbers temporaries in the order
+/// they appear in.
+fn normalize(ir: &str) -> String {
+    let tmp_regex = Regex::new(r"%t(\d+)").unwrap();
+    let mut tmp_ids = HashMap::new();
+    let mut output = String::new();
+    for line in ir.lines().filter(|line| !line.starts_with("target ")) {
+        let line = tmp_regex.replace_all(line, |caps: &Captures| {
+            let next_id = tmp_ids.len();
+            let id = *tmp_ids.entry(caps[1].to_owned()).or_insert(next_id);
+            format!("%t{}", id)
+        });
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+#[test]
+fn golden_ir() {
+    let golden_path = env::current_dir().unwrap().join("tests/golden");
+    let tmpdir = TempDir::new().unwrap();
+    let temppath = tmpdir.path().join("temp");
+
+    let mut inputs = vec![golden_path.join("java/lang/Object.class")];
+    inputs.extend(
+        CLASSES
+            .iter()
+            .map(|name| golden_path.join(format!("{}.class", name))),
+    );
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--no-main"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[tmpdir.path().join("main.ll")])
+        .with_args(&["--save-temp"])
+        .with_args(&[&temppath])
+        .with_args(&inputs)
+        .unwrap();
+
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    for name in CLASSES {
+        let ir = fs::read_to_string(temppath.join(format!("{}.ll", name))).unwrap();
+        let actual = normalize(&ir);
+        let snapshot_path = golden_path.join(format!("{}.ll", name));
+        if update {
+            fs::write(&snapshot_path, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&snapshot_path).unwrap();
+        assert!(
+            expected == actual,
+            "IR of {} does not match {}, got:\n{}",
+            name,
+            snapshot_path.display(),
+            actual
+        );
+    }
+}
@@ -0,0 +1,16 @@
+public class FieldAccess {
+    static int instances;
+
+    private int count;
+    private long total;
+
+    void add(long value) {
+        count += 1;
+        total += value;
+        instances++;
+    }
+
+    long total() {
+        return total;
+    }
+}
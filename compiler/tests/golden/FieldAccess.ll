@@ -0,0 +1,101 @@
+; ModuleID = 'FieldAccess'
+source_filename = "FieldAccess.java"
+
+%ref = type { i8*, i8* }
+declare i32 @_Jrt_start(i32, i8**, void (%ref) *)
+declare %ref @_Jrt_object_new(i64, i8*)
+declare i8* @_Jrt_object_field_ptr(%ref)
+declare i8* @_Jrt_object_vtable_lookup(%ref, i64)
+declare i8* @_Jrt_object_itable_lookup(%ref, i8*, i64)
+declare void @_Jrt_object_monitorenter(%ref)
+declare void @_Jrt_object_monitorexit(%ref)
+declare void @_Jrt_class_monitorenter(i8**)
+declare void @_Jrt_class_monitorexit(i8**)
+declare %ref @_Jrt_array_new(i32, i64)
+declare i32 @_Jrt_array_length(%ref)
+declare i8* @_Jrt_array_element_ptr(%ref)
+declare void @_Jrt_array_store_check(%ref, %ref)
+declare i32 @_Jrt_idiv(i32, i32)
+declare i32 @_Jrt_irem(i32, i32)
+declare i64 @_Jrt_ldiv(i64, i64)
+declare i64 @_Jrt_lrem(i64, i64)
+declare void @_Jrt_throw(%ref) noreturn
+declare void @_Jrt_abstract() noreturn
+declare %ref @_Jrt_ldstr(i8*)
+%_ZTVN11FieldAccessE = type {
+  i32, ; <number of methods>
+  i32 (%ref) *, ; #0 method hashCode
+  void (%ref, i64) *, ; #1 method add
+  i64 (%ref) *, ; #2 method total
+  i32 ; <number of interfaces>
+}
+
+declare i32 @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv(%ref)
+
+declare void @_ZN4java4lang6Object4initIu9Jd2b61b67EEvv(%ref)
+
+%_ZN11FieldAccessE = type {
+  i32, ; count
+  i64 ; total
+}
+
+@_ZTVN11FieldAccessE = constant %_ZTVN11FieldAccessE {
+  i32 3, ; <number of methods>
+  i32 (%ref) * @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv,
+  void (%ref, i64) * @_ZN11FieldAccess3addIu9Jd3dc0ee3EEvl,
+  i64 (%ref) * @_ZN11FieldAccess5totalIu9Jacaaf3b8EElv,
+  i32 0 ; <number of interfaces>
+}
+@_Jrt_classid_N11FieldAccessE = constant { i32, i32 } { i32 1, i32 1 } ; <class id, last subclass id>
+@_ZN11FieldAccess9instancesE = global i32 zeroinitializer
+
+define void @_ZN11FieldAccess4initIu9J5a7ec79aEEvv(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  call void @_ZN4java4lang6Object4initIu9Jd2b61b67EEvv(%ref %v1)
+  ret void
+}
+
+define void @_ZN11FieldAccess3addIu9Jd3dc0ee3EEvl(%ref %v0, i64 %v1) {
+entry:
+  br label %B0
+B0:
+  %v2 = phi %ref [ %v0, %entry ]
+  %v3 = phi i64 [ %v1, %entry ]
+  %t0 = call i8* @_Jrt_object_field_ptr(%ref %v2)
+  %t1 = bitcast i8* %t0 to %_ZN11FieldAccessE*
+  %t2 = getelementptr %_ZN11FieldAccessE, %_ZN11FieldAccessE* %t1, i64 0, i32 0
+  %v4 = load i32, i32* %t2
+  %v5 = add i32 %v4, 1
+  %t3 = call i8* @_Jrt_object_field_ptr(%ref %v2)
+  %t4 = bitcast i8* %t3 to %_ZN11FieldAccessE*
+  %t5 = getelementptr %_ZN11FieldAccessE, %_ZN11FieldAccessE* %t4, i64 0, i32 0
+  store i32 %v5, i32* %t5
+  %t6 = call i8* @_Jrt_object_field_ptr(%ref %v2)
+  %t7 = bitcast i8* %t6 to %_ZN11FieldAccessE*
+  %t8 = getelementptr %_ZN11FieldAccessE, %_ZN11FieldAccessE* %t7, i64 0, i32 1
+  %v7 = load i64, i64* %t8
+  %v8 = add i64 %v7, %v3
+  %t9 = call i8* @_Jrt_object_field_ptr(%ref %v2)
+  %t10 = bitcast i8* %t9 to %_ZN11FieldAccessE*
+  %t11 = getelementptr %_ZN11FieldAccessE, %_ZN11FieldAccessE* %t10, i64 0, i32 1
+  store i64 %v8, i64* %t11
+  %v10 = load i32, i32* @_ZN11FieldAccess9instancesE
+  %v11 = add i32 %v10, 1
+  store i32 %v11, i32* @_ZN11FieldAccess9instancesE
+  ret void
+}
+
+define i64 @_ZN11FieldAccess5totalIu9Jacaaf3b8EElv(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  %t12 = call i8* @_Jrt_object_field_ptr(%ref %v1)
+  %t13 = bitcast i8* %t12 to %_ZN11FieldAccessE*
+  %t14 = getelementptr %_ZN11FieldAccessE, %_ZN11FieldAccessE* %t13, i64 0, i32 1
+  %v2 = load i64, i64* %t14
+  ret i64 %v2
+}
@@ -0,0 +1,72 @@
+; ModuleID = 'Square'
+source_filename = "VirtualDispatch.java"
+
+%ref = type { i8*, i8* }
+declare i32 @_Jrt_start(i32, i8**, void (%ref) *)
+declare %ref @_Jrt_object_new(i64, i8*)
+declare i8* @_Jrt_object_field_ptr(%ref)
+declare i8* @_Jrt_object_vtable_lookup(%ref, i64)
+declare i8* @_Jrt_object_itable_lookup(%ref, i8*, i64)
+declare void @_Jrt_object_monitorenter(%ref)
+declare void @_Jrt_object_monitorexit(%ref)
+declare void @_Jrt_class_monitorenter(i8**)
+declare void @_Jrt_class_monitorexit(i8**)
+declare %ref @_Jrt_array_new(i32, i64)
+declare i32 @_Jrt_array_length(%ref)
+declare i8* @_Jrt_array_element_ptr(%ref)
+declare void @_Jrt_array_store_check(%ref, %ref)
+declare i32 @_Jrt_idiv(i32, i32)
+declare i32 @_Jrt_irem(i32, i32)
+declare i64 @_Jrt_ldiv(i64, i64)
+declare i64 @_Jrt_lrem(i64, i64)
+declare void @_Jrt_throw(%ref) noreturn
+declare void @_Jrt_abstract() noreturn
+declare %ref @_Jrt_ldstr(i8*)
+%_ZTVN6SquareE = type {
+  i32, ; <number of methods>
+  i32 (%ref) *, ; #0 method hashCode
+  i32 (%ref) *, ; #1 method area
+  i32 ; <number of interfaces>
+}
+
+declare i32 @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv(%ref)
+
+declare void @_ZN15VirtualDispatch4initIu9Jcb2f4821EEvv(%ref)
+
+%_ZN6SquareE = type {
+  i32 ; side
+}
+
+@_ZTVN6SquareE = constant %_ZTVN6SquareE {
+  i32 2, ; <number of methods>
+  i32 (%ref) * @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv,
+  i32 (%ref) * @_ZN6Square4areaIu9J9a2f209fEEiv,
+  i32 0 ; <number of interfaces>
+}
+@_Jrt_classid_N6SquareE = constant { i32, i32 } { i32 4, i32 4 } ; <class id, last subclass id>
+
+define void @_ZN6Square4initIu9J50267f19EEvv(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  call void @_ZN15VirtualDispatch4initIu9Jcb2f4821EEvv(%ref %v1)
+  ret void
+}
+
+define i32 @_ZN6Square4areaIu9J9a2f209fEEiv(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  %t0 = call i8* @_Jrt_object_field_ptr(%ref %v1)
+  %t1 = bitcast i8* %t0 to %_ZN6SquareE*
+  %t2 = getelementptr %_ZN6SquareE, %_ZN6SquareE* %t1, i64 0, i32 0
+  %v2 = load i32, i32* %t2
+  %t3 = call i8* @_Jrt_object_field_ptr(%ref %v1)
+  %t4 = bitcast i8* %t3 to %_ZN6SquareE*
+  %t5 = getelementptr %_ZN6SquareE, %_ZN6SquareE* %t4, i64 0, i32 0
+  %v3 = load i32, i32* %t5
+  %v4 = add i32 %v2, %v3
+  ret i32 %v4
+}
@@ -0,0 +1,25 @@
+public class Switch {
+    static int dense(int value) {
+        switch (value) {
+            case 1:
+                return 10;
+            case 2:
+                return 20;
+            case 3:
+                return 30;
+            default:
+                return -1;
+        }
+    }
+
+    static int sparse(int value) {
+        switch (value) {
+            case 10:
+                return 1;
+            case 1000:
+                return 2;
+            default:
+                return 0;
+        }
+    }
+}
@@ -0,0 +1,86 @@
+; ModuleID = 'Switch'
+source_filename = "Switch.java"
+
+%ref = type { i8*, i8* }
+declare i32 @_Jrt_start(i32, i8**, void (%ref) *)
+declare %ref @_Jrt_object_new(i64, i8*)
+declare i8* @_Jrt_object_field_ptr(%ref)
+declare i8* @_Jrt_object_vtable_lookup(%ref, i64)
+declare i8* @_Jrt_object_itable_lookup(%ref, i8*, i64)
+declare void @_Jrt_object_monitorenter(%ref)
+declare void @_Jrt_object_monitorexit(%ref)
+declare void @_Jrt_class_monitorenter(i8**)
+declare void @_Jrt_class_monitorexit(i8**)
+declare %ref @_Jrt_array_new(i32, i64)
+declare i32 @_Jrt_array_length(%ref)
+declare i8* @_Jrt_array_element_ptr(%ref)
+declare void @_Jrt_array_store_check(%ref, %ref)
+declare i32 @_Jrt_idiv(i32, i32)
+declare i32 @_Jrt_irem(i32, i32)
+declare i64 @_Jrt_ldiv(i64, i64)
+declare i64 @_Jrt_lrem(i64, i64)
+declare void @_Jrt_throw(%ref) noreturn
+declare void @_Jrt_abstract() noreturn
+declare %ref @_Jrt_ldstr(i8*)
+%_ZTVN6SwitchE = type {
+  i32, ; <number of methods>
+  i32 (%ref) *, ; #0 method hashCode
+  i32 ; <number of interfaces>
+}
+
+declare i32 @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv(%ref)
+
+declare void @_ZN4java4lang6Object4initIu9Jd2b61b67EEvv(%ref)
+
+@_ZTVN6SwitchE = constant %_ZTVN6SwitchE {
+  i32 1, ; <number of methods>
+  i32 (%ref) * @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv,
+  i32 0 ; <number of interfaces>
+}
+@_Jrt_classid_N6SwitchE = constant { i32, i32 } { i32 2, i32 2 } ; <class id, last subclass id>
+
+define void @_ZN6Switch4initIu9J45ce6306EEvv(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  call void @_ZN4java4lang6Object4initIu9Jd2b61b67EEvv(%ref %v1)
+  ret void
+}
+
+define i32 @_ZN6Switch5denseIu9J8f2aa66bEEii(i32 %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi i32 [ %v0, %entry ]
+  switch i32 %v1, label %B37 [ i32 1, label %B28 i32 2, label %B31 i32 3, label %B34 ]
+B34:
+  %v5 = phi i32 [ %v1, %B0 ]
+  ret i32 30
+B31:
+  %v4 = phi i32 [ %v1, %B0 ]
+  ret i32 20
+B28:
+  %v3 = phi i32 [ %v1, %B0 ]
+  ret i32 10
+B37:
+  %v2 = phi i32 [ %v1, %B0 ]
+  ret i32 -1
+}
+
+define i32 @_ZN6Switch6sparseIu9J71047ed2EEii(i32 %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi i32 [ %v0, %entry ]
+  switch i32 %v1, label %B32 [ i32 10, label %B28 i32 1000, label %B30 ]
+B30:
+  %v4 = phi i32 [ %v1, %B0 ]
+  ret i32 2
+B28:
+  %v3 = phi i32 [ %v1, %B0 ]
+  ret i32 1
+B32:
+  %v2 = phi i32 [ %v1, %B0 ]
+  ret i32 0
+}
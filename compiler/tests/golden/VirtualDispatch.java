@@ -0,0 +1,17 @@
+public class VirtualDispatch {
+    int area() {
+        return 0;
+    }
+
+    static int measure(VirtualDispatch shape) {
+        return shape.area() + shape.hashCode();
+    }
+}
+
+class Square extends VirtualDispatch {
+    int side;
+
+    int area() {
+        return side + side;
+    }
+}
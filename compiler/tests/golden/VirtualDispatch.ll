@@ -0,0 +1,74 @@
+; ModuleID = 'VirtualDispatch'
+source_filename = "VirtualDispatch.java"
+
+%ref = type { i8*, i8* }
+declare i32 @_Jrt_start(i32, i8**, void (%ref) *)
+declare %ref @_Jrt_object_new(i64, i8*)
+declare i8* @_Jrt_object_field_ptr(%ref)
+declare i8* @_Jrt_object_vtable_lookup(%ref, i64)
+declare i8* @_Jrt_object_itable_lookup(%ref, i8*, i64)
+declare void @_Jrt_object_monitorenter(%ref)
+declare void @_Jrt_object_monitorexit(%ref)
+declare void @_Jrt_class_monitorenter(i8**)
+declare void @_Jrt_class_monitorexit(i8**)
+declare %ref @_Jrt_array_new(i32, i64)
+declare i32 @_Jrt_array_length(%ref)
+declare i8* @_Jrt_array_element_ptr(%ref)
+declare void @_Jrt_array_store_check(%ref, %ref)
+declare i32 @_Jrt_idiv(i32, i32)
+declare i32 @_Jrt_irem(i32, i32)
+declare i64 @_Jrt_ldiv(i64, i64)
+declare i64 @_Jrt_lrem(i64, i64)
+declare void @_Jrt_throw(%ref) noreturn
+declare void @_Jrt_abstract() noreturn
+declare %ref @_Jrt_ldstr(i8*)
+%_ZTVN15VirtualDispatchE = type {
+  i32, ; <number of methods>
+  i32 (%ref) *, ; #0 method hashCode
+  i32 (%ref) *, ; #1 method area
+  i32 ; <number of interfaces>
+}
+
+declare i32 @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv(%ref)
+
+declare void @_ZN4java4lang6Object4initIu9Jd2b61b67EEvv(%ref)
+
+@_ZTVN15VirtualDispatchE = constant %_ZTVN15VirtualDispatchE {
+  i32 2, ; <number of methods>
+  i32 (%ref) * @_ZN4java4lang6Object8hashCodeIu9Jd98872cdEEiv,
+  i32 (%ref) * @_ZN15VirtualDispatch4areaIu9J2248fd97EEiv,
+  i32 0 ; <number of interfaces>
+}
+@_Jrt_classid_N15VirtualDispatchE = constant { i32, i32 } { i32 3, i32 4 } ; <class id, last subclass id>
+
+define void @_ZN15VirtualDispatch4initIu9Jcb2f4821EEvv(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  call void @_ZN4java4lang6Object4initIu9Jd2b61b67EEvv(%ref %v1)
+  ret void
+}
+
+define i32 @_ZN15VirtualDispatch4areaIu9J2248fd97EEiv(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  ret i32 0
+}
+
+define i32 @_ZN15VirtualDispatch7measureIu9J24c50abcEEiN15VirtualDispatchE(%ref %v0) {
+entry:
+  br label %B0
+B0:
+  %v1 = phi %ref [ %v0, %entry ]
+  %t0 = call i8* @_Jrt_object_vtable_lookup(%ref %v1, i64 1)
+  %t1 = bitcast i8* %t0 to i32 (%ref)*
+  %v2 = call i32 %t1(%ref %v1)
+  %t2 = call i8* @_Jrt_object_vtable_lookup(%ref %v1, i64 0)
+  %t3 = bitcast i8* %t2 to i32 (%ref)*
+  %v3 = call i32 %t3(%ref %v1)
+  %v4 = add i32 %v2, %v3
+  ret i32 %v4
+}
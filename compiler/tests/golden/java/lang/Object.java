@@ -0,0 +1,9 @@
+package java.lang;
+
+public class Object {
+    public Object() {}
+
+    public int hashCode() {
+        return 0;
+    }
+}
@@ -0,0 +1,31 @@
+use assert_cli::Assert;
+
+mod utils;
+
+use utils::JavaSource;
+
+#[test]
+fn keep_going_reports_failed_classes_and_compiles_the_rest() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { public static void main(String[] args) {} }\n\
+         class Broken { static double half(double d) { return d / 2; } }\n",
+    );
+    let temppath = source.path().join("temp");
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--main", "Test"])
+        .with_args(&["--keep-going"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[source.path().join("main.ll")])
+        .with_args(&["--save-temp"])
+        .with_args(&[&temppath])
+        .with_args(&source.classes())
+        .fails()
+        .stdout()
+        .contains("1 class(es) failed to compile\n  Broken: unsupported instruction DDiv")
+        .unwrap();
+
+    assert!(temppath.join("Test.ll").is_file());
+    assert!(!temppath.join("Broken.ll").exists());
+}
@@ -0,0 +1,23 @@
+use assert_cli::Assert;
+
+mod utils;
+
+use utils::JavaSource;
+
+#[test]
+fn main_class_without_main_method_is_rejected() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { static void main(String[] args) {} }\n",
+    );
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--main", "Test"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[source.path().join("Test.ll")])
+        .with_args(&source.classes())
+        .fails()
+        .stdout()
+        .contains("main class Test does not declare public static void main(String[])")
+        .unwrap();
+}
@@ -0,0 +1,37 @@
+use std::fs;
+
+use assert_cli::Assert;
+
+mod utils;
+
+use utils::JavaSource;
+
+#[test]
+fn no_main_emits_library_without_entry_point() {
+    let source = JavaSource::compile(
+        "Greeter",
+        "public class Greeter { static int greet() { return Counter.next(); } }\n\
+         class Counter { static int next() { return 1; } }\n",
+    );
+    let llvm_path = source.path().join("lib.ll");
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--no-main"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[&llvm_path])
+        .with_args(&source.classes())
+        .unwrap();
+
+    let ir = fs::read_to_string(&llvm_path).unwrap();
+    assert!(ir.contains("define i32 @_ZN7Greeter5greet"));
+    assert!(ir.contains("define i32 @_ZN7Counter4next"));
+    assert!(!ir.contains("@main("));
+}
+
+#[test]
+fn no_main_conflicts_with_main() {
+    Assert::cargo_binary("compiler")
+        .with_args(&["--no-main", "--main", "Test", "--emit-llvm", "Test.ll"])
+        .fails()
+        .unwrap();
+}
@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::io::Write;
+
+use assert_cli::Assert;
+use tempfile::TempDir;
+
+mod utils;
+
+use utils::{runtime_path, JavaSource};
+
+#[test]
+fn failed_allocation_throws_out_of_memory_error() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { static long[] values = new long[1 << 28]; }\n",
+    );
+
+    // an allocator that runs out of memory for anything but small objects
+    let executable = source.link_with_c(&[(
+        "alloc.c",
+        "#include <stdlib.h>\n\
+         void *_Jrt_alloc(size_t size) { return size > 4096 ? NULL : malloc(size); }\n\
+         int main(void) { return 0; }\n",
+    )]);
+
+    Assert::command(&[executable])
+        .fails()
+        .stderr()
+        .contains("java.lang.OutOfMemoryError: failed to allocate")
+        .unwrap();
+}
+
+#[test]
+fn array_size_overflow_throws_out_of_memory_error() {
+    let include_path = std::env::current_dir().unwrap().join("../runtime/lib");
+
+    let tmpdir = TempDir::new().unwrap();
+    let tmppath = tmpdir.path();
+
+    // the size of a java array can't overflow on 64-bit targets, so the check
+    // is exercised with a component size that no java type has
+    let mut testfile = File::create(tmppath.join("test.c")).unwrap();
+    testfile
+        .write_all(
+            b"#define _GNU_SOURCE 1\n\
+              #include <stdint.h>\n\
+              #include \"array.h\"\n\
+              struct object_base _ZTVN4java4lang6ObjectE;\n\
+              int main(void) { array_new(UINT32_MAX, SIZE_MAX / 2); return 0; }\n",
+        )
+        .unwrap();
+    testfile.sync_all().unwrap();
+
+    Assert::command(&["cc"])
+        .with_args(&["-I"])
+        .with_args(&[include_path])
+        .with_args(&[tmppath.join("test.c"), runtime_path()])
+        .with_args(&["-o"])
+        .with_args(&[tmppath.join("test")])
+        .with_args(&["-lpthread", "-ldl"])
+        .unwrap();
+
+    Assert::command(&[tmppath.join("test")])
+        .fails()
+        .stderr()
+        .contains(
+            "java.lang.OutOfMemoryError: array of 4294967295 elements exceeds the address space",
+        )
+        .unwrap();
+}
@@ -0,0 +1,105 @@
+use std::fs;
+
+use assert_cli::Assert;
+
+mod utils;
+
+use utils::JavaSource;
+
+#[test]
+fn save_temp_writes_one_file_per_class() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { public static void main(String[] args) {} }\n\
+         class Greeter {}\n",
+    );
+    let tmppath = source.path();
+    let temppath = tmppath.join("temp");
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--main", "Test"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[tmppath.join("main.ll")])
+        .with_args(&["--save-temp"])
+        .with_args(&[&temppath])
+        .with_args(&source.classes())
+        .unwrap();
+
+    for name in &["Test.ll", "Greeter.ll", "java.lang.Object.ll"] {
+        let path = temppath.join(name);
+        assert!(path.is_file(), "{} was not written", path.display());
+    }
+}
+
+#[test]
+fn save_temp_writes_single_merged_module() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { public static void main(String[] args) { Greeter.greet(); } }\n\
+         class Greeter { static void greet() {} }\n",
+    );
+    let tmppath = source.path();
+    let temppath = tmppath.join("temp");
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--main", "Test"])
+        .with_args(&["--single-module"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[tmppath.join("main.ll")])
+        .with_args(&["--save-temp"])
+        .with_args(&[&temppath])
+        .with_args(&source.classes())
+        .unwrap();
+
+    let names = fs::read_dir(&temppath)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["main.ll"]);
+
+    let ir = fs::read_to_string(temppath.join("main.ll")).unwrap();
+    llvm::Module::parse_ir(ir.as_bytes()).unwrap();
+    assert_eq!(ir.matches("; ModuleID").count(), 1);
+    assert_eq!(ir.matches("declare %ref @_Jrt_ldstr(i8*)").count(), 1);
+    assert!(ir.contains("define void @_ZN4Test4main"));
+    assert!(ir.contains("define void @_ZN7Greeter5greet"));
+    assert!(!ir.contains("declare void @_ZN7Greeter5greet"));
+}
+
+#[test]
+fn save_temp_defines_shared_vtable_once() {
+    let source = JavaSource::compile(
+        "Test",
+        "public class Test { public static void main(String[] args) { new Shared(); } }\n\
+         class Other { static Object make() { return new Shared(); } }\n\
+         class Shared {}\n",
+    );
+    let tmppath = source.path();
+    let temppath = tmppath.join("temp");
+
+    Assert::cargo_binary("compiler")
+        .with_args(&["--main", "Test"])
+        .with_args(&["--emit-llvm"])
+        .with_args(&[tmppath.join("main.ll")])
+        .with_args(&["--save-temp"])
+        .with_args(&[&temppath])
+        .with_args(&source.classes())
+        .unwrap();
+
+    let vtable = "@_ZTVN6SharedE = ";
+    let definitions = fs::read_dir(&temppath)
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .filter(|ir| ir.contains(&format!("{}constant", vtable)))
+        .count();
+    assert_eq!(definitions, 1);
+
+    for name in &["Test.ll", "Other.ll"] {
+        let ir = fs::read_to_string(temppath.join(name)).unwrap();
+        assert!(
+            ir.contains(&format!("{}external constant", vtable)),
+            "{} does not declare the shared vtable",
+            name
+        );
+    }
+}
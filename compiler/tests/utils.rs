@@ -1,7 +1,12 @@
-use std::fs::File;
+// each test binary only uses some of the helpers
+#![allow(dead_code)]
+
+use std::fs::{self, File};
 use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use assert_cli::Assert;
+use regex::RegexBuilder;
 use serde_derive::Deserialize;
 use tempfile::TempDir;
 
@@ -19,52 +24,135 @@ macro_rules! cases {
     }
 }
 
-#[derive(Deserialize)]
-pub struct TestCase {
-    source: String,
-    output: String,
+/// Returns the path of the runtime library, which the tests link against.
+pub fn runtime_path() -> PathBuf {
+    std::env::current_dir()
+        .unwrap()
+        .join("../runtime/libruntime.a")
 }
 
-impl TestCase {
-    pub fn expect(&self) {
-        let cwd = std::env::current_dir().unwrap();
+/// A Java source file, compiled with `javac` in a temporary directory.
+pub struct JavaSource {
+    tmpdir: TempDir,
+}
 
+impl JavaSource {
+    /// Writes `source` to `<main>.java` and compiles it.
+    pub fn compile(main: &str, source: &str) -> Self {
         let tmpdir = TempDir::new().unwrap();
-        let tmppath = tmpdir.path();
-
-        let runtime_path = cwd.join("../runtime/libruntime.a");
-        let output_path = tmppath.join("Test");
+        let source_name = format!("{}.java", main);
 
-        let mut srcfile = File::create(tmppath.join("Test.java")).unwrap();
-        srcfile.write_all(self.source.as_bytes()).unwrap();
+        let mut srcfile = File::create(tmpdir.path().join(&source_name)).unwrap();
+        srcfile.write_all(source.as_bytes()).unwrap();
         srcfile.sync_all().unwrap();
 
-        Assert::command(&["javac", "-encoding", "utf8", "Test.java"])
-            .current_dir(&tmppath)
+        Assert::command(&["javac", "-g", "-encoding", "utf8", &source_name])
+            .current_dir(tmpdir.path())
             .unwrap();
 
+        JavaSource { tmpdir }
+    }
+
+    /// The temporary directory, which is removed when the source is dropped.
+    pub fn path(&self) -> &Path {
+        self.tmpdir.path()
+    }
+
+    /// The class files produced by `javac`.
+    pub fn classes(&self) -> Vec<PathBuf> {
         let mut classes = vec![];
-        for entry_result in tmppath.read_dir().unwrap() {
-            let entry = entry_result.unwrap();
-            let path = entry.path();
+        for entry_result in self.path().read_dir().unwrap() {
+            let path = entry_result.unwrap().path();
             let is_class = path.extension().map(|ext| ext == "class").unwrap_or(false);
             if is_class {
                 classes.push(path);
             }
         }
+        classes
+    }
+
+    /// Compiles the classes without an entry point, and links them with the
+    /// runtime and the given C files, as pairs of name and contents, into an
+    /// executable. Returns the path of the executable.
+    pub fn link_with_c(&self, c_files: &[(&str, &str)]) -> PathBuf {
+        let object_path = self.path().join("classes.o");
+        let output_path = self.path().join("a.out");
+
+        Assert::cargo_binary("compiler")
+            .with_args(&["--no-main"])
+            .with_args(&["-o"])
+            .with_args(&[&object_path])
+            .with_args(&self.classes())
+            .unwrap();
+
+        let mut c_paths = vec![];
+        for (name, contents) in c_files {
+            let path = self.path().join(name);
+            let mut file = File::create(&path).unwrap();
+            file.write_all(contents.as_bytes()).unwrap();
+            file.sync_all().unwrap();
+            c_paths.push(path);
+        }
+
+        Assert::command(&["cc"])
+            .with_args(&[&object_path])
+            .with_args(&c_paths)
+            .with_args(&[runtime_path()])
+            .with_args(&["-o"])
+            .with_args(&[&output_path])
+            .with_args(&["-lpthread", "-ldl"])
+            .unwrap();
+
+        output_path
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TestCase {
+    #[serde(default = "TestCase::default_main")]
+    main: String,
+    source: String,
+    output: String,
+    #[serde(default)]
+    ir: Vec<String>,
+    /// When set, the program is expected to fail with this on stderr.
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl TestCase {
+    fn default_main() -> String {
+        "Test".to_owned()
+    }
+
+    pub fn expect(&self) {
+        let source = JavaSource::compile(&self.main, &self.source);
+        let tmppath = source.path();
+        let output_path = tmppath.join(&self.main);
 
         Assert::cargo_binary("compiler")
             .with_args(&["-r"])
-            .with_args(&[&runtime_path])
+            .with_args(&[runtime_path()])
             .with_args(&["-o"])
             .with_args(&[&output_path])
-            .with_args(&["--main", "Test"])
-            .with_args(&classes)
+            .with_args(&["--main", &self.main])
+            .with_args(&["--save-temp"])
+            .with_args(&[tmppath])
+            .with_args(&source.classes())
             .unwrap();
 
-        Assert::command(&[output_path])
+        let ir = fs::read_to_string(tmppath.join(format!("{}.ll", self.main))).unwrap();
+        for pattern in self.ir.iter() {
+            let regex = RegexBuilder::new(pattern).multi_line(true).build().unwrap();
+            assert!(regex.is_match(&ir), "IR does not match {:?}", pattern);
+        }
+
+        let assert = Assert::command(&[output_path])
             .stdout()
-            .is(self.output.as_str())
-            .unwrap();
+            .is(self.output.as_str());
+        match self.error {
+            Some(ref error) => assert.fails().stderr().contains(error.as_str()).unwrap(),
+            None => assert.unwrap(),
+        }
     }
 }
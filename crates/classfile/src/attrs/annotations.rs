@@ -0,0 +1,176 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use failure::{bail, format_err, Fallible};
+use strbuf::StrBuf;
+
+use super::super::constant_pool::ConstantLiteral;
+use super::super::{ConstantIndex, ConstantPool};
+use super::{private, Attribute, RawAttribute};
+
+/// The value of an annotation element.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElementValue {
+    /// A primitive or string constant. The tag distinguishes the primitive
+    /// types that share a literal representation, e.g. `Z` for booleans and
+    /// `I` for ints, and is `s` for strings.
+    Const(char, ConstantLiteral),
+    Enum {
+        type_name: StrBuf,
+        const_name: StrBuf,
+    },
+    /// A class literal, given as a return descriptor, e.g. `Ljava/lang/String;`
+    /// or `V` for `void.class`.
+    Class(StrBuf),
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    /// The field descriptor of the annotation type.
+    pub type_name: StrBuf,
+    pub elements: Vec<(StrBuf, ElementValue)>,
+}
+
+/// The default value of an element of an annotation type, which is attached
+/// to the method declaring the element.
+#[derive(Debug)]
+pub struct AnnotationDefault {
+    pub default_value: ElementValue,
+}
+
+impl private::Sealed for AnnotationDefault {}
+
+impl Attribute for AnnotationDefault {
+    const NAME: &'static str = "AnnotationDefault";
+
+    fn decode(raw: RawAttribute, consts: &ConstantPool) -> Fallible<Self> {
+        let default_value = parse_element_value(&mut raw.as_ref(), consts)?;
+        Ok(AnnotationDefault { default_value })
+    }
+}
+
+fn parse_utf8(bytes: &mut &[u8], consts: &ConstantPool) -> Fallible<StrBuf> {
+    let index = ConstantIndex::parse(&mut *bytes)?;
+    consts
+        .get_utf8(index)
+        .cloned()
+        .ok_or_else(|| format_err!("constant #{} is not a string", index.into_u16()))
+}
+
+fn parse_annotation(bytes: &mut &[u8], consts: &ConstantPool) -> Fallible<Annotation> {
+    let type_name = parse_utf8(bytes, consts)?;
+    let len = bytes.read_u16::<BigEndian>()?;
+    let mut elements = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let name = parse_utf8(bytes, consts)?;
+        let value = parse_element_value(bytes, consts)?;
+        elements.push((name, value));
+    }
+    Ok(Annotation {
+        type_name,
+        elements,
+    })
+}
+
+fn parse_element_value(bytes: &mut &[u8], consts: &ConstantPool) -> Fallible<ElementValue> {
+    let tag = char::from(bytes.read_u8()?);
+    match tag {
+        'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => {
+            let index = ConstantIndex::parse(&mut *bytes)?;
+            let value = match tag {
+                'D' => consts.get_double(index).map(ConstantLiteral::Double),
+                'F' => consts.get_float(index).map(ConstantLiteral::Float),
+                'J' => consts.get_long(index).map(ConstantLiteral::Long),
+                // unlike `ldc`, strings refer to a utf8 constant directly
+                's' => consts.get_utf8(index).cloned().map(ConstantLiteral::String),
+                _ => consts.get_integer(index).map(ConstantLiteral::Int),
+            };
+            match value {
+                Some(value) => Ok(ElementValue::Const(tag, value)),
+                None => bail!(
+                    "constant #{} is not a valid {} element value",
+                    index.into_u16(),
+                    tag
+                ),
+            }
+        }
+        'e' => Ok(ElementValue::Enum {
+            type_name: parse_utf8(bytes, consts)?,
+            const_name: parse_utf8(bytes, consts)?,
+        }),
+        'c' => Ok(ElementValue::Class(parse_utf8(bytes, consts)?)),
+        '@' => Ok(ElementValue::Annotation(parse_annotation(bytes, consts)?)),
+        '[' => {
+            let len = bytes.read_u16::<BigEndian>()?;
+            let mut values = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                values.push(parse_element_value(bytes, consts)?);
+            }
+            Ok(ElementValue::Array(values))
+        }
+        _ => bail!("unknown element value tag {:?}", tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::ConstantPoolBuilder;
+
+    fn decode(consts: &ConstantPool, bytes: Vec<u8>) -> Fallible<ElementValue> {
+        let raw = RawAttribute {
+            bytes: bytes.into(),
+        };
+        Ok(AnnotationDefault::decode(raw, consts)?.default_value)
+    }
+
+    #[test]
+    fn nested_element_values() {
+        let mut pool = ConstantPoolBuilder::default();
+        pool.utf8("Ljava/util/concurrent/TimeUnit;");
+        pool.utf8("SECONDS");
+        pool.utf8("Ljava/lang/String;");
+        pool.utf8("LLabel;");
+        pool.utf8("value");
+        pool.utf8("retry");
+        let consts = pool.parse();
+
+        // { TimeUnit.SECONDS, String.class, @Label(value = "retry") }
+        let value = decode(
+            &consts,
+            vec![
+                b'[', 0, 3, b'e', 0, 1, 0, 2, b'c', 0, 3, b'@', 0, 4, 0, 1, 0, 5, b's', 0, 6,
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            ElementValue::Array(vec![
+                ElementValue::Enum {
+                    type_name: StrBuf::new("Ljava/util/concurrent/TimeUnit;"),
+                    const_name: StrBuf::new("SECONDS"),
+                },
+                ElementValue::Class(StrBuf::new("Ljava/lang/String;")),
+                ElementValue::Annotation(Annotation {
+                    type_name: StrBuf::new("LLabel;"),
+                    elements: vec![(
+                        StrBuf::new("value"),
+                        ElementValue::Const('s', ConstantLiteral::String(StrBuf::new("retry")))
+                    )],
+                }),
+            ]),
+            value
+        );
+    }
+
+    #[test]
+    fn mistyped_constant_is_rejected() {
+        let mut pool = ConstantPoolBuilder::default();
+        pool.utf8("3");
+        let consts = pool.parse();
+
+        assert!(decode(&consts, vec![b'I', 0, 1]).is_err());
+        assert!(decode(&consts, vec![b'x', 0, 1]).is_err());
+    }
+}
@@ -1,10 +1,10 @@
-use byteorder::{BigEndian, ReadBytesExt};
-use failure::Fallible;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use failure::{format_err, Fallible};
 
 use super::super::instructions::Disassembler;
 use super::super::{ConstantIndex, ConstantPool};
-use super::{private, Attribute, Attributes, RawAttribute};
-use crate::ByteBuf;
+use super::{private, Attribute, Attributes, LineNumberTable, RawAttribute};
+use crate::{ByteBuf, StrBuf};
 
 #[derive(Debug)]
 pub struct Code {
@@ -31,6 +31,45 @@ impl Code {
             bytes: self.exception_table.clone(),
         }
     }
+
+    /// Returns the line numbers of the code, which are only present in classes
+    /// compiled with debug information (e.g. `javac -g`).
+    pub fn line_number_table(&self) -> Fallible<Option<LineNumberTable>> {
+        self.attributes.get_opt::<LineNumberTable>()
+    }
+
+    /// Returns the exception handlers with their catch types resolved to class
+    /// names, where `None` catches any exception (e.g. for `finally`).
+    pub fn handlers(&self, consts: &ConstantPool) -> Fallible<Vec<Handler>> {
+        self.exception_handlers()
+            .map(|handler_result| {
+                let handler = handler_result?;
+                let catch_type = handler.catch_type_name(consts)?.cloned();
+                Ok(Handler {
+                    start_pc: handler.start_pc,
+                    end_pc: handler.end_pc,
+                    handler_pc: handler.handler_pc,
+                    catch_type,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Code {
+    /// Encodes a `Code` attribute body without exception handlers or nested
+    /// attributes, as those would refer to offsets within the replaced code.
+    pub(crate) fn encode(code: &[u8], max_stack: u16, max_locals: u16) -> Fallible<ByteBuf> {
+        let mut bytes = Vec::with_capacity(code.len() + 12);
+        bytes.write_u16::<BigEndian>(max_stack)?;
+        bytes.write_u16::<BigEndian>(max_locals)?;
+        bytes.write_u32::<BigEndian>(code.len() as u32)?;
+        bytes.extend_from_slice(code);
+        // exception table, attributes
+        bytes.write_u16::<BigEndian>(0)?;
+        bytes.write_u16::<BigEndian>(0)?;
+        Ok(bytes.into())
+    }
 }
 
 impl private::Sealed for Code {}
@@ -90,6 +129,34 @@ pub struct ExceptionHandler {
     pub catch_type: ConstantIndex,
 }
 
+impl ExceptionHandler {
+    /// Returns whether the handler catches any exception, as `finally`
+    /// blocks do. Such handlers have a catch type of 0.
+    pub fn catches_any(&self) -> bool {
+        self.catch_type.into_u16() == 0
+    }
+
+    /// Resolves the name of the caught exception class, or `None` for
+    /// handlers that catch any exception.
+    pub fn catch_type_name<'a>(&self, consts: &'a ConstantPool) -> Fallible<Option<&'a StrBuf>> {
+        if self.catches_any() {
+            return Ok(None);
+        }
+        let class = consts
+            .get_class(self.catch_type)
+            .ok_or_else(|| format_err!("catch type {:?} is not a class", self.catch_type))?;
+        Ok(Some(consts.get_utf8(class.name_index).unwrap()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Handler {
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    pub catch_type: Option<StrBuf>,
+}
+
 fn parse_exception_handler(bytes: &mut ByteBuf) -> Fallible<ExceptionHandler> {
     let start_pc = bytes.read_u16::<BigEndian>()?;
     let end_pc = bytes.read_u16::<BigEndian>()?;
@@ -102,3 +169,112 @@ fn parse_exception_handler(bytes: &mut ByteBuf) -> Fallible<ExceptionHandler> {
         catch_type,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ConstantPoolBuilder;
+
+    fn code_with_attributes(attributes: Vec<u8>, consts: &ConstantPool) -> Code {
+        Code {
+            max_stack: 1,
+            max_locals: 0,
+            // iconst_0, pop, return
+            code: vec![0x03, 0x57, 0xb1].into(),
+            exception_table_len: 0,
+            exception_table: vec![].into(),
+            attributes: Attributes::parse(&mut attributes.into(), consts).unwrap(),
+        }
+    }
+
+    #[test]
+    fn line_number_table_of_debug_code() {
+        let mut pool = ConstantPoolBuilder::default();
+        pool.utf8("LineNumberTable");
+        let consts = pool.parse();
+        // one attribute: #1, 10 bytes, two entries (pc 0 => line 3, pc 2 => line 4)
+        let attributes = vec![0, 1, 0, 1, 0, 0, 0, 10, 0, 2, 0, 0, 0, 3, 0, 2, 0, 4];
+        let code = code_with_attributes(attributes, &consts);
+
+        let table = code.line_number_table().unwrap().unwrap();
+
+        let entries = table
+            .entries
+            .iter()
+            .map(|entry| (entry.start_pc, entry.line_number))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(0, 3), (2, 4)], entries);
+    }
+
+    #[test]
+    fn line_number_table_of_code_without_debug_info() {
+        let consts = ConstantPoolBuilder::default().parse();
+        let code = code_with_attributes(vec![0, 0], &consts);
+
+        assert!(code.line_number_table().unwrap().is_none());
+    }
+
+    #[test]
+    fn handlers_resolve_catch_types() {
+        let mut pool = ConstantPoolBuilder::default();
+        pool.class("java/io/IOException");
+        let consts = pool.parse();
+        let code = Code {
+            max_stack: 0,
+            max_locals: 0,
+            code: vec![].into(),
+            exception_table_len: 2,
+            // try { ... } catch (IOException e) { ... } finally { ... }
+            exception_table: vec![0, 0, 0, 4, 0, 5, 0, 2, 0, 0, 0, 8, 0, 10, 0, 0].into(),
+            attributes: Attributes::parse(&mut vec![0, 0].into(), &consts).unwrap(),
+        };
+
+        let handlers = code.handlers(&consts).unwrap();
+
+        assert_eq!(
+            vec![
+                Handler {
+                    start_pc: 0,
+                    end_pc: 4,
+                    handler_pc: 5,
+                    catch_type: Some("java/io/IOException".to_owned().into()),
+                },
+                Handler {
+                    start_pc: 0,
+                    end_pc: 8,
+                    handler_pc: 10,
+                    catch_type: None,
+                },
+            ],
+            handlers
+        );
+    }
+
+    #[test]
+    fn finally_handler_catches_any() {
+        let mut pool = ConstantPoolBuilder::default();
+        pool.utf8("Test");
+        let consts = pool.parse();
+        let code = Code {
+            max_stack: 0,
+            max_locals: 0,
+            code: vec![].into(),
+            exception_table_len: 2,
+            // try { ... } finally { ... }, and a handler with a catch type
+            // that doesn't refer to a class
+            exception_table: vec![0, 0, 0, 4, 0, 7, 0, 0, 0, 0, 0, 4, 0, 7, 0, 1].into(),
+            attributes: Attributes::parse(&mut vec![0, 0].into(), &consts).unwrap(),
+        };
+
+        let handlers = code
+            .exception_handlers()
+            .collect::<Fallible<Vec<_>>>()
+            .unwrap();
+
+        assert!(handlers[0].catches_any());
+        assert_eq!(None, handlers[0].catch_type_name(&consts).unwrap());
+        assert!(!handlers[1].catches_any());
+        assert!(handlers[1].catch_type_name(&consts).is_err());
+        assert!(code.handlers(&consts).is_err());
+    }
+}
@@ -1,9 +1,11 @@
 use byteorder::{BigEndian, ReadBytesExt};
-use failure::{bail, Fallible};
+use failure::{bail, ensure, Fallible};
 
 use super::{ConstantIndex, ConstantPool};
 use crate::{ByteBuf, StrBuf};
 
+pub mod annotations;
+pub use self::annotations::AnnotationDefault;
 pub mod code;
 pub use self::code::Code;
 pub mod stack_map_table;
@@ -43,6 +45,26 @@ impl Attributes {
         }
     }
 
+    pub fn get_opt<A>(&self) -> Fallible<Option<A>>
+    where
+        A: Attribute,
+    {
+        if let Some(raw) = self.get_raw(A::NAME) {
+            Ok(Some(A::decode(raw, &self.consts)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub(crate) fn set_raw(&mut self, name: &str, bytes: ByteBuf) -> Fallible<()> {
+        if let Some((_, raw)) = self.attrs.iter_mut().find(|(s, _)| &**s == name) {
+            *raw = bytes;
+            Ok(())
+        } else {
+            bail!("attribute {} does not exist", name)
+        }
+    }
+
     pub fn get_raw(&self, name: &str) -> Option<RawAttribute> {
         self.attrs
             .iter()
@@ -121,6 +143,90 @@ impl Attribute for SourceFile {
     }
 }
 
+fn class_name(consts: &ConstantPool, index: ConstantIndex) -> Option<&StrBuf> {
+    consts.get_utf8(consts.get_class(index)?.name_index)
+}
+
+/// The host of the nest a class belongs to (Java 11+).
+///
+/// Nestmates may access each other's private members.
+#[derive(Debug)]
+pub struct NestHost {
+    host_class_index: ConstantIndex,
+    consts: ConstantPool,
+}
+
+impl NestHost {
+    pub fn host_class_index(&self) -> ConstantIndex {
+        self.host_class_index
+    }
+
+    pub fn host_name(&self) -> &StrBuf {
+        class_name(&self.consts, self.host_class_index).unwrap()
+    }
+}
+
+impl private::Sealed for NestHost {}
+
+impl Attribute for NestHost {
+    const NAME: &'static str = "NestHost";
+
+    fn decode(raw: RawAttribute, consts: &ConstantPool) -> Fallible<Self> {
+        let host_class_index = ConstantIndex::parse(raw.as_ref())?;
+        ensure!(
+            class_name(consts, host_class_index).is_some(),
+            "NestHost attribute must refer to a class constant"
+        );
+        Ok(NestHost {
+            host_class_index,
+            consts: consts.clone(),
+        })
+    }
+}
+
+/// The classes in the nest hosted by a class (Java 11+).
+#[derive(Debug)]
+pub struct NestMembers {
+    member_class_indices: Vec<ConstantIndex>,
+    consts: ConstantPool,
+}
+
+impl NestMembers {
+    pub fn member_class_indices(&self) -> &[ConstantIndex] {
+        &self.member_class_indices
+    }
+
+    pub fn member_names(&self) -> impl Iterator<Item = &StrBuf> {
+        self.member_class_indices
+            .iter()
+            .map(move |index| class_name(&self.consts, *index).unwrap())
+    }
+}
+
+impl private::Sealed for NestMembers {}
+
+impl Attribute for NestMembers {
+    const NAME: &'static str = "NestMembers";
+
+    fn decode(raw: RawAttribute, consts: &ConstantPool) -> Fallible<Self> {
+        let mut bytes = raw.as_ref();
+        let len = bytes.read_u16::<BigEndian>()?;
+        let mut member_class_indices = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let index = ConstantIndex::parse(&mut bytes)?;
+            ensure!(
+                class_name(consts, index).is_some(),
+                "NestMembers attribute must refer to class constants"
+            );
+            member_class_indices.push(index);
+        }
+        Ok(NestMembers {
+            member_class_indices,
+            consts: consts.clone(),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct LineNumberTable {
     pub entries: Vec<LineNumberTableEntry>,
@@ -152,3 +258,34 @@ pub struct LineNumberTableEntry {
     pub start_pc: u16,
     pub line_number: u16,
 }
+
+#[derive(Debug)]
+pub struct Deprecated;
+
+impl private::Sealed for Deprecated {}
+
+impl Attribute for Deprecated {
+    const NAME: &'static str = "Deprecated";
+
+    fn decode(raw: RawAttribute, _consts: &ConstantPool) -> Fallible<Self> {
+        ensure!(
+            raw.as_ref().is_empty(),
+            "Deprecated attribute must be empty"
+        );
+        Ok(Deprecated)
+    }
+}
+
+#[derive(Debug)]
+pub struct Synthetic;
+
+impl private::Sealed for Synthetic {}
+
+impl Attribute for Synthetic {
+    const NAME: &'static str = "Synthetic";
+
+    fn decode(raw: RawAttribute, _consts: &ConstantPool) -> Fallible<Self> {
+        ensure!(raw.as_ref().is_empty(), "Synthetic attribute must be empty");
+        Ok(Synthetic)
+    }
+}
@@ -1,9 +1,12 @@
+use std::collections::BTreeSet;
+
 use byteorder::{BigEndian, ReadBytesExt};
-use failure::{bail, Fallible};
+use failure::{bail, format_err, Fallible};
 use strbuf::StrBuf;
 
+use super::super::instructions::Instr;
 use super::super::{ConstantIndex, ConstantPool};
-use super::{private, Attribute, RawAttribute};
+use super::{private, Attribute, Code, RawAttribute};
 use crate::ByteBuf;
 
 #[derive(Debug)]
@@ -29,6 +32,114 @@ impl StackMapTable {
             consts: self.consts.clone(),
         }
     }
+
+    /// Applies the entries to the implicit initial frame of a method, yielding
+    /// the complete frame at the absolute offset of each entry.
+    ///
+    /// Like in the attribute itself, a `Long` or `Double` local is a single
+    /// entry in `Frame::locals`, even though it occupies two local slots.
+    pub fn frames(&self, initial_locals: &[VerificationTypeInfo]) -> Fallible<Vec<(u32, Frame)>> {
+        let mut frames = Vec::with_capacity(self.count as usize);
+        let mut locals = initial_locals.to_vec();
+        let mut offset: Option<u32> = None;
+        for entry_result in self.entries() {
+            let entry = entry_result?;
+            let mut stack = vec![];
+            let offset_delta = match entry {
+                Entry::SameFrame { offset_delta } => u16::from(offset_delta),
+                Entry::SameFrameExtended { offset_delta } => offset_delta,
+                Entry::SameLocals1StackItem {
+                    offset_delta,
+                    stack_item,
+                } => {
+                    stack.push(stack_item);
+                    u16::from(offset_delta)
+                }
+                Entry::SameLocals1StackItemExtended {
+                    offset_delta,
+                    stack_item,
+                } => {
+                    stack.push(stack_item);
+                    offset_delta
+                }
+                Entry::ChopFrame { k, offset_delta } => {
+                    if usize::from(k) > locals.len() {
+                        bail!("can't chop {} locals from a frame of {}", k, locals.len());
+                    }
+                    locals.truncate(locals.len() - usize::from(k));
+                    offset_delta
+                }
+                Entry::AppendFrame {
+                    offset_delta,
+                    locals: appended,
+                } => {
+                    locals.extend(appended);
+                    offset_delta
+                }
+                Entry::FullFrame {
+                    offset_delta,
+                    locals: full_locals,
+                    stack_items,
+                } => {
+                    locals = full_locals;
+                    stack = stack_items;
+                    offset_delta
+                }
+            };
+            // all but the first frame are one byte further than their delta
+            let next_offset = match offset {
+                Some(prev) => prev + u32::from(offset_delta) + 1,
+                None => u32::from(offset_delta),
+            };
+            offset = Some(next_offset);
+            frames.push((
+                next_offset,
+                Frame {
+                    locals: locals.clone(),
+                    stack,
+                },
+            ));
+        }
+        Ok(frames)
+    }
+
+    /// Checks that each `Uninitialized` entry refers to the offset of the
+    /// `new` instruction that created the object.
+    pub fn check_uninitialized(&self, code: &Code) -> Fallible<()> {
+        let mut disasm = code.disassemble();
+        let mut news = BTreeSet::new();
+        while let Some((pc, instr)) = disasm.decode_next()? {
+            if let Instr::New(_) = instr {
+                news.insert(pc);
+            }
+        }
+        for entry_result in self.entries() {
+            let types = match entry_result? {
+                Entry::SameFrame { .. }
+                | Entry::SameFrameExtended { .. }
+                | Entry::ChopFrame { .. } => vec![],
+                Entry::SameLocals1StackItem { stack_item, .. }
+                | Entry::SameLocals1StackItemExtended { stack_item, .. } => vec![stack_item],
+                Entry::AppendFrame { locals, .. } => locals,
+                Entry::FullFrame {
+                    mut locals,
+                    stack_items,
+                    ..
+                } => {
+                    locals.extend(stack_items);
+                    locals
+                }
+            };
+            for vtype in types {
+                if let VerificationTypeInfo::Uninitialized(offset) = vtype {
+                    if !news.contains(&u32::from(offset)) {
+                        bail!("uninitialized object at {} was not created by new", offset);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl private::Sealed for StackMapTable {}
@@ -101,6 +212,12 @@ pub enum Entry {
     },
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub locals: Vec<VerificationTypeInfo>,
+    pub stack: Vec<VerificationTypeInfo>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum VerificationTypeInfo {
     Top,
@@ -128,8 +245,12 @@ fn parse_verification_type_info(
         6 => Ok(VerificationTypeInfo::UninitializedThis),
         7 => {
             let class_index = ConstantIndex::parse(bytes)?;
-            let class_const = consts.get_class(class_index).unwrap();
-            let class_name = consts.get_utf8(class_const.name_index).unwrap();
+            let class_name = consts
+                .get_class(class_index)
+                .and_then(|class_const| consts.get_utf8(class_const.name_index))
+                .ok_or_else(|| {
+                    format_err!("object type #{} is not a class", class_index.into_u16())
+                })?;
             Ok(VerificationTypeInfo::Object(class_name.clone()))
         }
         8 => Ok(VerificationTypeInfo::Uninitialized(
@@ -199,3 +320,139 @@ fn parse_stack_map_table_entry(bytes: &mut ByteBuf, consts: &ConstantPool) -> Fa
         bail!("unknown frame type {}", frame_type)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::ConstantPoolBuilder;
+    use VerificationTypeInfo::*;
+
+    fn stack_map_table(count: u16, bytes: Vec<u8>) -> StackMapTable {
+        StackMapTable {
+            count,
+            bytes: bytes.into(),
+            consts: ConstantPoolBuilder::default().parse(),
+        }
+    }
+
+    fn frame(locals: &[VerificationTypeInfo], stack: &[VerificationTypeInfo]) -> Frame {
+        Frame {
+            locals: locals.to_vec(),
+            stack: stack.to_vec(),
+        }
+    }
+
+    #[test]
+    fn append_and_chop_frames() {
+        let mut bytes = vec![];
+        // append_frame (k = 2) at 5: int, long
+        bytes.extend_from_slice(&[253, 0, 5, 1, 4]);
+        // same_locals_1_stack_item_frame at 5 + 3 + 1: float
+        bytes.extend_from_slice(&[64 + 3, 2]);
+        // chop_frame (k = 1) at 9 + 2 + 1
+        bytes.extend_from_slice(&[250, 0, 2]);
+        // same_frame at 12 + 7 + 1
+        bytes.extend_from_slice(&[7]);
+        let table = stack_map_table(4, bytes);
+
+        let this = Object(StrBuf::new("Test"));
+        let frames = table.frames(&[Object(StrBuf::new("Test"))]).unwrap();
+
+        assert_eq!(
+            vec![
+                (5, frame(&[this.clone(), Integer, Long], &[])),
+                (9, frame(&[this.clone(), Integer, Long], &[Float])),
+                (12, frame(&[this.clone(), Integer], &[])),
+                (20, frame(&[this.clone(), Integer], &[])),
+            ],
+            frames
+        );
+    }
+
+    #[test]
+    fn full_frame_replaces_locals() {
+        let mut bytes = vec![];
+        // full_frame at 10: locals [double, top], stack [int]
+        bytes.extend_from_slice(&[255, 0, 10, 0, 2, 3, 0, 0, 1, 1]);
+        // same_frame_extended at 10 + 300 + 1
+        bytes.extend_from_slice(&[251, 1, 44]);
+        let table = stack_map_table(2, bytes);
+
+        let frames = table.frames(&[Integer, Integer]).unwrap();
+
+        assert_eq!(
+            vec![
+                (10, frame(&[Double, Top], &[Integer])),
+                (311, frame(&[Double, Top], &[])),
+            ],
+            frames
+        );
+    }
+
+    #[test]
+    fn chop_more_locals_than_present() {
+        // chop_frame (k = 2) at 0
+        let table = stack_map_table(1, vec![249, 0, 0]);
+
+        assert!(table.frames(&[Integer]).is_err());
+    }
+
+    fn string_pool() -> ConstantPool {
+        // #1 = Utf8 java/lang/String, #2 = Class #1
+        let mut pool = ConstantPoolBuilder::default();
+        pool.class("java/lang/String");
+        pool.parse()
+    }
+
+    #[test]
+    fn full_frame_with_object_types() {
+        let consts = string_pool();
+        // full_frame at 4: locals [String], stack [uninitialized(0), uninitialized(0)]
+        let bytes = vec![0, 1, 255, 0, 4, 0, 1, 7, 0, 2, 0, 2, 8, 0, 0, 8, 0, 0];
+        let table = StackMapTable::decode(
+            RawAttribute {
+                bytes: bytes.into(),
+            },
+            &consts,
+        )
+        .unwrap();
+        // new #2, dup, aload_0
+        let code = Code::encode(&[0xbb, 0, 2, 0x59, 0x2a], 3, 1).unwrap();
+        let code = Code::decode(RawAttribute { bytes: code }, &consts).unwrap();
+
+        let frames = table.frames(&[]).unwrap();
+
+        assert_eq!(
+            vec![(
+                4,
+                frame(
+                    &[Object(StrBuf::new("java/lang/String"))],
+                    &[Uninitialized(0), Uninitialized(0)]
+                )
+            )],
+            frames
+        );
+        table.check_uninitialized(&code).unwrap();
+    }
+
+    #[test]
+    fn uninitialized_must_refer_to_new() {
+        let consts = string_pool();
+        // same_locals_1_stack_item_frame at 4: uninitialized(3)
+        let table = StackMapTable::decode(
+            RawAttribute {
+                bytes: vec![0, 1, 64 + 4, 8, 0, 3].into(),
+            },
+            &consts,
+        )
+        .unwrap();
+        let code = Code::encode(&[0xbb, 0, 2, 0x59, 0x2a], 3, 1).unwrap();
+        let code = Code::decode(RawAttribute { bytes: code }, &consts).unwrap();
+
+        assert!(table.check_uninitialized(&code).is_err());
+        // object_variable_info referring to the utf8 constant
+        let table = stack_map_table(1, vec![64, 7, 0, 1]);
+        assert!(table.frames(&[]).is_err());
+    }
+}
@@ -1,12 +1,13 @@
+use std::hash::{Hash, Hasher};
 use std::io::Read;
 use std::ops::Index;
 use std::sync::Arc;
 
 use byteorder::{BigEndian, ReadBytesExt};
-use failure::{bail, Fallible};
+use failure::{bail, format_err, Fallible};
 use strbuf::StrBuf;
 
-use super::descriptors::{FieldType, MethodDescriptor};
+use super::descriptors::{ArrayType, FieldType, MethodDescriptor};
 use super::{FieldRef, MethodRef};
 use crate::buffer::ByteBuf;
 
@@ -25,7 +26,7 @@ const CONSTANT_METHOD_HANDLE: u8 = 15;
 const CONSTANT_METHOD_TYPE: u8 = 16;
 const CONSTANT_INVOKE_DYNAMIC: u8 = 18;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ConstantPool {
     vec: Arc<[Constant]>,
 }
@@ -44,6 +45,16 @@ impl ConstantPool {
         (1..=self.vec.len()).map(|i| ConstantIndex(i as u16))
     }
 
+    /// Iterates over the constants in the pool, skipping the unusable slots
+    /// that follow long and double constants.
+    pub fn iter(&self) -> impl Iterator<Item = (ConstantIndex, &Constant)> {
+        self.vec
+            .iter()
+            .enumerate()
+            .filter(|(_, constant)| **constant != Constant::Unusable)
+            .map(|(i, constant)| (ConstantIndex(i as u16 + 1), constant))
+    }
+
     pub fn get_info(&self, idx: ConstantIndex) -> Option<&Constant> {
         if idx.0 > 0 {
             self.vec.get(idx.0 as usize - 1)
@@ -68,6 +79,22 @@ impl ConstantPool {
         }
     }
 
+    /// Resolves a class constant, which refers to array classes by their
+    /// descriptor, e.g. `[Ljava/lang/Object;`, and to other classes by name.
+    pub fn get_class_or_array(&self, idx: ConstantIndex) -> Fallible<ClassRef> {
+        let name = self
+            .get_class(idx)
+            .and_then(|class| self.get_utf8(class.name_index))
+            .ok_or_else(|| format_err!("invalid class constant #{}", idx.0))?;
+        if !name.starts_with('[') {
+            return Ok(ClassRef::Class(name.clone()));
+        }
+        match FieldType::try_from_str(name) {
+            Ok(FieldType::Array(array_type)) => Ok(ClassRef::Array(array_type)),
+            _ => bail!("invalid array class name {:?} in class #{}", &**name, idx.0),
+        }
+    }
+
     pub fn get_method_ref(&self, idx: ConstantIndex) -> Option<MethodRef> {
         if let Some(&Constant::MethodRef(ref method_ref_const)) = self.get_info(idx) {
             let name_and_type = self
@@ -103,22 +130,83 @@ impl ConstantPool {
     }
 
     pub fn get_field_ref(&self, idx: ConstantIndex) -> Option<FieldRef> {
-        if let Some(&Constant::FieldRef(ref method_ref_const)) = self.get_info(idx) {
-            let name_and_type = self
-                .get_name_and_type(method_ref_const.name_and_type_index)
-                .unwrap();
-            let descriptor_string = self.get_utf8(name_and_type.descriptor_index).unwrap();
-            let descriptor = FieldType::parse(descriptor_string.as_bytes()).unwrap();
-            Some(FieldRef {
-                class_index: method_ref_const.class_index,
-                name_index: name_and_type.name_index,
-                descriptor,
-            })
+        if let Some(Constant::FieldRef(field_ref_const)) = self.get_info(idx) {
+            match self.resolve_field_ref(field_ref_const) {
+                Ok(field_ref) => Some(field_ref),
+                Err(err) => {
+                    log::warn!("invalid field ref #{}: {}", idx.0, err);
+                    None
+                }
+            }
         } else {
             None
         }
     }
 
+    fn resolve_field_ref(&self, field_ref_const: &FieldRefConstant) -> Fallible<FieldRef> {
+        let name_and_type = match self.get_name_and_type(field_ref_const.name_and_type_index) {
+            Some(name_and_type) => name_and_type,
+            None => bail!("expected name and type"),
+        };
+        let descriptor_string = match self.get_utf8(name_and_type.descriptor_index) {
+            Some(descriptor_string) => descriptor_string,
+            None => bail!("expected descriptor string"),
+        };
+        let descriptor = FieldType::parse(descriptor_string.as_bytes())
+            .map_err(|err| format_err!("malformed descriptor {:?}: {}", descriptor_string, err))?;
+        Ok(FieldRef {
+            class_index: field_ref_const.class_index,
+            name_index: name_and_type.name_index,
+            descriptor,
+        })
+    }
+
+    pub fn get_integer(&self, idx: ConstantIndex) -> Option<i32> {
+        if let Some(Constant::Integer(inner)) = self.get_info(idx) {
+            Some(inner.value)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_long(&self, idx: ConstantIndex) -> Option<i64> {
+        if let Some(Constant::Long(inner)) = self.get_info(idx) {
+            Some(inner.value)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_float(&self, idx: ConstantIndex) -> Option<f32> {
+        if let Some(Constant::Float(inner)) = self.get_info(idx) {
+            Some(inner.value)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_double(&self, idx: ConstantIndex) -> Option<f64> {
+        if let Some(Constant::Double(inner)) = self.get_info(idx) {
+            Some(inner.value)
+        } else {
+            None
+        }
+    }
+
+    /// Resolves the value of a `ConstantValue` attribute.
+    pub fn get_constant_value(&self, idx: ConstantIndex) -> Option<ConstantLiteral> {
+        match self.get_info(idx)? {
+            Constant::Integer(inner) => Some(ConstantLiteral::Int(inner.value)),
+            Constant::Long(inner) => Some(ConstantLiteral::Long(inner.value)),
+            Constant::Float(inner) => Some(ConstantLiteral::Float(inner.value)),
+            Constant::Double(inner) => Some(ConstantLiteral::Double(inner.value)),
+            Constant::String(inner) => self
+                .get_utf8(inner.string_index)
+                .map(|string| ConstantLiteral::String(string.clone())),
+            _ => None,
+        }
+    }
+
     pub fn get_name_and_type(&self, idx: ConstantIndex) -> Option<&NameAndTypeConstant> {
         if let Some(&Constant::NameAndType(ref inner)) = self.get_info(idx) {
             Some(inner)
@@ -128,7 +216,7 @@ impl ConstantPool {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct ConstantIndex(pub(crate) u16);
 
 impl ConstantIndex {
@@ -304,7 +392,7 @@ impl<'a> ConstantPoolParser<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub enum Constant {
     Class(ClassConstant),
     FieldRef(FieldRefConstant),
@@ -323,35 +411,51 @@ pub enum Constant {
     Unusable,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstantLiteral {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(StrBuf),
+}
+
+/// The class referred to by a class constant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClassRef {
+    Class(StrBuf),
+    Array(ArrayType),
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct ClassConstant {
     pub name_index: ConstantIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct FieldRefConstant {
     pub class_index: ConstantIndex,
     pub name_and_type_index: ConstantIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct MethodRefConstant {
     pub class_index: ConstantIndex,
     pub name_and_type_index: ConstantIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct InterfaceMethodRefConstant {
     pub class_index: ConstantIndex,
     pub name_and_type_index: ConstantIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct StringConstant {
     pub string_index: ConstantIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct IntegerConstant {
     pub value: i32,
 }
@@ -361,7 +465,23 @@ pub struct FloatConstant {
     pub value: f32,
 }
 
-#[derive(Debug)]
+// floats are compared by their bit pattern, so that NaN constants equal
+// themselves and +0.0 and -0.0 stay distinct
+impl PartialEq for FloatConstant {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.to_bits() == other.value.to_bits()
+    }
+}
+
+impl Eq for FloatConstant {}
+
+impl Hash for FloatConstant {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.to_bits().hash(state)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct LongConstant {
     pub value: i64,
 }
@@ -371,28 +491,209 @@ pub struct DoubleConstant {
     pub value: f64,
 }
 
-#[derive(Debug)]
+impl PartialEq for DoubleConstant {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.to_bits() == other.value.to_bits()
+    }
+}
+
+impl Eq for DoubleConstant {}
+
+impl Hash for DoubleConstant {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.to_bits().hash(state)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct NameAndTypeConstant {
     pub name_index: ConstantIndex,
     pub descriptor_index: ConstantIndex,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Utf8Constant(pub StrBuf);
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct MethodHandleConstant {
     pub reference_kind: u8,
     pub reference_index: ConstantIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct MethodTypeConstant {
     pub descriptor_index: ConstantIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct InvokeDynamicConstant {
     pub bootstrap_method_attr_index: ConstantIndex,
     pub name_and_type_index: ConstantIndex,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+    use crate::test_util::ConstantPoolBuilder;
+
+    fn constant_pool() -> ConstantPool {
+        let mut pool = ConstantPoolBuilder::default();
+        // #1: int 42
+        pool.constant(&[CONSTANT_INTEGER, 0, 0, 0, 42]);
+        // #2: long -1 (occupying two slots)
+        let mut long = vec![CONSTANT_LONG];
+        long.extend_from_slice(&(-1i64).to_be_bytes());
+        pool.wide_constant(&long);
+        // #4: float 1.5
+        let mut float = vec![CONSTANT_FLOAT];
+        float.extend_from_slice(&1.5f32.to_bits().to_be_bytes());
+        pool.constant(&float);
+        // #5: double 0.25 (occupying two slots)
+        let mut double = vec![CONSTANT_DOUBLE];
+        double.extend_from_slice(&0.25f64.to_bits().to_be_bytes());
+        pool.wide_constant(&double);
+        // #7: string "hi"
+        pool.constant(&[CONSTANT_STRING, 0, 8]);
+        // #8: utf8 "hi"
+        pool.utf8("hi");
+        // #9: class "hi"
+        pool.class_ref(8);
+        pool.parse()
+    }
+
+    fn float_pool(value: f32) -> ConstantPool {
+        let mut float = vec![CONSTANT_FLOAT];
+        float.extend_from_slice(&value.to_bits().to_be_bytes());
+        let mut pool = ConstantPoolBuilder::default();
+        pool.constant(&float);
+        pool.parse()
+    }
+
+    fn hash(pool: &ConstantPool) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        pool.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn get_constant_value() {
+        let pool = constant_pool();
+        let value = |idx| pool.get_constant_value(ConstantIndex(idx));
+
+        assert_eq!(Some(ConstantLiteral::Int(42)), value(1));
+        assert_eq!(Some(ConstantLiteral::Long(-1)), value(2));
+        assert_eq!(Some(ConstantLiteral::Float(1.5)), value(4));
+        assert_eq!(Some(ConstantLiteral::Double(0.25)), value(5));
+        match value(7) {
+            Some(ConstantLiteral::String(ref string)) => assert_eq!("hi", &**string),
+            other => panic!("unexpected constant value {:?}", other),
+        }
+    }
+
+    #[test]
+    fn get_constant_value_rejects_other_constants() {
+        let pool = constant_pool();
+
+        assert_eq!(None, pool.get_constant_value(ConstantIndex(3)));
+        assert_eq!(None, pool.get_constant_value(ConstantIndex(8)));
+        assert_eq!(None, pool.get_constant_value(ConstantIndex(9)));
+    }
+
+    #[test]
+    fn get_numeric_constants() {
+        let pool = constant_pool();
+
+        assert_eq!(Some(42), pool.get_integer(ConstantIndex(1)));
+        assert_eq!(Some(-1), pool.get_long(ConstantIndex(2)));
+        assert_eq!(Some(1.5), pool.get_float(ConstantIndex(4)));
+        assert_eq!(Some(0.25), pool.get_double(ConstantIndex(5)));
+        assert_eq!(None, pool.get_integer(ConstantIndex(2)));
+        assert_eq!(None, pool.get_long(ConstantIndex(5)));
+        assert_eq!(None, pool.get_float(ConstantIndex(1)));
+        assert_eq!(None, pool.get_double(ConstantIndex(7)));
+    }
+
+    #[test]
+    fn get_class_or_array() {
+        let mut pool = ConstantPoolBuilder::default();
+        // #1: utf8 "java/lang/String", #2: class #1
+        pool.class("java/lang/String");
+        // #3: utf8 "[Ljava/lang/Object;", #4: class #3
+        pool.class("[Ljava/lang/Object;");
+        // #5: utf8 "[Q", #6: class #5
+        pool.class("[Q");
+        let pool = pool.parse();
+
+        assert_eq!(
+            ClassRef::Class(StrBuf::new("java/lang/String")),
+            pool.get_class_or_array(ConstantIndex(2)).unwrap()
+        );
+        match pool.get_class_or_array(ConstantIndex(4)).unwrap() {
+            ClassRef::Array(array_type) => assert_eq!(
+                FieldType::try_from_str("Ljava/lang/Object;").unwrap(),
+                *array_type.component_type
+            ),
+            other => panic!("unexpected class {:?}", other),
+        }
+        assert_eq!(
+            "invalid array class name \"[Q\" in class #6",
+            pool.get_class_or_array(ConstantIndex(6))
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "invalid class constant #1",
+            pool.get_class_or_array(ConstantIndex(1))
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn get_field_ref_with_malformed_descriptor() {
+        let mut pool = ConstantPoolBuilder::default();
+        // #1: field ref #2.#3
+        pool.constant(&[CONSTANT_FIELD_REF, 0, 2, 0, 3]);
+        // #2: class #4
+        pool.class_ref(4);
+        // #3: name and type #4:#5
+        pool.constant(&[CONSTANT_NAME_AND_TYPE, 0, 4, 0, 5]);
+        // #4: utf8 "x", #5: utf8 "Q"
+        pool.utf8("x");
+        pool.utf8("Q");
+        let pool = pool.parse();
+
+        assert!(pool.get_field_ref(ConstantIndex(1)).is_none());
+    }
+
+    #[test]
+    fn iter_skips_unusable_slots() {
+        let pool = constant_pool();
+        let indices = pool.iter().map(|(idx, _)| idx.0).collect::<Vec<_>>();
+
+        assert_eq!(vec![1, 2, 4, 5, 7, 8, 9], indices);
+        let third = pool.iter().nth(2);
+        match third {
+            Some((ConstantIndex(4), Constant::Float(_))) => {}
+            other => panic!("unexpected constant {:?}", other),
+        }
+    }
+
+    #[test]
+    fn identical_pools_are_equal() {
+        let pool = constant_pool();
+        let other = constant_pool();
+
+        assert_eq!(pool, other);
+        assert_eq!(hash(&pool), hash(&other));
+    }
+
+    #[test]
+    fn float_constants_compare_bit_patterns() {
+        assert_ne!(float_pool(0.0), float_pool(-0.0));
+        assert_eq!(float_pool(f32::NAN), float_pool(f32::NAN));
+        assert_eq!(hash(&float_pool(f32::NAN)), hash(&float_pool(f32::NAN)));
+    }
+}
@@ -30,6 +30,10 @@ impl MethodDescriptor {
         };
         Ok(MethodDescriptor { params, ret })
     }
+
+    pub fn try_from_str(input: &str) -> Fallible<Self> {
+        Self::parse(input.as_bytes())
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -73,7 +77,7 @@ impl FieldType {
                 if class_name_bytes.pop() != Some(b';') {
                     bail!("invalid class name");
                 }
-                let class_name = String::from_utf8(class_name_bytes)?.replace('/', ".");
+                let class_name = String::from_utf8(class_name_bytes)?;
                 Ok(FieldType::Object(ObjectType { class_name }))
             }
             '[' => {
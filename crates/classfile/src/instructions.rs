@@ -1,7 +1,9 @@
+use std::collections::BTreeSet;
+use std::fmt;
 use std::io::Cursor;
 
-use byteorder::{BigEndian, ReadBytesExt};
-use failure::{bail, Fallible};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use failure::{bail, Fail, Fallible};
 
 use crate::ByteBuf;
 
@@ -31,6 +33,21 @@ pub enum ArrayType {
     Long,
 }
 
+impl ArrayType {
+    fn encode(&self) -> u8 {
+        match self {
+            ArrayType::Boolean => 4,
+            ArrayType::Char => 5,
+            ArrayType::Float => 6,
+            ArrayType::Double => 7,
+            ArrayType::Byte => 8,
+            ArrayType::Short => 9,
+            ArrayType::Int => 10,
+            ArrayType::Long => 11,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Instr {
     AaLoad,
@@ -266,8 +283,467 @@ impl Instr {
             _ => false,
         }
     }
+
+    /// Writes the opcode and operands of this instruction, as located at `pc`.
+    ///
+    /// Local variable instructions with an index between 0 and 3 are encoded
+    /// using their short form where one exists.
+    pub fn encode(&self, pc: u32, out: &mut Vec<u8>) -> Fallible<()> {
+        match self {
+            Instr::AaLoad => out.push(0x32),
+            Instr::AaStore => out.push(0x53),
+            Instr::AConstNull => out.push(0x01),
+            Instr::ALoad(idx) => {
+                out.push(0x19);
+                out.push(*idx);
+            }
+            Instr::ALoad0 => out.push(0x2a),
+            Instr::ALoad1 => out.push(0x2b),
+            Instr::ALoad2 => out.push(0x2c),
+            Instr::ALoad3 => out.push(0x2d),
+            Instr::ANewArray(idx) => {
+                out.push(0xbd);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::AReturn => out.push(0xb0),
+            Instr::ArrayLength => out.push(0xbe),
+            Instr::AStore(idx) => {
+                out.push(0x3a);
+                out.push(*idx);
+            }
+            Instr::AStore0 => out.push(0x4b),
+            Instr::AStore1 => out.push(0x4c),
+            Instr::AStore2 => out.push(0x4d),
+            Instr::AStore3 => out.push(0x4e),
+            Instr::AThrow => out.push(0xbf),
+            Instr::BaLoad => out.push(0x33),
+            Instr::BaStore => out.push(0x54),
+            Instr::BiPush(value) => {
+                out.push(0x10);
+                out.push(*value as u8);
+            }
+            Instr::CaLoad => out.push(0x34),
+            Instr::CaStore => out.push(0x55),
+            Instr::CheckCast(idx) => {
+                out.push(0xc0);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::D2F => out.push(0x90),
+            Instr::D2I => out.push(0x8e),
+            Instr::D2L => out.push(0x8f),
+            Instr::DAdd => out.push(0x63),
+            Instr::DaLoad => out.push(0x31),
+            Instr::DaStore => out.push(0x52),
+            Instr::DCmpG => out.push(0x98),
+            Instr::DCmpL => out.push(0x97),
+            Instr::DConst0 => out.push(0x0e),
+            Instr::DConst1 => out.push(0x0f),
+            Instr::DDiv => out.push(0x6f),
+            Instr::DLoad(idx @ 0..=3) => out.push(0x26 + idx),
+            Instr::DLoad(idx) => {
+                out.push(0x18);
+                out.push(*idx);
+            }
+            Instr::DMul => out.push(0x6b),
+            Instr::DNeg => out.push(0x77),
+            Instr::DRem => out.push(0x73),
+            Instr::DReturn => out.push(0xaf),
+            Instr::DStore(idx @ 0..=3) => out.push(0x47 + idx),
+            Instr::DStore(idx) => {
+                out.push(0x39);
+                out.push(*idx);
+            }
+            Instr::DSub => out.push(0x67),
+            Instr::Dup => out.push(0x59),
+            Instr::DupX1 => out.push(0x5a),
+            Instr::DupX2 => out.push(0x5b),
+            Instr::Dup2 => out.push(0x5c),
+            Instr::Dup2X1 => out.push(0x5d),
+            Instr::Dup2X2 => out.push(0x5e),
+            Instr::F2D => out.push(0x8d),
+            Instr::F2I => out.push(0x8b),
+            Instr::F2L => out.push(0x8c),
+            Instr::FAdd => out.push(0x62),
+            Instr::FaLoad => out.push(0x30),
+            Instr::FaStore => out.push(0x51),
+            Instr::FCmpG => out.push(0x96),
+            Instr::FCmpL => out.push(0x95),
+            Instr::FConst0 => out.push(0x0b),
+            Instr::FConst1 => out.push(0x0c),
+            Instr::FConst2 => out.push(0x0d),
+            Instr::FDiv => out.push(0x6e),
+            Instr::FLoad(idx @ 0..=3) => out.push(0x22 + idx),
+            Instr::FLoad(idx) => {
+                out.push(0x17);
+                out.push(*idx);
+            }
+            Instr::FMul => out.push(0x6a),
+            Instr::FNeg => out.push(0x76),
+            Instr::FRem => out.push(0x72),
+            Instr::FReturn => out.push(0xae),
+            Instr::FStore(idx @ 0..=3) => out.push(0x43 + idx),
+            Instr::FStore(idx) => {
+                out.push(0x38);
+                out.push(*idx);
+            }
+            Instr::FSub => out.push(0x66),
+            Instr::GetField(idx) => {
+                out.push(0xb4);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::GetStatic(idx) => {
+                out.push(0xb2);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::Goto(offset) => {
+                out.push(0xa7);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::GotoW(offset) => {
+                out.push(0xc8);
+                out.write_i32::<BigEndian>(*offset)?;
+            }
+            Instr::I2B => out.push(0x91),
+            Instr::I2C => out.push(0x92),
+            Instr::I2D => out.push(0x87),
+            Instr::I2F => out.push(0x86),
+            Instr::I2L => out.push(0x85),
+            Instr::I2S => out.push(0x93),
+            Instr::IAdd => out.push(0x60),
+            Instr::IaLoad => out.push(0x2e),
+            Instr::IAnd => out.push(0x7e),
+            Instr::IaStore => out.push(0x4f),
+            Instr::IConstM1 => out.push(0x02),
+            Instr::IConst0 => out.push(0x03),
+            Instr::IConst1 => out.push(0x04),
+            Instr::IConst2 => out.push(0x05),
+            Instr::IConst3 => out.push(0x06),
+            Instr::IConst4 => out.push(0x07),
+            Instr::IConst5 => out.push(0x08),
+            Instr::IDiv => out.push(0x6c),
+            Instr::IfACmpEq(offset) => {
+                out.push(0xa5);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfACmpNe(offset) => {
+                out.push(0xa6);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfICmpEq(offset) => {
+                out.push(0x9f);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfICmpNe(offset) => {
+                out.push(0xa0);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfICmpLt(offset) => {
+                out.push(0xa1);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfICmpGe(offset) => {
+                out.push(0xa2);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfICmpGt(offset) => {
+                out.push(0xa3);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfICmpLe(offset) => {
+                out.push(0xa4);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfEq(offset) => {
+                out.push(0x99);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfNe(offset) => {
+                out.push(0x9a);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfLt(offset) => {
+                out.push(0x9b);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfGe(offset) => {
+                out.push(0x9c);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfGt(offset) => {
+                out.push(0x9d);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfLe(offset) => {
+                out.push(0x9e);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfNonNull(offset) => {
+                out.push(0xc7);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IfNull(offset) => {
+                out.push(0xc6);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::IInc(idx, value) => {
+                out.push(0x84);
+                out.push(*idx);
+                out.push(*value as u8);
+            }
+            Instr::ILoad(idx @ 0..=3) => out.push(0x1a + idx),
+            Instr::ILoad(idx) => {
+                out.push(0x15);
+                out.push(*idx);
+            }
+            Instr::IMul => out.push(0x68),
+            Instr::INeg => out.push(0x74),
+            Instr::InstanceOf(idx) => {
+                out.push(0xc1);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::InvokeDynamic(idx, zero) => {
+                out.push(0xba);
+                out.write_u16::<BigEndian>(*idx)?;
+                out.write_u16::<BigEndian>(*zero)?;
+            }
+            Instr::InvokeInterface(idx, count, zero) => {
+                out.push(0xb9);
+                out.write_u16::<BigEndian>(*idx)?;
+                out.push(*count);
+                out.push(*zero);
+            }
+            Instr::InvokeSpecial(idx) => {
+                out.push(0xb7);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::InvokeStatic(idx) => {
+                out.push(0xb8);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::InvokeVirtual(idx) => {
+                out.push(0xb6);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::IOr => out.push(0x80),
+            Instr::IRem => out.push(0x70),
+            Instr::IReturn => out.push(0xac),
+            Instr::IShL => out.push(0x78),
+            Instr::IShR => out.push(0x7a),
+            Instr::IStore(idx @ 0..=3) => out.push(0x3b + idx),
+            Instr::IStore(idx) => {
+                out.push(0x36);
+                out.push(*idx);
+            }
+            Instr::ISub => out.push(0x64),
+            Instr::IUShR => out.push(0x7c),
+            Instr::IXor => out.push(0x82),
+            Instr::Jsr(offset) => {
+                out.push(0xa8);
+                out.write_i16::<BigEndian>(*offset)?;
+            }
+            Instr::JsrW(offset) => {
+                out.push(0xc9);
+                out.write_i32::<BigEndian>(*offset)?;
+            }
+            Instr::L2D => out.push(0x8a),
+            Instr::L2F => out.push(0x89),
+            Instr::L2I => out.push(0x88),
+            Instr::LAdd => out.push(0x61),
+            Instr::LaLoad => out.push(0x2f),
+            Instr::LAnd => out.push(0x7f),
+            Instr::LaStore => out.push(0x50),
+            Instr::LCmp => out.push(0x94),
+            Instr::LConst0 => out.push(0x09),
+            Instr::LConst1 => out.push(0x0a),
+            Instr::LdC(idx) => {
+                out.push(0x12);
+                out.push(*idx);
+            }
+            Instr::LdCW(idx) => {
+                out.push(0x13);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::LdC2W(idx) => {
+                out.push(0x14);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::LDiv => out.push(0x6d),
+            Instr::LLoad(idx @ 0..=3) => out.push(0x1e + idx),
+            Instr::LLoad(idx) => {
+                out.push(0x16);
+                out.push(*idx);
+            }
+            Instr::LMul => out.push(0x69),
+            Instr::LNeg => out.push(0x75),
+            Instr::LookupSwitch(lookup_switch) => {
+                out.push(0xab);
+                encode_switch_padding(pc, out);
+                out.write_i32::<BigEndian>(lookup_switch.default)?;
+                out.write_i32::<BigEndian>(lookup_switch.pairs.len() as i32)?;
+                for (value, offset) in lookup_switch.pairs.iter() {
+                    out.write_i32::<BigEndian>(*value)?;
+                    out.write_i32::<BigEndian>(*offset)?;
+                }
+            }
+            Instr::LOr => out.push(0x81),
+            Instr::LRem => out.push(0x71),
+            Instr::LReturn => out.push(0xad),
+            Instr::LShL => out.push(0x79),
+            Instr::LShR => out.push(0x7b),
+            Instr::LStore(idx @ 0..=3) => out.push(0x3f + idx),
+            Instr::LStore(idx) => {
+                out.push(0x37);
+                out.push(*idx);
+            }
+            Instr::LSub => out.push(0x65),
+            Instr::LUShR => out.push(0x7d),
+            Instr::LXor => out.push(0x83),
+            Instr::MonitorEnter => out.push(0xc2),
+            Instr::MonitorExit => out.push(0xc3),
+            Instr::MultiNewArray(idx, dimensions) => {
+                out.push(0xc5);
+                out.write_u16::<BigEndian>(*idx)?;
+                out.push(*dimensions);
+            }
+            Instr::New(idx) => {
+                out.push(0xbb);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::NewArray(array_type) => {
+                out.push(0xbc);
+                out.push(array_type.encode());
+            }
+            Instr::Nop => out.push(0x00),
+            Instr::Pop => out.push(0x57),
+            Instr::Pop2 => out.push(0x58),
+            Instr::PutField(idx) => {
+                out.push(0xb5);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::PutStatic(idx) => {
+                out.push(0xb3);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::Ret(idx) => {
+                out.push(0xa9);
+                out.push(*idx);
+            }
+            Instr::Return => out.push(0xb1),
+            Instr::SaLoad => out.push(0x35),
+            Instr::SaStore => out.push(0x56),
+            Instr::SiPush(value) => {
+                out.push(0x11);
+                out.write_i16::<BigEndian>(*value)?;
+            }
+            Instr::Swap => out.push(0x5f),
+            Instr::TableSwitch(table_switch) => {
+                out.push(0xaa);
+                encode_switch_padding(pc, out);
+                out.write_i32::<BigEndian>(table_switch.default)?;
+                out.write_i32::<BigEndian>(table_switch.low)?;
+                out.write_i32::<BigEndian>(table_switch.high)?;
+                for offset in table_switch.offsets.iter() {
+                    out.write_i32::<BigEndian>(*offset)?;
+                }
+            }
+            Instr::WideILoad(idx) => {
+                out.extend_from_slice(&[0xc4, 0x15]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideFLoad(idx) => {
+                out.extend_from_slice(&[0xc4, 0x17]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideALoad(idx) => {
+                out.extend_from_slice(&[0xc4, 0x19]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideLLoad(idx) => {
+                out.extend_from_slice(&[0xc4, 0x16]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideDLoad(idx) => {
+                out.extend_from_slice(&[0xc4, 0x18]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideIStore(idx) => {
+                out.extend_from_slice(&[0xc4, 0x36]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideFStore(idx) => {
+                out.extend_from_slice(&[0xc4, 0x38]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideAStore(idx) => {
+                out.extend_from_slice(&[0xc4, 0x3a]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideLStore(idx) => {
+                out.extend_from_slice(&[0xc4, 0x37]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideDStore(idx) => {
+                out.extend_from_slice(&[0xc4, 0x39]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideRet(idx) => {
+                out.extend_from_slice(&[0xc4, 0xa9]);
+                out.write_u16::<BigEndian>(*idx)?;
+            }
+            Instr::WideIInc(idx, value) => {
+                out.extend_from_slice(&[0xc4, 0x84]);
+                out.write_u16::<BigEndian>(*idx)?;
+                out.write_i16::<BigEndian>(*value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of bytes this instruction occupies when encoded at `pc`.
+    pub fn encoded_len(&self, pc: u32) -> Fallible<u32> {
+        let mut out = vec![];
+        self.encode(pc, &mut out)?;
+        Ok(out.len() as u32)
+    }
+}
+
+fn encode_switch_padding(pc: u32, out: &mut Vec<u8>) {
+    // operands start at the next multiple of four after the opcode
+    let align_diff = 0u32.wrapping_sub(pc + 1) & 0b11;
+    for _ in 0..align_diff {
+        out.push(0);
+    }
 }
 
+#[derive(Debug)]
+pub enum DecodeError {
+    UnknownOpcode { pc: u32, opcode: u8 },
+    UnknownWideOpcode { pc: u32, opcode: u8 },
+    InvalidOffset { pc: u32 },
+    StraddlesEnd { pc: u32, end: u32 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:x} at pc {}", opcode, pc)
+            }
+            DecodeError::UnknownWideOpcode { pc, opcode } => {
+                write!(f, "unknown wide opcode {:x} at pc {}", opcode, pc)
+            }
+            DecodeError::InvalidOffset { pc } => {
+                write!(f, "pc {} is not at the start of an instruction", pc)
+            }
+            DecodeError::StraddlesEnd { pc, end } => {
+                write!(f, "instruction at pc {} extends past the end {}", pc, end)
+            }
+        }
+    }
+}
+
+impl Fail for DecodeError {}
+
 pub struct Disassembler {
     code: Cursor<ByteBuf>,
 }
@@ -283,10 +759,50 @@ impl Disassembler {
         self.code.position() as u32
     }
 
+    pub fn code_len(&self) -> u32 {
+        self.code.get_ref().len() as u32
+    }
+
     pub fn set_position(&mut self, pos: u32) {
         self.code.set_position(u64::from(pos))
     }
 
+    /// Like `set_position`, but fails unless `pos` is the start of an instruction.
+    pub fn seek(&mut self, pos: u32) -> Fallible<()> {
+        if !self.valid_offsets()?.contains(&pos) {
+            return Err(DecodeError::InvalidOffset { pc: pos }.into());
+        }
+        self.set_position(pos);
+        Ok(())
+    }
+
+    /// Decodes the whole method to find the offsets at which instructions start.
+    pub fn valid_offsets(&self) -> Fallible<BTreeSet<u32>> {
+        let mut disasm = Disassembler::new(self.code.get_ref().clone());
+        let mut offsets = BTreeSet::new();
+        while let Some((pos, _)) = disasm.decode_next()? {
+            offsets.insert(pos);
+        }
+        Ok(offsets)
+    }
+
+    /// Decodes the instructions in `[start, end)`, failing if the last one
+    /// does not end exactly at `end`.
+    pub fn decode_range(&mut self, start: u32, end: u32) -> Fallible<Vec<(u32, Instr)>> {
+        self.seek(start)?;
+        let mut instrs = Vec::new();
+        while self.position() < end {
+            match self.decode_next()? {
+                Some((pc, _)) if self.position() > end => {
+                    return Err(DecodeError::StraddlesEnd { pc, end }.into());
+                }
+                Some(instr) => instrs.push(instr),
+                None => return Err(DecodeError::InvalidOffset { pc: end }.into()),
+            }
+        }
+        Ok(instrs)
+    }
+
     pub fn decode_next(&mut self) -> Fallible<Option<(u32, Instr)>> {
         let pos = self.position();
         if pos >= self.code.get_ref().len() as u32 {
@@ -517,9 +1033,9 @@ impl Disassembler {
                     self.code.read_u16::<BigEndian>()?,
                     self.code.read_i16::<BigEndian>()?,
                 ),
-                unknown_opcode => bail!("unknown wide opcode {:x}", unknown_opcode),
+                opcode => return Err(DecodeError::UnknownWideOpcode { pc: pos, opcode }.into()),
             },
-            unknown_opcode => bail!("unknown opcode {:x}", unknown_opcode),
+            opcode => return Err(DecodeError::UnknownOpcode { pc: pos, opcode }.into()),
         };
         Ok(Some((pos, instruction)))
     }
@@ -579,3 +1095,100 @@ impl Disassembler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_opcode_reports_pc() {
+        // iconst_0, nop, <0xff>
+        let mut disasm = Disassembler::new(vec![0x03, 0x00, 0xff].into());
+        assert_eq!(0, disasm.decode_next().unwrap().unwrap().0);
+        assert_eq!(1, disasm.decode_next().unwrap().unwrap().0);
+
+        let err = disasm.decode_next().unwrap_err();
+        assert_eq!("unknown opcode ff at pc 2", err.to_string());
+        match err.downcast::<DecodeError>().unwrap() {
+            DecodeError::UnknownOpcode { pc, opcode } => {
+                assert_eq!(2, pc);
+                assert_eq!(0xff, opcode);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn seek_to_instruction_boundary() {
+        // sipush 256, ireturn
+        let mut disasm = Disassembler::new(vec![0x11, 0x01, 0x00, 0xac].into());
+        let offsets = disasm.valid_offsets().unwrap();
+        assert_eq!(vec![0, 3], offsets.into_iter().collect::<Vec<_>>());
+
+        disasm.seek(3).unwrap();
+        assert_eq!(3, disasm.position());
+        match disasm.decode_next().unwrap() {
+            Some((3, Instr::IReturn)) => {}
+            other => panic!("unexpected instruction {:?}", other),
+        }
+
+        let err = disasm.seek(1).unwrap_err();
+        assert_eq!(
+            "pc 1 is not at the start of an instruction",
+            err.to_string()
+        );
+        assert_eq!(4, disasm.position());
+    }
+
+    #[test]
+    fn decode_range_on_boundary() {
+        // iconst_0, sipush 256, iadd, ireturn
+        let mut disasm = Disassembler::new(vec![0x03, 0x11, 0x01, 0x00, 0x60, 0xac].into());
+        let instrs = disasm.decode_range(1, 5).unwrap();
+        match &instrs[..] {
+            [(1, Instr::SiPush(256)), (4, Instr::IAdd)] => {}
+            other => panic!("unexpected instructions {:?}", other),
+        }
+        assert_eq!(5, disasm.position());
+    }
+
+    #[test]
+    fn decode_range_straddling_end() {
+        // iconst_0, sipush 256, ireturn
+        let mut disasm = Disassembler::new(vec![0x03, 0x11, 0x01, 0x00, 0xac].into());
+        let err = disasm.decode_range(0, 2).unwrap_err();
+        assert_eq!(
+            "instruction at pc 1 extends past the end 2",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let mut code = vec![];
+        // iload_2, iload 5, bipush -3, sipush 1000, iinc 1 -1, wide iinc 300 1000
+        code.extend_from_slice(&[0x1c, 0x15, 0x05, 0x10, 0xfd, 0x11, 0x03, 0xe8]);
+        code.extend_from_slice(&[0x84, 0x01, 0xff, 0xc4, 0x84, 0x01, 0x2c, 0x03, 0xe8]);
+        // invokeinterface #7 1 0, newarray int, goto -5
+        code.extend_from_slice(&[0xb9, 0x00, 0x07, 0x01, 0x00, 0xbc, 0x0a, 0xa7, 0xff, 0xfb]);
+        // nop, tableswitch at pc 28 with padding, default 4, low 0, high 1
+        code.extend_from_slice(&[0x00, 0xaa, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 1]);
+        code.extend_from_slice(&[0, 0, 0, 8, 0, 0, 0, 12]);
+        // lookupswitch at pc 52 with padding, default 4, one pair
+        code.extend_from_slice(&[0xab, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 1]);
+        code.extend_from_slice(&[0, 0, 0, 9, 0, 0, 0, 8]);
+        // return
+        code.push(0xb1);
+
+        let mut disasm = Disassembler::new(code.clone().into());
+        let mut encoded = vec![];
+        while let Some((pc, instr)) = disasm.decode_next().unwrap() {
+            assert_eq!(pc, encoded.len() as u32);
+            let len = instr.encoded_len(pc).unwrap();
+            instr.encode(pc, &mut encoded).unwrap();
+            assert_eq!(disasm.position(), pc + len);
+        }
+
+        assert_eq!(code, encoded);
+    }
+}
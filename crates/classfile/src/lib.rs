@@ -1,8 +1,9 @@
+use std::collections::BTreeSet;
 use std::io::Read;
 
 use byteorder::{BigEndian, ReadBytesExt};
 use bytes::Bytes;
-use failure::{ensure, Fallible};
+use failure::{bail, ensure, format_err, Fallible};
 use strbuf::StrBuf;
 
 mod access_flags;
@@ -11,13 +12,26 @@ pub use self::access_flags::{ClassAccessFlags, FieldAccessFlags, MethodAccessFla
 pub mod constant_pool;
 pub use self::constant_pool::{ConstantIndex, ConstantPool};
 pub mod attrs;
-pub use self::attrs::{Attribute, Attributes};
+pub use self::attrs::{Attribute, Attributes, Code};
 pub mod descriptors;
 pub use self::descriptors::{FieldType, MethodDescriptor};
 pub mod instructions;
-
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod visitor;
+pub use self::visitor::ClassFileVisitor;
+
+use self::attrs::{AnnotationDefault, Deprecated, Synthetic};
+use self::constant_pool::Constant;
+use self::descriptors::{ParameterDescriptor, ReturnTypeDescriptor};
 use crate::buffer::ByteBuf;
 
+/// The newest class file format that is supported, which is the one of Java 8.
+pub const MAX_SUPPORTED_MAJOR_VERSION: u16 = 52;
+
+/// The oldest class file format, which is the one of JDK 1.0.2.
+const MIN_SUPPORTED_MAJOR_VERSION: u16 = 45;
+
 #[derive(Debug)]
 pub struct Version {
     pub major: u16,
@@ -36,6 +50,15 @@ impl Field {
     pub fn is_static(&self) -> bool {
         self.access_flags.contains(FieldAccessFlags::STATIC)
     }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.attributes.get::<Deprecated>().is_ok()
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.contains(FieldAccessFlags::SYNTHETIC)
+            || self.attributes.get::<Synthetic>().is_ok()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -58,6 +81,28 @@ impl Method {
     pub fn is_native(&self) -> bool {
         self.access_flags.contains(MethodAccessFlags::NATIVE)
     }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.access_flags.contains(MethodAccessFlags::SYNCHRONIZED)
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.attributes.get::<Deprecated>().is_ok()
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.contains(MethodAccessFlags::SYNTHETIC)
+            || self.attributes.get::<Synthetic>().is_ok()
+    }
+
+    pub fn code(&self) -> Fallible<Option<Code>> {
+        self.attributes.get_opt::<Code>()
+    }
+
+    /// Returns the default value of an element of an annotation type.
+    pub fn annotation_default(&self) -> Fallible<Option<AnnotationDefault>> {
+        self.attributes.get_opt::<AnnotationDefault>()
+    }
 }
 
 #[derive(Debug)]
@@ -74,6 +119,31 @@ pub struct FieldRef {
     pub descriptor: FieldType,
 }
 
+/// The leading part of a class file, up to and including the interfaces.
+#[derive(Debug)]
+pub struct ClassHeader {
+    pub version: Version,
+    pub constant_pool: ConstantPool,
+    pub access_flags: ClassAccessFlags,
+    pub this_class: ConstantIndex,
+    pub super_class: Option<ConstantIndex>,
+    pub interfaces: Vec<ConstantIndex>,
+}
+
+impl ClassHeader {
+    pub fn get_name(&self) -> &StrBuf {
+        let class = self.constant_pool.get_class(self.this_class).unwrap();
+        self.constant_pool.get_utf8(class.name_index).unwrap()
+    }
+
+    pub fn get_super_name(&self) -> Option<&StrBuf> {
+        self.super_class.map(|idx| {
+            let class = self.constant_pool.get_class(idx).unwrap();
+            self.constant_pool.get_utf8(class.name_index).unwrap()
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct ClassFile {
     pub version: Version,
@@ -97,13 +167,14 @@ impl ClassFile {
     pub fn parse_bytes(input: Bytes) -> Fallible<Self> {
         let mut parser = ClassFileParser::new(input.into());
 
-        parser.parse_magic()?;
-        let version = parser.parse_version()?;
-        let constant_pool = parser.parse_constant_pool()?;
-        let access_flags = parser.parse_access_flags()?;
-        let this_class = parser.parse_this_class()?;
-        let super_class = parser.parse_super_class()?;
-        let interfaces = parser.parse_interfaces()?;
+        let ClassHeader {
+            version,
+            constant_pool,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+        } = parser.parse_header()?;
         let fields = parser.parse_fields(&constant_pool)?;
         let methods = parser.parse_methods(&constant_pool)?;
         let attributes = parser.parse_attributes(&constant_pool)?;
@@ -121,12 +192,36 @@ impl ClassFile {
         })
     }
 
+    /// Like `parse_bytes`, but fails early for class files whose major version
+    /// is newer than `max_major`.
+    pub fn parse_bytes_with_max_version(input: Bytes, max_major: u16) -> Fallible<Self> {
+        let mut parser = ClassFileParser::new(input.clone().into());
+        parser.parse_magic()?;
+        let version = parser.parse_version()?;
+        ensure!(
+            version.major <= max_major,
+            "unsupported class file major version {} (the maximum is {})",
+            version.major,
+            max_major
+        );
+        Self::parse_bytes(input)
+    }
+
+    /// Parses only the header of a class file, skipping fields, methods and attributes.
+    pub fn parse_header(input: Bytes) -> Fallible<ClassHeader> {
+        ClassFileParser::new(input.into()).parse_header()
+    }
+
     pub fn get_name(&self) -> &StrBuf {
         self.constant_pool
             .get_utf8(self.get_this_class().name_index)
             .unwrap()
     }
 
+    pub fn supported_version(&self) -> bool {
+        (MIN_SUPPORTED_MAJOR_VERSION..=MAX_SUPPORTED_MAJOR_VERSION).contains(&self.version.major)
+    }
+
     pub fn get_this_class(&self) -> &self::constant_pool::ClassConstant {
         self.constant_pool.get_class(self.this_class).unwrap()
     }
@@ -139,6 +234,134 @@ impl ClassFile {
     pub fn is_interface(&self) -> bool {
         self.access_flags.contains(ClassAccessFlags::INTERFACE)
     }
+
+    /// Whether `invokespecial` on superclass methods should select the method
+    /// starting from the direct superclass (`ACC_SUPER`).
+    pub fn uses_super_semantics(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::SUPER)
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.attributes.get::<Deprecated>().is_ok()
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::SYNTHETIC)
+            || self.attributes.get::<Synthetic>().is_ok()
+    }
+
+    /// Replaces the bytecode of the method at `method_idx`.
+    ///
+    /// Exception handlers and attributes of the previous `Code` attribute,
+    /// such as line numbers and stack maps, are dropped.
+    pub fn set_method_code(
+        &mut self,
+        method_idx: usize,
+        code: Vec<u8>,
+        max_stack: u16,
+        max_locals: u16,
+    ) -> Fallible<()> {
+        let method = match self.methods.get_mut(method_idx) {
+            Some(method) => method,
+            None => bail!("method index {} out of bounds", method_idx),
+        };
+        let bytes = Code::encode(&code, max_stack, max_locals)?;
+        method.attributes.set_raw(Code::NAME, bytes)
+    }
+
+    /// Iterates over the methods that have a decodable `Code` attribute,
+    /// along with their code. Abstract and native methods, as well as
+    /// methods whose code fails to decode, are skipped.
+    pub fn methods_with_code(&self) -> impl Iterator<Item = (&Method, Code)> {
+        self.methods
+            .iter()
+            .filter_map(|method| match method.code() {
+                Ok(Some(code)) => Some((method, code)),
+                Ok(None) | Err(_) => None,
+            })
+    }
+
+    pub fn interface_names(&self) -> Fallible<Vec<&str>> {
+        self.interfaces
+            .iter()
+            .map(|idx| {
+                self.constant_pool
+                    .get_class(*idx)
+                    .and_then(|class| self.constant_pool.get_utf8(class.name_index))
+                    .map(|name| &**name)
+                    .ok_or_else(|| format_err!("invalid interface class constant #{}", idx.0))
+            })
+            .collect()
+    }
+
+    /// Collects the names of all classes that this class refers to, through
+    /// class constants or the object types in field and method descriptors.
+    ///
+    /// Array classes contribute the class of their innermost element type,
+    /// if that is an object type. Malformed descriptors are skipped.
+    pub fn referenced_classes(&self) -> BTreeSet<String> {
+        let consts = &self.constant_pool;
+        let mut names = BTreeSet::new();
+        let add_descriptor = |names: &mut BTreeSet<String>, idx| {
+            let descriptor = match consts.get_utf8(idx) {
+                Some(descriptor) => descriptor,
+                None => return,
+            };
+            if descriptor.starts_with('(') {
+                if let Ok(method_descriptor) = MethodDescriptor::try_from_str(descriptor) {
+                    add_method_descriptor(names, &method_descriptor);
+                }
+            } else if let Ok(field_type) = FieldType::try_from_str(descriptor) {
+                add_field_type(names, &field_type);
+            }
+        };
+        for (_, constant) in consts.iter() {
+            match constant {
+                Constant::Class(class) => {
+                    if let Some(name) = consts.get_utf8(class.name_index) {
+                        if name.starts_with('[') {
+                            add_descriptor(&mut names, class.name_index);
+                        } else {
+                            names.insert(name.to_string());
+                        }
+                    }
+                }
+                Constant::NameAndType(name_and_type) => {
+                    add_descriptor(&mut names, name_and_type.descriptor_index)
+                }
+                Constant::MethodType(method_type) => {
+                    add_descriptor(&mut names, method_type.descriptor_index)
+                }
+                _ => {}
+            }
+        }
+        for field in self.fields.iter() {
+            add_field_type(&mut names, &field.descriptor);
+        }
+        for method in self.methods.iter() {
+            add_method_descriptor(&mut names, &method.descriptor);
+        }
+        names
+    }
+}
+
+fn add_field_type(names: &mut BTreeSet<String>, field_type: &FieldType) {
+    match field_type {
+        FieldType::Base(_) => {}
+        FieldType::Object(object_type) => {
+            names.insert(object_type.class_name.clone());
+        }
+        FieldType::Array(array_type) => add_field_type(names, &array_type.component_type),
+    }
+}
+
+fn add_method_descriptor(names: &mut BTreeSet<String>, method_descriptor: &MethodDescriptor) {
+    for ParameterDescriptor::Field(field_type) in method_descriptor.params.iter() {
+        add_field_type(names, field_type);
+    }
+    if let ReturnTypeDescriptor::Field(ref field_type) = method_descriptor.ret {
+        add_field_type(names, field_type);
+    }
 }
 
 struct ClassFileParser {
@@ -150,6 +373,25 @@ impl ClassFileParser {
         ClassFileParser { reader }
     }
 
+    fn parse_header(&mut self) -> Fallible<ClassHeader> {
+        self.parse_magic()?;
+        let version = self.parse_version()?;
+        let constant_pool = self.parse_constant_pool()?;
+        let access_flags = self.parse_access_flags()?;
+        let this_class = self.parse_this_class()?;
+        let super_class = self.parse_super_class()?;
+        let interfaces = self.parse_interfaces()?;
+
+        Ok(ClassHeader {
+            version,
+            constant_pool,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+        })
+    }
+
     fn parse_magic(&mut self) -> Fallible<()> {
         let magic = self.reader.read_u32::<BigEndian>()?;
         ensure!(magic == 0xCAFE_BABE, "unknown magic byte sequence");
@@ -240,3 +482,229 @@ impl ClassFileParser {
         Attributes::parse(&mut self.reader, constants)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::ClassBuilder;
+
+    #[test]
+    fn interface_names() {
+        let mut class = ClassBuilder::new("Test");
+        class
+            .interface("java/lang/Runnable")
+            .interface("java/lang/Comparable");
+
+        let class_file = class.parse();
+        let names = class_file.interface_names().unwrap();
+
+        assert_eq!(vec!["java/lang/Runnable", "java/lang/Comparable"], names);
+    }
+
+    fn class_with_version(major: u16) -> Vec<u8> {
+        ClassBuilder::new("Test").major_version(major).to_bytes()
+    }
+
+    #[test]
+    fn supported_version() {
+        let java8 = ClassFile::parse_bytes(class_with_version(52).into()).unwrap();
+        let java21 = ClassFile::parse_bytes(class_with_version(65).into()).unwrap();
+
+        assert!(java8.supported_version());
+        assert!(!java21.supported_version());
+    }
+
+    #[test]
+    fn parse_bytes_with_max_version() {
+        let java8 = class_with_version(52);
+        let java21 = class_with_version(65);
+
+        assert!(ClassFile::parse_bytes_with_max_version(java8.into(), 52).is_ok());
+        let err = ClassFile::parse_bytes_with_max_version(java21.into(), 52).unwrap_err();
+        assert_eq!(
+            "unsupported class file major version 65 (the maximum is 52)",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn referenced_classes() {
+        let mut class = ClassBuilder::new("Test");
+        class.consts.class("[I");
+        class
+            .consts
+            .method_ref(2, "join", "([ILjava/util/List;)Ljava/lang/String;");
+        class.field(0x0001, "names", "Ljava/util/List;");
+
+        let class_file = class.parse();
+
+        assert_eq!(
+            vec![
+                "Test",
+                "java/lang/Object",
+                "java/lang/String",
+                "java/util/List"
+            ],
+            class_file
+                .referenced_classes()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn abstract_method_has_no_code() {
+        let mut class = ClassBuilder::new("Test").access_flags(0x0421);
+        class.method(0x0401, "run", "()V", &[]);
+
+        let class_file = class.parse();
+        let method = &class_file.methods[0];
+
+        assert!(method.is_abstract());
+        assert!(method.code().unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_header_ignores_truncated_methods() {
+        let mut bytes = ClassBuilder::new("Test").to_bytes();
+        // replace the method and attribute counts with a truncated method
+        bytes.truncate(bytes.len() - 4);
+        bytes.extend_from_slice(&[0, 1, 0, 0x09]);
+
+        assert!(ClassFile::parse_bytes(bytes.clone().into()).is_err());
+
+        let header = ClassFile::parse_header(bytes.into()).unwrap();
+        assert_eq!("Test", &**header.get_name());
+        assert_eq!("java/lang/Object", &**header.get_super_name().unwrap());
+        assert!(header.interfaces.is_empty());
+    }
+
+    #[test]
+    fn deprecated_method() {
+        let mut class = ClassBuilder::new("Test").access_flags(0x0421);
+        let deprecated = class.attribute("Deprecated", &[]);
+        class.method(0x0401, "run", "()V", &[deprecated]);
+        // a malformed non-empty Synthetic attribute
+        let synthetic = class.attribute("Synthetic", &[0]);
+        class.class_attribute(synthetic);
+
+        let class_file = class.parse();
+        let method = &class_file.methods[0];
+
+        assert!(method.is_deprecated());
+        assert!(!method.is_synthetic());
+        assert!(method.attributes.get::<Deprecated>().is_ok());
+        assert!(!class_file.is_deprecated());
+        assert!(class_file.attributes.get::<Synthetic>().is_err());
+    }
+
+    #[test]
+    fn methods_with_code() {
+        let mut class = ClassBuilder::new("Test").access_flags(0x0421);
+        // return
+        let code = class.code(0, 1, &[0xb1]);
+        class.method(0x0001, "run", "()V", &[code]);
+        class.method(0x0401, "stop", "()V", &[]);
+        let truncated = class.attribute("Code", &[0, 0]);
+        class.method(0x0001, "broken", "()V", &[truncated]);
+
+        let class_file = class.parse();
+        let methods = class_file.methods_with_code().collect::<Vec<_>>();
+
+        assert_eq!(1, methods.len());
+        let (method, code) = &methods[0];
+        assert_eq!(
+            "run",
+            &**class_file
+                .constant_pool
+                .get_utf8(method.name_index)
+                .unwrap()
+        );
+        assert_eq!(&[0xb1], code.bytecode());
+    }
+
+    /// Builds a class with a nest attribute, where #6 refers to the class
+    /// `other` and #5 to its name.
+    fn nestmate(name: &str, other: &str, attribute: &str, info: &[u8]) -> ClassFile {
+        let mut class = ClassBuilder::new(name).major_version(55);
+        class.consts.class(other);
+        let attribute = class.attribute(attribute, info);
+        class.class_attribute(attribute);
+        class.parse()
+    }
+
+    #[test]
+    fn nestmates() {
+        let outer = nestmate("Outer", "Outer$Inner", "NestMembers", &[0, 1, 0, 6]);
+        let inner = nestmate("Outer$Inner", "Outer", "NestHost", &[0, 6]);
+
+        let members = outer.attributes.get::<attrs::NestMembers>().unwrap();
+        let names = members
+            .member_names()
+            .map(|name| &**name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["Outer$Inner"], names);
+        assert!(outer
+            .attributes
+            .get_opt::<attrs::NestHost>()
+            .unwrap()
+            .is_none());
+
+        let host = inner.attributes.get::<attrs::NestHost>().unwrap();
+        assert_eq!(ConstantIndex::from_u16(6), host.host_class_index());
+        assert_eq!("Outer", &**host.host_name());
+        assert!(inner
+            .attributes
+            .get_opt::<attrs::NestMembers>()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn nest_host_must_refer_to_class() {
+        let inner = nestmate("Outer$Inner", "Outer", "NestHost", &[0, 5]);
+
+        assert!(inner.attributes.get::<attrs::NestHost>().is_err());
+    }
+
+    #[test]
+    fn annotation_default() {
+        let mut class = ClassBuilder::new("Retry").access_flags(0x2601);
+        let three = class.consts.constant(&[3, 0, 0, 0, 3]);
+        let mut info = vec![b'I'];
+        info.extend_from_slice(&three.to_be_bytes());
+        let default = class.attribute("AnnotationDefault", &info);
+        class.method(0x0401, "times", "()I", &[default]);
+
+        let class_file = class.parse();
+        let default = class_file.methods[0].annotation_default().unwrap().unwrap();
+
+        assert_eq!(
+            attrs::annotations::ElementValue::Const('I', constant_pool::ConstantLiteral::Int(3)),
+            default.default_value
+        );
+    }
+
+    #[test]
+    fn set_method_code() {
+        let mut class = ClassBuilder::new("Test");
+        // iconst_1, pop, return
+        let code = class.code(1, 0, &[0x04, 0x57, 0xb1]);
+        class.method(0x0009, "run", "()V", &[code]);
+
+        let mut class_file = class.parse();
+        class_file.set_method_code(0, vec![0xb1], 0, 0).unwrap();
+
+        let code = class_file.methods[0].code().unwrap().unwrap();
+        let mut disasm = code.disassemble();
+
+        assert_eq!(0, code.max_stack);
+        match disasm.decode_next().unwrap() {
+            Some((0, instructions::Instr::Return)) => {}
+            other => panic!("unexpected instruction {:?}", other),
+        }
+        assert!(disasm.decode_next().unwrap().is_none());
+        assert!(class_file.set_method_code(1, vec![0xb1], 0, 0).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+//! Builders for class files in tests, so that they don't need to spell out
+//! the bytes of constant pools, members and attributes by hand.
+//!
+//! The builders only encode what they are given, which makes it possible to
+//! build class files that are malformed in specific ways.
+
+use crate::{ClassFile, ConstantPool};
+
+/// Encodes a constant pool, handing out the index of each added constant.
+#[derive(Clone, Debug, Default)]
+pub struct ConstantPoolBuilder {
+    bytes: Vec<u8>,
+    count: u16,
+}
+
+impl ConstantPoolBuilder {
+    /// Adds a constant given as its tag followed by its info.
+    pub fn constant(&mut self, bytes: &[u8]) -> u16 {
+        self.bytes.extend_from_slice(bytes);
+        self.count += 1;
+        self.count
+    }
+
+    /// Like `constant`, but for `long` and `double` constants, which also
+    /// take up the index after their own.
+    pub fn wide_constant(&mut self, bytes: &[u8]) -> u16 {
+        let index = self.constant(bytes);
+        self.count += 1;
+        index
+    }
+
+    pub fn utf8(&mut self, value: &str) -> u16 {
+        let mut bytes = vec![1];
+        bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(value.as_bytes());
+        self.constant(&bytes)
+    }
+
+    /// Adds a class constant referring to the utf8 constant at `name_index`.
+    pub fn class_ref(&mut self, name_index: u16) -> u16 {
+        let mut bytes = vec![7];
+        bytes.extend_from_slice(&name_index.to_be_bytes());
+        self.constant(&bytes)
+    }
+
+    /// Adds a class constant along with the utf8 constant for its name.
+    pub fn class(&mut self, name: &str) -> u16 {
+        let name_index = self.utf8(name);
+        self.class_ref(name_index)
+    }
+
+    /// Adds a name and type constant along with the utf8 constants for its
+    /// name and descriptor.
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let mut bytes = vec![12];
+        bytes.extend_from_slice(&self.utf8(name).to_be_bytes());
+        bytes.extend_from_slice(&self.utf8(descriptor).to_be_bytes());
+        self.constant(&bytes)
+    }
+
+    /// Adds a field ref to a field of the class constant at `class_index`.
+    pub fn field_ref(&mut self, class_index: u16, name: &str, descriptor: &str) -> u16 {
+        self.member_ref(9, class_index, name, descriptor)
+    }
+
+    /// Adds a method ref to a method of the class constant at `class_index`.
+    pub fn method_ref(&mut self, class_index: u16, name: &str, descriptor: &str) -> u16 {
+        self.member_ref(10, class_index, name, descriptor)
+    }
+
+    fn member_ref(&mut self, tag: u8, class_index: u16, name: &str, descriptor: &str) -> u16 {
+        let name_and_type = self.name_and_type(name, descriptor);
+        let mut bytes = vec![tag];
+        bytes.extend_from_slice(&class_index.to_be_bytes());
+        bytes.extend_from_slice(&name_and_type.to_be_bytes());
+        self.constant(&bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.count + 1).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.bytes);
+        bytes
+    }
+
+    pub fn parse(&self) -> ConstantPool {
+        ConstantPool::parse(&mut self.to_bytes().into()).unwrap()
+    }
+}
+
+/// Encodes a class file.
+///
+/// The constant pool of a new builder starts with the name of the class at
+/// #1, the class itself at #2, the name of its superclass at #3 and the
+/// superclass at #4.
+#[derive(Clone, Debug)]
+pub struct ClassBuilder {
+    pub consts: ConstantPoolBuilder,
+    major_version: u16,
+    access_flags: u16,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<Vec<u8>>,
+    methods: Vec<Vec<u8>>,
+    attributes: Vec<Vec<u8>>,
+}
+
+impl ClassBuilder {
+    /// Starts a public Java 8 class extending `java/lang/Object`.
+    pub fn new(name: &str) -> Self {
+        Self::with_super(name, Some("java/lang/Object"))
+    }
+
+    /// Starts a public Java 8 class with the given superclass, or with none
+    /// at all, like `java/lang/Object` itself.
+    pub fn with_super(name: &str, super_name: Option<&str>) -> Self {
+        let mut consts = ConstantPoolBuilder::default();
+        let this_class = consts.class(name);
+        let super_class = super_name.map(|name| consts.class(name)).unwrap_or(0);
+        ClassBuilder {
+            consts,
+            major_version: 52,
+            access_flags: 0x0021,
+            this_class,
+            super_class,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![],
+            attributes: vec![],
+        }
+    }
+
+    pub fn major_version(mut self, major_version: u16) -> Self {
+        self.major_version = major_version;
+        self
+    }
+
+    pub fn access_flags(mut self, access_flags: u16) -> Self {
+        self.access_flags = access_flags;
+        self
+    }
+
+    pub fn interface(&mut self, name: &str) -> &mut Self {
+        let index = self.consts.class(name);
+        self.interfaces.push(index);
+        self
+    }
+
+    pub fn field(&mut self, access_flags: u16, name: &str, descriptor: &str) -> &mut Self {
+        let field = self.member(access_flags, name, descriptor, &[]);
+        self.fields.push(field);
+        self
+    }
+
+    /// Adds a method with the given attributes, as encoded by `attribute` or
+    /// `code`.
+    pub fn method(
+        &mut self,
+        access_flags: u16,
+        name: &str,
+        descriptor: &str,
+        attributes: &[Vec<u8>],
+    ) -> &mut Self {
+        let method = self.member(access_flags, name, descriptor, attributes);
+        self.methods.push(method);
+        self
+    }
+
+    /// Adds an attribute of the class itself, as encoded by `attribute`.
+    pub fn class_attribute(&mut self, attribute: Vec<u8>) -> &mut Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Encodes an attribute, adding its name to the constant pool.
+    pub fn attribute(&mut self, name: &str, info: &[u8]) -> Vec<u8> {
+        let mut bytes = self.consts.utf8(name).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&(info.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(info);
+        bytes
+    }
+
+    /// Encodes a `Code` attribute without exception handlers or attributes.
+    pub fn code(&mut self, max_stack: u16, max_locals: u16, code: &[u8]) -> Vec<u8> {
+        let mut info = max_stack.to_be_bytes().to_vec();
+        info.extend_from_slice(&max_locals.to_be_bytes());
+        info.extend_from_slice(&(code.len() as u32).to_be_bytes());
+        info.extend_from_slice(code);
+        // exception table, attributes
+        info.extend_from_slice(&[0, 0, 0, 0]);
+        self.attribute("Code", &info)
+    }
+
+    fn member(
+        &mut self,
+        access_flags: u16,
+        name: &str,
+        descriptor: &str,
+        attributes: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let mut bytes = access_flags.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.consts.utf8(name).to_be_bytes());
+        bytes.extend_from_slice(&self.consts.utf8(descriptor).to_be_bytes());
+        extend_with_count(&mut bytes, attributes);
+        bytes
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0];
+        bytes.extend_from_slice(&self.major_version.to_be_bytes());
+        bytes.extend_from_slice(&self.consts.to_bytes());
+        bytes.extend_from_slice(&self.access_flags.to_be_bytes());
+        bytes.extend_from_slice(&self.this_class.to_be_bytes());
+        bytes.extend_from_slice(&self.super_class.to_be_bytes());
+        bytes.extend_from_slice(&(self.interfaces.len() as u16).to_be_bytes());
+        for interface in self.interfaces.iter() {
+            bytes.extend_from_slice(&interface.to_be_bytes());
+        }
+        extend_with_count(&mut bytes, &self.fields);
+        extend_with_count(&mut bytes, &self.methods);
+        extend_with_count(&mut bytes, &self.attributes);
+        bytes
+    }
+
+    pub fn parse(&self) -> ClassFile {
+        ClassFile::parse_bytes(self.to_bytes().into()).unwrap()
+    }
+}
+
+fn extend_with_count(bytes: &mut Vec<u8>, items: &[Vec<u8>]) {
+    bytes.extend_from_slice(&(items.len() as u16).to_be_bytes());
+    for item in items {
+        bytes.extend_from_slice(item);
+    }
+}
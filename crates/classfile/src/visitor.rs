@@ -0,0 +1,81 @@
+use failure::Fallible;
+
+use crate::attrs::Code;
+use crate::instructions::Instr;
+use crate::{ClassFile, ConstantPool, Field, Method};
+
+/// Callbacks for walking the members of a class file with `ClassFile::accept`.
+///
+/// All methods do nothing by default, so visitors only need to implement
+/// the callbacks they are interested in.
+pub trait ClassFileVisitor {
+    fn visit_field(&mut self, _consts: &ConstantPool, _field: &Field) {}
+
+    fn visit_method(&mut self, _consts: &ConstantPool, _method: &Method) {}
+
+    fn visit_code(&mut self, _consts: &ConstantPool, _method: &Method, _code: &Code) {}
+
+    fn visit_instruction(&mut self, _consts: &ConstantPool, _pc: u32, _instr: &Instr) {}
+}
+
+impl ClassFile {
+    /// Visits all fields, then all methods along with their code and
+    /// instructions, in the order they appear in the class file.
+    pub fn accept<V: ClassFileVisitor>(&self, visitor: &mut V) -> Fallible<()> {
+        let consts = &self.constant_pool;
+        for field in self.fields.iter() {
+            visitor.visit_field(consts, field);
+        }
+        for method in self.methods.iter() {
+            visitor.visit_method(consts, method);
+            if let Some(code) = method.code()? {
+                visitor.visit_code(consts, method, &code);
+                let mut disasm = code.disassemble();
+                while let Some((pc, instr)) = disasm.decode_next()? {
+                    visitor.visit_instruction(consts, pc, &instr);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::ClassBuilder;
+
+    #[derive(Default)]
+    struct Collector {
+        methods: Vec<String>,
+        instructions: Vec<u32>,
+    }
+
+    impl ClassFileVisitor for Collector {
+        fn visit_method(&mut self, consts: &ConstantPool, method: &Method) {
+            let name = consts.get_utf8(method.name_index).unwrap();
+            self.methods.push(name.to_string());
+        }
+
+        fn visit_instruction(&mut self, _consts: &ConstantPool, pc: u32, _instr: &Instr) {
+            self.instructions.push(pc);
+        }
+    }
+
+    #[test]
+    fn collect_method_names() {
+        let mut class = ClassBuilder::new("Test").access_flags(0x0421);
+        class.method(0x0401, "run", "()V", &[]);
+        // nop, return
+        let code = class.code(0, 0, &[0x00, 0xb1]);
+        class.method(0x0009, "stop", "()V", &[code]);
+
+        let class_file = class.parse();
+        let mut collector = Collector::default();
+        class_file.accept(&mut collector).unwrap();
+
+        assert_eq!(vec!["run", "stop"], collector.methods);
+        assert_eq!(vec![0, 1], collector.instructions);
+    }
+}
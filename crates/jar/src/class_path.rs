@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use failure::{format_err, Fallible};
+
+use crate::{ClassEntry, JarReader};
+
+#[derive(Debug, Default)]
+pub struct ClassPath {
+    readers: Vec<JarReader<File>>,
+    paths: HashSet<PathBuf>,
+}
+
+impl ClassPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_jar<P: AsRef<Path>>(&mut self, path: P) -> Fallible<()> {
+        let path = fs::canonicalize(path)?;
+        if self.paths.insert(path.clone()) {
+            self.readers.push(JarReader::open(path)?);
+        }
+        Ok(())
+    }
+
+    /// Adds the jar at `path`, along with every jar that is transitively
+    /// referenced from the `Class-Path` header of its manifest.
+    pub fn add_jar_with_manifest_classpath<P: AsRef<Path>>(&mut self, path: P) -> Fallible<()> {
+        let mut pending = vec![fs::canonicalize(path)?];
+        while let Some(path) = pending.pop() {
+            // jars that have been opened before are skipped, which also
+            // breaks cycles between manifests
+            if !self.paths.insert(path.clone()) {
+                continue;
+            }
+            let reader = JarReader::open(&path)?;
+            if let (Some(manifest), Some(dir)) = (reader.manifest(), path.parent()) {
+                // like the jvm, ignore entries that don't point to a file
+                for entry in manifest.class_path().iter().rev() {
+                    if let Ok(entry_path) = fs::canonicalize(dir.join(entry)) {
+                        pending.push(entry_path);
+                    }
+                }
+            }
+            self.readers.push(reader);
+        }
+        Ok(())
+    }
+
+    pub fn get_class_entry(&mut self, name: &str) -> Fallible<ClassEntry> {
+        for reader in self.readers.iter_mut() {
+            if let Ok(class_entry) = reader.get_class_entry(name) {
+                return Ok(class_entry);
+            }
+        }
+        Err(format_err!("class {} not found", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+
+    fn write_jar(path: &Path, manifest: &str, classes: &[&str]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        writer
+            .start_file("META-INF/MANIFEST.MF", FileOptions::default())
+            .unwrap();
+        writer.write_all(manifest.as_bytes()).unwrap();
+        for class in classes {
+            let name = format!("{}.class", class);
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(&[0xca, 0xfe, 0xba, 0xbe]).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn manifest_class_path_is_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("lib")).unwrap();
+        write_jar(
+            &dir.path().join("a.jar"),
+            "Manifest-Version: 1.0\nClass-Path: lib/b.jar missing.jar\n",
+            &["A"],
+        );
+        write_jar(
+            &dir.path().join("lib/b.jar"),
+            "Manifest-Version: 1.0\nClass-Path: ../a.jar\n",
+            &["B"],
+        );
+
+        let mut class_path = ClassPath::new();
+        class_path
+            .add_jar_with_manifest_classpath(dir.path().join("a.jar"))
+            .unwrap();
+
+        assert_eq!(2, class_path.readers.len());
+        assert!(class_path.get_class_entry("A").is_ok());
+        assert!(class_path.get_class_entry("B").is_ok());
+        assert!(class_path.get_class_entry("C").is_err());
+    }
+}
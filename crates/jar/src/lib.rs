@@ -1,22 +1,25 @@
 pub extern crate classfile;
 
 use std::fs;
-use std::io::{BufReader, Read, Seek};
+use std::io::{BufReader, Cursor, Read, Seek};
 use std::path::Path;
 
 use bytes::Bytes;
 use classfile::ClassFile;
-use failure::Fallible;
+use failure::{bail, Fallible};
 use fnv::FnvBuildHasher;
 use zip::read::ZipArchive;
 
+mod class_path;
 mod manifest;
+pub use self::class_path::ClassPath;
 pub use self::manifest::Manifest;
 
 #[derive(Debug)]
 pub struct JarReader<R: Read + Seek> {
     archive: ZipArchive<BufReader<R>, FnvBuildHasher>,
     manifest: Option<Manifest>,
+    class_prefix: String,
 }
 
 impl<R: Read + Seek> JarReader<R> {
@@ -30,7 +33,17 @@ impl<R: Read + Seek> JarReader<R> {
             Err(err) => return Err(err.into()),
         };
 
-        Ok(JarReader { manifest, archive })
+        Ok(JarReader {
+            manifest,
+            archive,
+            class_prefix: String::new(),
+        })
+    }
+
+    /// Looks up class entries below `prefix`, e.g. `classes/` in jmod files.
+    pub fn with_class_prefix(mut self, prefix: &str) -> Self {
+        self.class_prefix = prefix.to_owned();
+        self
     }
 
     pub fn manifest(&self) -> Option<&Manifest> {
@@ -38,10 +51,27 @@ impl<R: Read + Seek> JarReader<R> {
     }
 
     pub fn get_class_entry(&mut self, name: &str) -> Fallible<ClassEntry> {
-        let mut file = self.archive.by_name(&format!("{}.class", name))?;
-        let mut data = Vec::with_capacity(file.size() as usize);
-        file.read_to_end(&mut data)?;
-        Ok(ClassEntry { bytes: data.into() })
+        let path = format!("{}{}.class", self.class_prefix, name);
+        match self.archive.by_name(&path) {
+            Ok(mut file) => {
+                let mut data = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut data)?;
+                return Ok(ClassEntry { bytes: data.into() });
+            }
+            Err(zip::result::ZipError::FileNotFound) => {}
+            Err(err) => return Err(err.into()),
+        }
+        for suffix in &[".pack.gz", ".pack"] {
+            let packed_path = format!("{}{}", path, suffix);
+            if self.archive.by_name(&packed_path).is_ok() {
+                bail!(
+                    "class {} is pack200-compressed ({}), which is not supported",
+                    name,
+                    packed_path
+                );
+            }
+        }
+        Err(zip::result::ZipError::FileNotFound.into())
     }
 }
 
@@ -52,6 +82,13 @@ impl JarReader<fs::File> {
     }
 }
 
+impl JarReader<Cursor<Bytes>> {
+    /// Reads a jar that is already held in memory, e.g. after downloading it.
+    pub fn from_bytes(bytes: Bytes) -> Fallible<Self> {
+        JarReader::try_new(Cursor::new(bytes))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ClassEntry {
     bytes: Bytes,
@@ -61,4 +98,69 @@ impl ClassEntry {
     pub fn decode(&self) -> Fallible<ClassFile> {
         ClassFile::parse_bytes(self.bytes.clone())
     }
+
+    /// Reads the name of the class without decoding the entire class file.
+    pub fn name(&self) -> Fallible<String> {
+        let header = ClassFile::parse_header(self.bytes.clone())?;
+        Ok(header.get_name().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use classfile::test_util::ClassBuilder;
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+
+    fn class_bytes(name: &str) -> Vec<u8> {
+        ClassBuilder::new(name).to_bytes()
+    }
+
+    #[test]
+    fn name_matches_decoded_class() {
+        let entry = ClassEntry {
+            bytes: class_bytes("com/example/Test").into(),
+        };
+
+        let class_file = entry.decode().unwrap();
+        assert_eq!("com/example/Test", entry.name().unwrap());
+        assert_eq!(&**class_file.get_name(), entry.name().unwrap());
+    }
+
+    #[test]
+    fn pack200_class_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("packed.jar");
+        let mut writer = ZipWriter::new(fs::File::create(&path).unwrap());
+        writer
+            .start_file("Test.class.pack.gz", FileOptions::default())
+            .unwrap();
+        writer.write_all(&[0x1f, 0x8b]).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = JarReader::open(&path).unwrap();
+        let err = reader.get_class_entry("Test").unwrap_err();
+        assert_eq!(
+            "class Test is pack200-compressed (Test.class.pack.gz), which is not supported",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn from_bytes() {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        writer
+            .start_file("com/example/Test.class", FileOptions::default())
+            .unwrap();
+        writer.write_all(&class_bytes("com/example/Test")).unwrap();
+        let jar = writer.finish().unwrap().into_inner();
+
+        let mut reader = JarReader::from_bytes(jar.into()).unwrap();
+        let entry = reader.get_class_entry("com/example/Test").unwrap();
+        assert!(reader.manifest().is_none());
+        assert_eq!("com/example/Test", entry.name().unwrap());
+    }
 }
@@ -14,6 +14,14 @@ impl Manifest {
         self.main.get(name).map(|value| &*value as &str)
     }
 
+    /// Returns the relative paths listed in the `Class-Path` header.
+    pub fn class_path(&self) -> Vec<&str> {
+        match self.get("Class-Path") {
+            Some(value) => value.split_whitespace().collect(),
+            None => vec![],
+        }
+    }
+
     pub(crate) fn parse<R>(mut read: R) -> Fallible<Self>
     where
         R: Read,
@@ -83,4 +91,12 @@ mod tests {
         );
         assert_eq!("Test", manifest.get("Main-Class").unwrap());
     }
+
+    #[test]
+    fn parse_class_path() {
+        let input = "Manifest-Version: 1.0\nClass-Path: lib/a.jar b.jar\n\n";
+        let manifest = Manifest::parse(std::io::Cursor::new(input)).unwrap();
+
+        assert_eq!(vec!["lib/a.jar", "b.jar"], manifest.class_path());
+    }
 }
@@ -1,3 +1,4 @@
+use std::ffi::CString;
 use std::fmt;
 use std::ptr;
 use std::sync::Once;
@@ -28,6 +29,7 @@ pub enum RelocMode {
 
 static INIT_NATIVE_TARGET: Once = Once::new();
 static INIT_NATIVE_ASM_PRINTER: Once = Once::new();
+static INIT_ALL_TARGETS: Once = Once::new();
 
 fn init_native_target() {
     INIT_NATIVE_TARGET.call_once(|| {
@@ -53,7 +55,17 @@ fn init_native_asm_printer() {
     });
 }
 
+fn init_all_targets() {
+    INIT_ALL_TARGETS.call_once(|| unsafe {
+        llvm_sys::target::LLVM_InitializeAllTargetInfos();
+        llvm_sys::target::LLVM_InitializeAllTargets();
+        llvm_sys::target::LLVM_InitializeAllTargetMCs();
+        llvm_sys::target::LLVM_InitializeAllAsmPrinters();
+    });
+}
+
 pub struct TargetMachineBuilder {
+    triple: Option<CString>,
     level: LLVMCodeGenOptLevel,
     reloc: LLVMRelocMode,
     code_model: LLVMCodeModel,
@@ -66,12 +78,22 @@ impl TargetMachineBuilder {
         let code_model = LLVMCodeModel::LLVMCodeModelDefault;
 
         TargetMachineBuilder {
+            triple: None,
             level,
             reloc,
             code_model,
         }
     }
 
+    /// Targets the given triple instead of the host's default triple.
+    pub fn set_triple(&mut self, triple: &str) -> Result<(), Error> {
+        let triple = CString::new(triple).map_err(|_| Error {
+            message: format!("invalid target triple {:?}", triple),
+        })?;
+        self.triple = Some(triple);
+        Ok(())
+    }
+
     pub fn set_opt_level(&mut self, level: OptLevel) {
         self.level = match level {
             OptLevel::None => LLVMCodeGenOptLevel::LLVMCodeGenLevelNone,
@@ -94,9 +116,22 @@ impl TargetMachineBuilder {
         init_native_target();
         init_native_asm_printer();
 
+        let default_triple;
+        let target_triple = match self.triple {
+            Some(ref triple) => {
+                init_all_targets();
+                triple.as_ptr()
+            }
+            None => {
+                default_triple = Message {
+                    inner: unsafe { LLVMGetDefaultTargetTriple() },
+                };
+                default_triple.inner as *const c_char
+            }
+        };
+
         let llref;
         unsafe {
-            let target_triple = LLVMGetDefaultTargetTriple();
             let mut target = ptr::null_mut();
             let mut msg_ptr = ptr::null_mut();
             let code = LLVMGetTargetFromTriple(
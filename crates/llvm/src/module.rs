@@ -2,6 +2,7 @@ use std::ffi::CStr;
 use std::ptr;
 
 use libc::c_char;
+use llvm_sys::analysis::*;
 use llvm_sys::bit_writer::*;
 use llvm_sys::core::*;
 use llvm_sys::ir_reader::*;
@@ -10,6 +11,7 @@ use llvm_sys::prelude::*;
 
 use crate::buffer::MemoryBuffer;
 use crate::error::Error;
+use crate::message::Message;
 
 pub struct Module {
     pub(crate) llref: LLVMModuleRef,
@@ -77,6 +79,25 @@ impl Module {
         }
     }
 
+    /// Checks that the module is well-formed, e.g. after running passes on it.
+    pub fn verify(&self) -> Result<(), Error> {
+        unsafe {
+            let mut msg_ptr = ptr::null_mut();
+            let code = LLVMVerifyModule(
+                self.llref,
+                LLVMVerifierFailureAction::LLVMReturnStatusAction,
+                &mut msg_ptr as *mut *mut c_char,
+            );
+            let result = if code == 0 {
+                Ok(())
+            } else {
+                Err(Error::from_ptr(msg_ptr))
+            };
+            LLVMDisposeMessage(msg_ptr);
+            result
+        }
+    }
+
     pub fn to_bitcode(&self) -> MemoryBuffer {
         let llref;
         unsafe {
@@ -84,6 +105,14 @@ impl Module {
         }
         MemoryBuffer { llref }
     }
+
+    pub fn to_ir(&self) -> Message {
+        let inner;
+        unsafe {
+            inner = LLVMPrintModuleToString(self.llref);
+        }
+        Message { inner }
+    }
 }
 
 impl Drop for Module {
@@ -41,6 +41,18 @@ impl PassManagerBuilder {
         }
     }
 
+    /// Builds the passes for a module that is yet to be linked with others.
+    /// Unlike `build`, this leaves out the link-time passes, which assume
+    /// that the module is the whole program.
+    pub fn build_for_module(self) -> PassManager {
+        let llref;
+        unsafe {
+            llref = LLVMCreatePassManager();
+            LLVMPassManagerBuilderPopulateModulePassManager(self.llref, llref);
+        }
+        PassManager { llref }
+    }
+
     pub fn build(self) -> PassManager {
         let llref;
         unsafe {
@@ -3,6 +3,7 @@ use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+use std::str::Utf8Error;
 
 use bytes::Bytes;
 
@@ -14,10 +15,21 @@ impl StrBuf {
         StrBuf(string::String::from_str(s))
     }
 
+    /// Wraps the bytes without copying them, after checking that they are valid utf8.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, Utf8Error> {
+        std::str::from_utf8(&bytes)?;
+        // SAFETY: The bytes have just been validated.
+        Ok(unsafe { Self::from_utf8_unchecked(bytes) })
+    }
+
     pub unsafe fn from_utf8_unchecked(bytes: Bytes) -> Self {
         StrBuf(string::String::from_utf8_unchecked(bytes))
     }
 
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.get_ref()
+    }
+
     pub fn str_ref(&self, s: &str) -> Self {
         let bytes = self.0.get_ref().slice_ref(s.as_bytes());
         unsafe { StrBuf::from_utf8_unchecked(bytes) }
@@ -80,3 +92,24 @@ impl Deref for StrBuf {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes_with_valid_utf8() {
+        let bytes = Bytes::from(&b"caf\xc3\xa9"[..]);
+        let string = StrBuf::from_bytes(bytes.clone()).unwrap();
+
+        assert_eq!("caf\u{e9}", &*string);
+        assert_eq!(&bytes[..], string.as_bytes());
+    }
+
+    #[test]
+    fn from_bytes_with_invalid_utf8() {
+        let err = StrBuf::from_bytes(Bytes::from(&b"caf\xc3"[..])).unwrap_err();
+
+        assert_eq!(3, err.valid_up_to());
+    }
+}
@@ -4,8 +4,13 @@
 
 #include "../lib/ref.h"
 #include "../lib/array.h"
+#include "../lib/utils.h"
 
 ref_t _Jrt_array_new(uint32_t count, uint64_t component_size) {
+    // the count is a java int, so values above INT32_MAX are negative
+    if (count > INT32_MAX) {
+        PANIC("java.lang.NegativeArraySizeException: %d\n", (int32_t)count);
+    }
     return array_new(count, component_size);
 }
 
@@ -16,3 +21,8 @@ uint32_t _Jrt_array_length(ref_t ref) {
 void *_Jrt_array_element_ptr(ref_t ref) {
     return ARRAY_DATA_PTR(ref, void);
 }
+
+void _Jrt_array_store_check(ref_t array, ref_t value) {
+    // arrays don't record their component class yet, so every
+    // value is accepted until that information is available
+}
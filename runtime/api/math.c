@@ -0,0 +1,47 @@
+#define _GNU_SOURCE 1
+#include <stdint.h>
+
+#include "../lib/utils.h"
+
+// Dividing the minimum value by -1 overflows, which Java defines to yield the
+// minimum value again (and a remainder of 0), but C leaves undefined.
+
+int32_t _Jrt_idiv(int32_t dividend, int32_t divisor) {
+    if (divisor == 0) {
+        PANIC("java.lang.ArithmeticException: / by zero\n");
+    }
+    if (divisor == -1) {
+        return (int32_t)(0u - (uint32_t)dividend);
+    }
+    return dividend / divisor;
+}
+
+int32_t _Jrt_irem(int32_t dividend, int32_t divisor) {
+    if (divisor == 0) {
+        PANIC("java.lang.ArithmeticException: / by zero\n");
+    }
+    if (divisor == -1) {
+        return 0;
+    }
+    return dividend % divisor;
+}
+
+int64_t _Jrt_ldiv(int64_t dividend, int64_t divisor) {
+    if (divisor == 0) {
+        PANIC("java.lang.ArithmeticException: / by zero\n");
+    }
+    if (divisor == -1) {
+        return (int64_t)(0u - (uint64_t)dividend);
+    }
+    return dividend / divisor;
+}
+
+int64_t _Jrt_lrem(int64_t dividend, int64_t divisor) {
+    if (divisor == 0) {
+        PANIC("java.lang.ArithmeticException: / by zero\n");
+    }
+    if (divisor == -1) {
+        return 0;
+    }
+    return dividend % divisor;
+}
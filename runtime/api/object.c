@@ -2,11 +2,16 @@
 #include <stdint.h>
 #include <stdlib.h>
 #include <memory.h>
+#include <pthread.h>
 
 #include "../lib/ref.h"
 #include "../lib/object.h"
 #include "../lib/monitor.h"
 
+__attribute__((weak)) void *_Jrt_alloc(size_t size) {
+    return malloc(size);
+}
+
 ref_t _Jrt_object_new(uint64_t size, void *vtable) {
     return object_new(size, vtable);
 }
@@ -41,3 +46,33 @@ void _Jrt_object_monitorexit(ref_t ref) {
     // TODO: ensure calling thread owns the monitor
     monitor_exit(monitor);
 }
+
+// Static synchronized methods lock the monitor of their class instead of a
+// receiver. Each class module holds a slot for it, which is filled on first use.
+static pthread_mutex_t class_monitor_lock = PTHREAD_MUTEX_INITIALIZER;
+
+static monitor_t *class_monitor(monitor_t **slot) {
+    monitor_t *monitor = __atomic_load_n(slot, __ATOMIC_ACQUIRE);
+    if (monitor == NULL) {
+        ensure(pthread_mutex_lock(&class_monitor_lock));
+        monitor = *slot;
+        if (monitor == NULL) {
+            monitor = malloc(sizeof(monitor_t));
+            if (monitor == NULL) {
+                PANIC("java.lang.OutOfMemoryError: failed to allocate a class monitor\n");
+            }
+            monitor_init(monitor);
+            __atomic_store_n(slot, monitor, __ATOMIC_RELEASE);
+        }
+        ensure(pthread_mutex_unlock(&class_monitor_lock));
+    }
+    return monitor;
+}
+
+void _Jrt_class_monitorenter(monitor_t **slot) {
+    monitor_enter(class_monitor(slot));
+}
+
+void _Jrt_class_monitorexit(monitor_t **slot) {
+    monitor_exit(class_monitor(slot));
+}
@@ -27,27 +27,32 @@ ref_t _ZN4java4lang6System3outE = {
     .vtable = &VTABLE_PRINTSTREAM
 };
 
+// marker interfaces have neither methods nor superinterfaces
+struct ref_itable_base _ZTVN4java2io12SerializableE;
+
+struct ref_itable_base _ZTVN4java4lang9CloneableE;
+
 struct ref_vtable_base _ZTVN4java4lang13StringBuilderE;
 
 struct ref_vtable_base _ZTVN4java4lang24IllegalArgumentExceptionE;
 
-void _ZN4java4lang13StringBuilder4initIu9J8cc45093EEvv(ref_t _this) {
+void _ZN4java4lang13StringBuilder4initIu9J81d50672EEvv(ref_t _this) {
     trap_unimplemented("java.lang.StringBuilder.<init>");
 }
 
-void _ZN4java4lang24IllegalArgumentException4initIu9Jffb6fc97EEvN4java4lang6StringE(ref_t _this, ref_t _string) {
+void _ZN4java4lang24IllegalArgumentException4initIu9J5123dca6EEvN4java4lang6StringE(ref_t _this, ref_t _string) {
     trap_unimplemented("java.lang.IllegalArgumentException.<init>");
 }
 
-ref_t _ZN4java4lang7Integer11toHexStringIu9Jab2e85aaEEN4java4lang6StringEi(int64_t _value) {
+ref_t _ZN4java4lang7Integer11toHexStringIu9J5452ac46EEN4java4lang6StringEi(int64_t _value) {
     trap_unimplemented("java.lang.Integer.toHexString");
     return REF_NULL;
 }
 
-uint32_t _ZN4java4lang5Float5isNaNIu9Jbff373faEEu7booleanf(float value) {
+uint32_t _ZN4java4lang5Float5isNaNIu9J4425465fEEu7booleanf(float value) {
     return isnan(value);
 }
 
-uint32_t _ZN4java4lang6Double5isNaNIu9J0cf9d461EEu7booleand(double value) {
+uint32_t _ZN4java4lang6Double5isNaNIu9Jeb319d25EEu7booleand(double value) {
     return isnan(value);
 }
@@ -9,6 +9,7 @@
 #include "ref.h"
 #include "extern.h"
 #include "object.h"
+#include "utils.h"
 
 #define ARRAY_BASE_PTR(ref) (OBJECT_DATA_PTR(ref, struct array_base))
 #define ARRAY_DATA_PTR(ref, typ) ((typ *)&ARRAY_BASE_PTR(ref)[1])
@@ -19,7 +20,12 @@ struct array_base {
 };
 
 static inline ref_t array_new(uint32_t length, uint64_t width) {
-    size_t data_size = sizeof(struct array_base) + length * width;
+    // the array data size must be computed without wrapping around,
+    // otherwise the allocation would be smaller than the array
+    if (width > 0 && length > (SIZE_MAX - sizeof(struct array_base)) / width) {
+        PANIC("java.lang.OutOfMemoryError: array of %u elements exceeds the address space\n", length);
+    }
+    size_t data_size = sizeof(struct array_base) + (size_t)length * width;
     ref_t ref = object_new(data_size, EXTERN_VTABLE_JAVA_LANG_OBJECT);
     ARRAY_BASE_PTR(ref)->length = length;
     ARRAY_BASE_PTR(ref)->width = width;
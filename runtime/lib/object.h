@@ -8,6 +8,7 @@
 
 #include "ref.h"
 #include "monitor.h"
+#include "utils.h"
 
 #define OBJECT_BASE_PTR(ref) ((struct object_base *)ref.object)
 #define OBJECT_DATA_PTR(ref, typ) ((typ *)&OBJECT_BASE_PTR(ref)[1])
@@ -16,12 +17,22 @@ struct object_base {
     monitor_t monitor;
 };
 
-static inline ref_t object_new(uint32_t data_size, void *vtable) {
+// Allocates the memory for new objects and arrays. Defaults to `malloc`,
+// but can be replaced by defining a non-weak `_Jrt_alloc` at link time.
+void *_Jrt_alloc(size_t size);
+
+static inline ref_t object_new(size_t data_size, void *vtable) {
+    if (data_size > SIZE_MAX - sizeof(struct object_base)) {
+        PANIC("java.lang.OutOfMemoryError: object size %zu exceeds the address space\n", data_size);
+    }
     size_t size = sizeof(struct object_base) + data_size;
     ref_t ref = {
-        .object = malloc(size),
+        .object = _Jrt_alloc(size),
         .vtable = vtable,
     };
+    if (ref.object == NULL) {
+        PANIC("java.lang.OutOfMemoryError: failed to allocate %zu bytes\n", size);
+    }
     monitor_init(&OBJECT_BASE_PTR(ref)->monitor);
     return ref;
 }
@@ -1,9 +1,10 @@
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::process;
 
 use classfile::{attrs, instructions::Instr, ClassFile};
-use failure::Fallible;
+use failure::{bail, Fallible};
 use structopt::StructOpt;
 
 fn format_constant(idx: u16, pool: &classfile::ConstantPool) -> String {
@@ -87,7 +88,7 @@ fn format_field_type(field_type: &classfile::descriptors::FieldType, out: &mut S
             out.push_str("[]");
         }
         FieldType::Object(ObjectType { ref class_name }) => {
-            out.push_str(class_name);
+            out.push_str(&class_name.replace('/', "."));
         }
     }
 }
@@ -152,11 +153,26 @@ fn compute_md5<P: AsRef<Path>>(path: P) -> Fallible<md5::Digest> {
     Ok(ctx.into())
 }
 
+fn verify_md5(digest: md5::Digest, expected: &str) -> Fallible<()> {
+    let actual = format!("{:x}", digest);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        bail!(
+            "MD5 checksum {} does not match expected {}",
+            actual,
+            expected
+        );
+    }
+    Ok(())
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "javapv")]
 struct Opt {
     #[structopt(parse(from_os_str))]
     input: PathBuf,
+    /// Fail unless the MD5 checksum of the input matches this hex digest
+    #[structopt(long = "verify-md5")]
+    verify_md5: Option<String>,
 }
 
 macro_rules! try_next {
@@ -173,6 +189,10 @@ macro_rules! try_next {
 
 fn analyze(opt: &Opt) -> Fallible<()> {
     let metadata = opt.input.metadata()?;
+    let digest = compute_md5(&opt.input)?;
+    if let Some(ref expected) = opt.verify_md5 {
+        verify_md5(digest, expected)?;
+    }
     let file = fs::File::open(&opt.input)?;
     let cf = ClassFile::parse(file)?;
 
@@ -182,7 +202,7 @@ fn analyze(opt: &Opt) -> Fallible<()> {
         chrono::DateTime::<chrono::Local>::from(metadata.modified()?).format("%d/%m/%Y"),
         metadata.len()
     );
-    println!("  MD5 checksum {:x}", compute_md5(&opt.input)?);
+    println!("  MD5 checksum {:x}", digest);
 
     let source_file = cf.attributes.get::<attrs::SourceFile>().unwrap();
 
@@ -201,7 +221,7 @@ fn analyze(opt: &Opt) -> Fallible<()> {
     println!("  major version: {}", cf.version.major);
 
     println!("Constant pool:");
-    for idx in cf.constant_pool.indices() {
+    for (idx, _) in cf.constant_pool.iter() {
         println!(
             "{:>5} = {}",
             format!("#{}", idx.into_u16()),
@@ -225,7 +245,7 @@ fn analyze(opt: &Opt) -> Fallible<()> {
         );
         println!("  {}", formatted_method);
 
-        if let Ok(code) = method.attributes.get::<attrs::Code>() {
+        if let Some(code) = method.code()? {
             let mut args_size = method.descriptor.params.len();
             let method_name = cf.constant_pool.get_utf8(method.name_index).unwrap();
             if &**method_name == "<init>" {
@@ -245,7 +265,7 @@ fn analyze(opt: &Opt) -> Fallible<()> {
                 );
             }
 
-            if let Ok(stack_map_table) = code.attributes.get::<attrs::StackMapTable>() {
+            if let Some(stack_map_table) = code.attributes.get_opt::<attrs::StackMapTable>()? {
                 println!(
                     "    StackMapTable: number_of_entries = {}",
                     stack_map_table.len()
@@ -268,5 +288,30 @@ fn analyze(opt: &Opt) -> Fallible<()> {
 fn main() {
     let opt = Opt::from_args();
 
-    analyze(&opt).unwrap()
+    if let Err(err) = analyze(&opt) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_md5_matches() {
+        let digest = md5::compute(b"");
+        verify_md5(digest, "d41d8cd98f00b204e9800998ecf8427e").unwrap();
+        verify_md5(digest, "D41D8CD98F00B204E9800998ECF8427E").unwrap();
+    }
+
+    #[test]
+    fn verify_md5_mismatch() {
+        let err = verify_md5(md5::compute(b""), "00000000000000000000000000000000").unwrap_err();
+        assert_eq!(
+            "MD5 checksum d41d8cd98f00b204e9800998ecf8427e does not match expected \
+             00000000000000000000000000000000",
+            err.to_string()
+        );
+    }
 }